@@ -1,4 +1,9 @@
-use std::{borrow::Cow, collections::VecDeque, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt::{self, Display},
+    sync::Arc,
+};
 
 use fraction::{ConstZero, Fraction};
 use naviz_parser::{
@@ -9,14 +14,20 @@ use naviz_parser::{
             ZoneConfigConfig,
         },
     },
-    input::concrete::{InstructionGroup, Instructions, SetupInstruction, TimedInstruction},
+    input::{
+        concrete::{
+            self, AbsoluteTimeline, InstructionGroup, Instructions, SetupInstruction, Target,
+        },
+        location::LocationError,
+    },
 };
 use naviz_state::{
     config::{
-        AtomsConfig, Config, FontConfig, GridConfig, GridLegendConfig, HPosition, LegendConfig,
-        LegendEntry, LegendSection, LineConfig, TimeConfig, TrapConfig, VPosition, ZoneConfig,
+        AtomsConfig, CaptionConfig, Config, FontConfig, GridConfig, GridLegendConfig, HPosition,
+        LegendConfig, LegendEntry, LegendSection, LineConfig, TimeConfig, TrapConfig, VPosition,
+        ZoneConfig,
     },
-    state::{AtomState, State},
+    state::{AtomState, CaptionState, State},
 };
 use regex::Regex;
 
@@ -24,24 +35,37 @@ use crate::{
     color::Color,
     interpolator::{
         Constant, ConstantJerkFixedAverageVelocity, ConstantJerkFixedMaxVelocity,
-        ConstantTransitionPoint, Diagonal, DurationCalculable, MaxVelocity, Triangle,
+        ConstantTransitionPoint, Diagonal, DurationCalculable, Linear, MaxVelocity, Triangle,
     },
     position::Position,
     timeline::{Time, Timeline},
     to_float::ToFloat,
 };
 
+/// A [TimedInstruction][concrete::TimedInstruction]
+/// with a [resolved][Instructions::resolve] position
+pub(crate) type TimedInstruction = concrete::TimedInstruction<(Fraction, Fraction)>;
+
 /// The timelines for a single atom
 pub struct AtomTimelines {
     position: Timeline<(), Position, f32, Diagonal<ConstantJerkFixedAverageVelocity>>,
     overlay_color: Timeline<(), Color, f32, Triangle>,
     size: Timeline<(), f32, f32, Triangle>,
     shuttling: Timeline<ConstantTransitionPoint, bool, f32, Constant>,
+    opacity: Timeline<(), f32, f32, Linear>,
+    highlight: Timeline<(), Highlight, (), Constant>,
 }
 
 impl AtomTimelines {
     /// Creates new AtomTimelines from the passed default values
-    pub fn new(position: Position, overlay_color: Color, size: f32, shuttling: bool) -> Self {
+    pub fn new(
+        position: Position,
+        overlay_color: Color,
+        size: f32,
+        shuttling: bool,
+        opacity: f32,
+        highlight: Highlight,
+    ) -> Self {
         Self {
             position: Timeline::new_with_interpolation(
                 position,
@@ -50,20 +74,32 @@ impl AtomTimelines {
             overlay_color: Timeline::new(overlay_color),
             size: Timeline::new(size),
             shuttling: Timeline::new(shuttling),
+            opacity: Timeline::new(opacity),
+            highlight: Timeline::new(highlight),
         }
     }
 
     /// Gets the values of these timelines at the passed time
-    pub fn get(&self, time: Time) -> (Position, Color, f32, bool) {
+    pub fn get(&self, time: Time) -> (Position, Color, f32, bool, f32, Highlight) {
         (
             self.position.get(time),
             self.overlay_color.get(time),
             self.size.get(time),
             self.shuttling.get(time),
+            self.opacity.get(time),
+            self.highlight.get(time),
         )
     }
 }
 
+/// The highlight of an atom:
+/// A `color` drawn over the atom and an `outline` (width and color) drawn around it, if any
+#[derive(Clone, Copy, Default)]
+pub struct Highlight {
+    pub color: Color,
+    pub outline: Option<(f32, Color)>,
+}
+
 /// An atom-state in the animator
 struct Atom {
     /// id of the atom
@@ -72,6 +108,97 @@ struct Atom {
     name: String,
     /// the timelines of the atom
     timelines: AtomTimelines,
+    /// whether the atom was lost (and can no longer be targeted)
+    lost: bool,
+    /// the highlights of the atom as `(start, end, highlight)` in order of their instructions
+    highlights: Vec<(Fraction, Fraction, Highlight)>,
+}
+
+impl Atom {
+    /// Creates a new [Atom] at the passed `position` with the passed initial `opacity`
+    fn new(id: &str, position: (Fraction, Fraction), opacity: f32, visual: &VisualConfig) -> Self {
+        Self {
+            id: id.to_string(),
+            name: get_name(&visual.atom.legend.name, id),
+            timelines: AtomTimelines::new(
+                position.into(),
+                Color::default(),
+                visual.atom.radius.f32(),
+                false,
+                opacity,
+                Highlight::default(),
+            ),
+            lost: false,
+            highlights: Vec::new(),
+        }
+    }
+
+    /// Adds the keyframes of the [highlights][Atom::highlights] to the timelines.
+    /// When highlights overlap, the one started last (or the shorter one if started together) wins.
+    fn resolve_highlights(&mut self) {
+        let mut boundaries: Vec<_> = self
+            .highlights
+            .iter()
+            .flat_map(|(start, end, _)| [*start, *end])
+            .collect();
+        boundaries.sort();
+        boundaries.dedup();
+
+        for time in boundaries {
+            let highlight = self
+                .highlights
+                .iter()
+                .filter(|(start, end, _)| *start <= time && time < *end)
+                // `max_by` returns the last maximum, so later instructions win ties
+                .max_by(|(s0, e0, _), (s1, e1, _)| s0.cmp(s1).then(e1.cmp(e0)))
+                .map(|(_, _, highlight)| *highlight)
+                .unwrap_or_default();
+            self.timelines.highlight.add((time.f32(), highlight));
+        }
+    }
+}
+
+/// A caption which is shown from `start` until `end`
+struct Caption {
+    text: String,
+    start: f32,
+    end: f32,
+    /// The position of the caption,
+    /// or [None] to place it at the edge set in the [VisualConfig]
+    position: Option<(f32, f32)>,
+}
+
+/// A problem in the input that was found while creating the [Animator].
+/// The [Animator] can still be used, but may not show what was intended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// Atoms targeted by a cz-operation form an interaction-cluster of the wrong size
+    ClusterSize {
+        /// The time the operation starts at
+        time: Fraction,
+        /// The number of qubits the operation acts on
+        expected: usize,
+        /// The ids of the atoms in the cluster
+        atoms: Vec<String>,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ClusterSize {
+                time,
+                expected,
+                atoms,
+            } => write!(
+                f,
+                "at time {time}: {}-qubit cz-operation found a cluster of {} atoms ({})",
+                expected,
+                atoms.len(),
+                atoms.join(", ")
+            ),
+        }
+    }
 }
 
 /// The animator.
@@ -81,43 +208,41 @@ struct Atom {
 /// and [Animator::config] to get the [Config].
 pub struct Animator {
     atoms: Vec<Atom>,
+    captions: Vec<Caption>,
     config: Arc<Config>,
 
     /// The total durations of the animations
     duration: Fraction,
 
+    /// Problems found in the input
+    warnings: Vec<Warning>,
+
     machine: MachineConfig,
     visual: VisualConfig,
 }
 
 impl Animator {
-    /// Creates the [Animator]: calculates the timelines and the [Config]
-    pub fn new(machine: MachineConfig, visual: VisualConfig, input: Instructions) -> Self {
+    /// Creates the [Animator]: calculates the timelines and the [Config].
+    ///
+    /// The positions of the `input` are [resolved][Instructions::resolve]
+    /// using the traps and zones of the `machine`;
+    /// returns a [LocationError] if a referenced trap or zone does not exist.
+    pub fn new(
+        machine: MachineConfig,
+        visual: VisualConfig,
+        input: Instructions,
+    ) -> Result<Self, LocationError> {
+        let input = input.resolve(&machine)?;
+
         // Create the atoms
         let mut atoms: Vec<_> = input
             .setup
             .iter()
             .map(|a| match a {
-                SetupInstruction::Atom { position, id } => Atom {
-                    id: id.clone(),
-                    name: get_name(&visual.atom.legend.name, id),
-                    timelines: AtomTimelines::new(
-                        (*position).into(),
-                        Color::default(),
-                        visual.atom.radius.f32(),
-                        false,
-                    ),
-                },
+                SetupInstruction::Atom { position, id } => Atom::new(id, *position, 1., &visual),
             })
             .collect();
 
-        // Convert the `Vec`s to `VecDeque`s to allow popping from front
-        let mut absolute_timeline: VecDeque<(_, VecDeque<_>)> = input
-            .instructions
-            .into_iter()
-            .map(|(t, i)| (t, i.into()))
-            .collect();
-
         // Extract the positions / coordinates
         let setup_positions = input.setup.iter().map(|a| match a {
             SetupInstruction::Atom { position, .. } => position,
@@ -133,76 +258,151 @@ impl Animator {
             setup_ys.clone().max().unwrap_or_default(),
         );
 
-        let mut duration_total = Fraction::ZERO;
+        let mut warnings = Vec::new();
+        let mut captions = Vec::new();
+        // The end of the last caption or highlight
+        let mut shown_until = Fraction::ZERO;
 
         // Animate the atoms
-        while let Some((time, mut relative_timeline)) = absolute_timeline.pop_front() {
-            if let Some((_, offset, group)) = relative_timeline.pop_front() {
-                let InstructionGroup {
-                    variable,
-                    instructions,
-                } = group;
-
-                // Duration of the group
-                let mut duration = Fraction::ZERO;
-                // Start time of the group
-                let start_time = time + offset;
-                let start_time_f32 = start_time.f32();
-
-                // The invariable duration (if not set to `variable`)
-                let invariable_duration = (!variable).then_some(()).and_then(|()| {
-                    instructions
-                        .iter()
-                        .map(|i| get_duration(i, &atoms, &machine, start_time))
-                        .max()
-                });
+        let duration_total = schedule(&input.instructions, |start_time, _, group| {
+            let InstructionGroup {
+                variable,
+                instructions,
+            } = group;
 
-                for instruction in instructions {
-                    // Duration of the current instruction (or the group if not `variable`)
-                    let current_duration = invariable_duration.unwrap_or_else(|| {
-                        get_duration(&instruction, &atoms, &machine, start_time)
-                    });
-                    let current_duration_f32 = current_duration.f32();
-                    // Update duration of group
-                    duration = duration.max(current_duration);
-
-                    // update extent
-                    if let Some(position) = get_position(&instruction) {
-                        content_extent.0 = content_extent.0.min(position.0);
-                        content_extent.1 = content_extent.1.min(position.1);
-                        content_extent.2 = content_extent.2.max(position.0);
-                        content_extent.3 = content_extent.3.max(position.1);
+            // Duration of the group
+            let mut duration = Fraction::ZERO;
+            let start_time_f32 = start_time.f32();
+            let start_position = |atoms: &[Atom], id: &str| {
+                atoms
+                    .iter()
+                    .find(|a| !a.lost && a.id == id)
+                    .map(|a| a.timelines.position.get(start_time_f32.into()))
+            };
+
+            // The duration of an instruction (the overridden duration if set)
+            let instruction_duration =
+                |atoms: &[Atom], (instruction, duration): &(TimedInstruction, Option<Fraction>)| {
+                    duration.unwrap_or_else(|| {
+                        get_duration(instruction, |id| start_position(atoms, id), &machine)
+                    })
+                };
+
+            // The invariable duration (if not set to `variable`)
+            let invariable_duration = (!variable).then_some(()).and_then(|()| {
+                instructions
+                    .iter()
+                    .map(|i| instruction_duration(&atoms, i))
+                    .max()
+            });
+
+            for timed in instructions {
+                let (instruction, _) = timed;
+                // Duration of the current instruction (or the group if not `variable`)
+                let current_duration =
+                    invariable_duration.unwrap_or_else(|| instruction_duration(&atoms, timed));
+                let current_duration_f32 = current_duration.f32();
+                // Update duration of group
+                duration = duration.max(current_duration);
+
+                // update extent
+                if let Some(position) = get_position(instruction) {
+                    content_extent.0 = content_extent.0.min(position.0);
+                    content_extent.1 = content_extent.1.min(position.1);
+                    content_extent.2 = content_extent.2.max(position.0);
+                    content_extent.3 = content_extent.3.max(position.1);
+                }
+
+                // Report and highlight interaction-clusters of the wrong size
+                if let Some((targets, qubits)) = cz_targets(instruction) {
+                    let invalid = invalid_config(&visual);
+                    for cluster in clusters(&atoms, targets, start_time, &machine) {
+                        if cluster.len() == qubits {
+                            continue;
+                        }
+                        for &idx in &cluster {
+                            add_operation(
+                                &mut atoms[idx].timelines,
+                                start_time_f32,
+                                current_duration_f32,
+                                &invalid,
+                                &visual,
+                            );
+                        }
+                        warnings.push(Warning::ClusterSize {
+                            time: start_time,
+                            expected: qubits,
+                            atoms: cluster.into_iter().map(|i| atoms[i].id.clone()).collect(),
+                        });
                     }
+                }
 
-                    targeted(&mut atoms, &instruction, start_time, &machine).for_each(|a| {
-                        insert_animation(
-                            &mut a.timelines,
-                            &instruction,
-                            start_time_f32,
-                            current_duration_f32,
-                            &visual,
-                        )
+                // Captions and highlights may be shown past the end of the other instructions
+                if let TimedInstruction::Caption { duration, .. }
+                | TimedInstruction::Highlight { duration, .. } = instruction
+                {
+                    shown_until = shown_until.max(start_time + *duration);
+                }
+
+                if let TimedInstruction::Caption {
+                    text,
+                    duration,
+                    position,
+                } = instruction
+                {
+                    captions.push(Caption {
+                        text: text.clone(),
+                        start: start_time_f32,
+                        end: (start_time + *duration).f32(),
+                        position: position.map(|(x, y)| (x.f32(), y.f32())),
                     });
                 }
 
-                let next_from_start = relative_timeline
-                    .front()
-                    .map(|(x, _, _)| *x)
-                    .unwrap_or_default();
-                // Update duration of whole animation by duration of group
-                duration_total = duration_total.max(start_time + duration);
-                let next_time = if next_from_start {
-                    start_time
-                } else {
-                    start_time + duration
-                };
-                let idx = absolute_timeline.binary_search_by_key(&&next_time, |(t, _)| t);
-                let idx = match idx {
-                    Ok(idx) => idx,
-                    Err(idx) => idx,
-                };
-                absolute_timeline.insert(idx, (next_time, relative_timeline));
+                // Spawned atoms are invisible until they are spawned
+                if let TimedInstruction::Spawn { position, id } = instruction {
+                    atoms.push(Atom::new(id, *position, 0., &visual));
+                }
+
+                targeted(&mut atoms, instruction, start_time, &machine).for_each(|a| {
+                    insert_animation(
+                        &mut a.timelines,
+                        instruction,
+                        start_time_f32,
+                        current_duration_f32,
+                        &visual,
+                    );
+                    if let TimedInstruction::Lose { .. } = instruction {
+                        a.lost = true;
+                    }
+                    if let TimedInstruction::Highlight {
+                        color,
+                        duration,
+                        outline,
+                        ..
+                    } = instruction
+                    {
+                        // Highlights with an outline keep the color of the atom itself
+                        let highlight = match outline {
+                            Some(width) => Highlight {
+                                color: Color::default(),
+                                outline: Some((width.f32(), (*color).into())),
+                            },
+                            None => Highlight {
+                                color: (*color).into(),
+                                outline: None,
+                            },
+                        };
+                        a.highlights
+                            .push((start_time, start_time + *duration, highlight));
+                    }
+                });
             }
+
+            duration
+        });
+
+        for atom in &mut atoms {
+            atom.resolve_highlights();
         }
 
         // Grow content extent to fit zones and traps
@@ -261,6 +461,23 @@ impl Animator {
                     ),
                 ]
                 .into_iter()
+                .chain({
+                    // Sort generic gates by their name for a stable order
+                    let mut gates: Vec<_> = visual.operation.config.gate.iter().collect();
+                    gates.sort_by_key(|(name, _)| *name);
+                    gates
+                        .into_iter()
+                        .map(|(_, config)| (&config.name, config.color))
+                })
+                .chain(
+                    [
+                        &visual.operation.config.measure,
+                        &visual.operation.config.invalid,
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .map(|config| (&config.name, config.color)),
+                )
                 .filter(|(name, _)| !name.is_empty())
                 .map(|(name, color)| LegendEntry {
                     text: name.clone(),
@@ -416,15 +633,31 @@ impl Animator {
                 },
                 display: visual.time.display,
             },
+            caption: CaptionConfig {
+                font: {
+                    let font = visual
+                        .caption
+                        .as_ref()
+                        .map(|caption| &caption.font)
+                        .unwrap_or(&visual.atom.legend.font);
+                    FontConfig {
+                        size: font.size.f32(),
+                        color: font.color.rgba(),
+                        family: font.family.clone(),
+                    }
+                },
+            },
         };
 
-        Self {
+        Ok(Self {
             atoms,
+            captions,
             config: Arc::new(config),
-            duration: duration_total,
+            duration: duration_total.max(shown_until),
+            warnings,
             machine,
             visual,
-        }
+        })
     }
 
     /// The calculated [Config]
@@ -437,6 +670,11 @@ impl Animator {
         self.duration
     }
 
+    /// The problems found in the input while creating this [Animator]
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
     /// Gets the [State] at the passed [Time]
     pub fn state(&self, time: Time) -> State {
         State {
@@ -448,28 +686,72 @@ impl Animator {
                          id: _,
                          name,
                          timelines,
+                         lost: _,
+                         highlights: _,
                      }| (timelines.get(time), name),
                 )
                 .map(
-                    |((position, overlay_color, size, shuttling), name)| AtomState {
-                        position: position.into(),
-                        size,
-                        color: overlay_color
-                            .over(&if shuttling {
-                                self.visual.atom.shuttling.color.into()
-                            } else {
-                                self.visual.atom.trapped.color.into()
-                            })
-                            .0,
-                        shuttle: shuttling,
-                        label: name.clone(),
+                    |((position, overlay_color, size, shuttling, opacity, highlight), name)| {
+                        AtomState {
+                            position: position.into(),
+                            size,
+                            color: highlight
+                                .color
+                                .over(&overlay_color.over(&if shuttling {
+                                    self.visual.atom.shuttling.color.into()
+                                } else {
+                                    self.visual.atom.trapped.color.into()
+                                }))
+                                .0,
+                            shuttle: shuttling,
+                            label: name.clone(),
+                            opacity,
+                            outline: highlight.outline.map(|(width, color)| (width, color.0)),
+                        }
                     },
                 )
                 .collect(),
             time: self.format_time(time),
+            captions: self.captions(time),
         }
     }
 
+    /// Gets the captions which are shown at the passed [Time].
+    /// Captions without a position are stacked from the top or bottom of the content.
+    fn captions(&self, time: Time) -> Vec<CaptionState> {
+        let ((left, top), (right, bottom)) = self.config.content_extent;
+        let skip = self.config.caption.font.size * 1.2;
+        let from_bottom = matches!(
+            self.visual
+                .caption
+                .as_ref()
+                .map(|caption| &caption.position),
+            Some(TopBottomPosition::Bottom)
+        );
+
+        let mut stacked = 0;
+        self.captions
+            .iter()
+            .filter(|caption| caption.start <= *time && *time < caption.end)
+            .map(|caption| {
+                let position = caption.position.unwrap_or_else(|| {
+                    let offset = skip * (stacked as f32 + 0.5);
+                    stacked += 1;
+                    let y = if from_bottom {
+                        bottom - offset
+                    } else {
+                        top + offset
+                    };
+                    ((left + right) / 2., y)
+                });
+                CaptionState {
+                    text: caption.text.clone(),
+                    position,
+                }
+            })
+            .collect()
+    }
+
     /// The background color
     pub fn background(&self) -> [u8; 4] {
         self.visual.viewport.color.rgba()
@@ -500,7 +782,10 @@ fn get_position(instruction: &TimedInstruction) -> Option<(Fraction, Fraction)>
         TimedInstruction::Load { position, .. } | TimedInstruction::Store { position, .. } => {
             *position
         }
-        TimedInstruction::Move { position, .. } => Some(*position),
+        TimedInstruction::Move { position, .. } | TimedInstruction::Spawn { position, .. } => {
+            Some(*position)
+        }
+        TimedInstruction::Caption { position, .. } => *position,
         _ => None,
     }
 }
@@ -536,9 +821,9 @@ fn targeted<'a>(
     enum Match<'a> {
         /// Match a single atom by ID
         Atom(&'a str),
-        /// Match multiple atoms by id or zones by config
+        /// Match multiple atoms by target or zones by config
         AtomsOrZones {
-            atoms: Vec<&'a str>,
+            targets: &'a [Target],
             zones: Vec<&'a naviz_parser::config::machine::ZoneConfig>,
         },
         /// Match by index
@@ -548,99 +833,270 @@ fn targeted<'a>(
         // Instructions that only target individual atoms
         TimedInstruction::Load { id, .. }
         | TimedInstruction::Store { id, .. }
-        | TimedInstruction::Move { id, .. } => Match::Atom(id),
+        | TimedInstruction::Move { id, .. }
+        | TimedInstruction::Spawn { id, .. }
+        | TimedInstruction::Lose { id } => Match::Atom(id),
         // Instructions that target arbitrary targets
-        TimedInstruction::Rz { targets, .. } | TimedInstruction::Ry { targets, .. } => {
-            Match::AtomsOrZones {
-                zones: targets
-                    .iter()
-                    .filter_map(|id| machine.zone.get(id))
-                    .collect(),
-                atoms: targets.iter().map(AsRef::as_ref).collect(),
-            }
-        }
-        // Instructions that target arbitrary targets and require interaction distance
-        TimedInstruction::Cz { targets, .. } => {
-            let zones: Vec<_> = targets
-                .iter()
-                .filter_map(|id| machine.zone.get(id))
-                .collect();
-
-            // Get the position for each atom (identified by index) that is targeted at the start_time
-            let in_zone: Vec<_> = atoms
-                .iter()
-                .enumerate()
-                .filter(|(_, a)| {
-                    targets.contains(&a.id)
-                        || zones.iter().any(|zone| is_in_zone(a, zone, start_time))
-                })
-                .map(|(idx, a)| (idx, a.timelines.position.get(start_time.f32().into())))
-                .collect();
-
-            // Generate the targeted atom indices using nested loop.
-            // Assuming that at any time only clusters of two atoms exist,
-            // at most all atoms will be added to this vector
-            // as no atom will be added multiple times in the below loop.
-            // Therefore, we preallocate this size
-            let mut targeted = Vec::with_capacity(in_zone.len());
-            for a in 0..in_zone.len() {
-                for b in (a + 1)..in_zone.len() {
-                    let (a, a_pos) = &in_zone[a];
-                    let (b, b_pos) = &in_zone[b];
-                    // Two atoms are close -> add both
-                    if is_close(a_pos, b_pos, machine.distance.interaction) {
-                        targeted.push(*a);
-                        targeted.push(*b);
-                    }
-                }
-            }
-            Match::Index(targeted)
+        TimedInstruction::Rz { targets, .. }
+        | TimedInstruction::Ry { targets, .. }
+        | TimedInstruction::Gate { targets, .. }
+        | TimedInstruction::Measure { targets }
+        | TimedInstruction::Highlight { targets, .. } => Match::AtomsOrZones {
+            zones: targeted_zones(targets, machine),
+            targets,
+        },
+        // Instructions that target clusters of atoms within the interaction distance
+        TimedInstruction::Cz { targets } => {
+            Match::Index(cluster_targets(atoms, targets, 2, start_time, machine))
         }
+        TimedInstruction::Mcz { qubits, targets } => Match::Index(cluster_targets(
+            atoms, targets, *qubits, start_time, machine,
+        )),
+        // Instructions that do not target any atoms
+        TimedInstruction::Caption { .. } => Match::Index(Vec::new()),
     };
     atoms
         .iter_mut()
         .enumerate()
+        // Lost atoms can no longer be targeted
+        .filter(|(_, a)| !a.lost)
         .filter(move |(idx, a)| match &m {
             Match::Atom(id) => &a.id == id,
-            Match::AtomsOrZones { atoms, zones } => {
-                atoms.contains(&&*a.id) || zones.iter().any(|zone| is_in_zone(a, zone, start_time))
+            Match::AtomsOrZones { targets, zones } => {
+                targets.iter().any(|t| t.matches(&a.id))
+                    || zones.iter().any(|zone| is_in_zone(a, zone, start_time))
             }
             Match::Index(indices) => indices.contains(idx),
         })
         .map(|(_, a)| a)
 }
 
-/// Gets the duration of the passed `instruction` when starting at the passed `time`,
-fn get_duration(
-    instruction: &TimedInstruction,
+/// Walks the [InstructionGroup]s of the passed `timeline` in order of their start times.
+/// Calls `group` with the start time, the indices into the [AbsoluteTimeline]
+/// and [RelativeTimeline][naviz_parser::input::concrete::RelativeTimeline],
+/// and the [InstructionGroup] itself,
+/// which returns the duration of the group.
+///
+/// Returns the time the last group ends at.
+pub(crate) fn schedule<'a>(
+    timeline: &'a AbsoluteTimeline<(Fraction, Fraction)>,
+    mut group: impl FnMut(
+        Fraction,
+        (usize, usize),
+        &'a InstructionGroup<(Fraction, Fraction)>,
+    ) -> Fraction,
+) -> Fraction {
+    // The queue of relative timelines: `(time, absolute index, next relative index)`
+    let mut queue: VecDeque<_> = timeline
+        .iter()
+        .enumerate()
+        .map(|(idx, (time, _))| (*time, idx, 0))
+        .collect();
+
+    let mut end = Fraction::ZERO;
+    while let Some((time, absolute, relative)) = queue.pop_front() {
+        let relative_timeline = &timeline[absolute].1;
+        if let Some((_, offset, instructions)) = relative_timeline.get(relative) {
+            // Start time of the group
+            let start_time = time + *offset;
+            let duration = group(start_time, (absolute, relative), instructions);
+            // Update duration of whole animation by duration of group
+            end = end.max(start_time + duration);
+
+            let next_from_start = relative_timeline
+                .get(relative + 1)
+                .map(|(x, _, _)| *x)
+                .unwrap_or_default();
+            let next_time = if next_from_start {
+                start_time
+            } else {
+                start_time + duration
+            };
+            let idx = queue.binary_search_by_key(&next_time, |(t, _, _)| *t);
+            let idx = match idx {
+                Ok(idx) => idx,
+                Err(idx) => idx,
+            };
+            queue.insert(idx, (next_time, absolute, relative + 1));
+        }
+    }
+    end
+}
+
+/// Gets the zones of the `machine` which are targeted by id in the passed `targets`
+fn targeted_zones<'a>(
+    targets: &[Target],
+    machine: &'a MachineConfig,
+) -> Vec<&'a naviz_parser::config::machine::ZoneConfig> {
+    targets
+        .iter()
+        .filter_map(|target| match target {
+            Target::Id(id) => machine.zone.get(id),
+            Target::Regex(_) => None,
+        })
+        .collect()
+}
+
+/// Gets the targets and number of qubits of the passed `instruction`
+/// if it is a cz-operation, or [None] otherwise.
+fn cz_targets(instruction: &TimedInstruction) -> Option<(&[Target], usize)> {
+    match instruction {
+        TimedInstruction::Cz { targets } => Some((targets, 2)),
+        TimedInstruction::Mcz { qubits, targets } => Some((targets, *qubits)),
+        _ => None,
+    }
+}
+
+/// Finds the interaction-clusters of the atoms targeted by the passed `targets`
+/// at the passed `time`,
+/// i.e., the groups of atoms (by index) which are connected by atoms
+/// that are at most the interaction distance apart.
+/// Atoms without any interaction-partner are only a cluster on their own
+/// if they are targeted by their id (i.e., not only by a regex or zone).
+fn clusters(
     atoms: &[Atom],
+    targets: &[Target],
+    time: Fraction,
     machine: &MachineConfig,
+) -> Vec<Vec<usize>> {
+    let zones = targeted_zones(targets, machine);
+
+    // Get the position for each atom (identified by index) that is targeted at the time
+    // and whether it is targeted by its id
+    let in_zone: Vec<_> = atoms
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| !a.lost)
+        .filter(|(_, a)| {
+            targets.iter().any(|t| t.matches(&a.id))
+                || zones.iter().any(|zone| is_in_zone(a, zone, time))
+        })
+        .map(|(idx, a)| {
+            let explicit = targets
+                .iter()
+                .any(|t| matches!(t, Target::Id(id) if *id == a.id));
+            (idx, a.timelines.position.get(time.f32().into()), explicit)
+        })
+        .collect();
+
+    // Find the connected components using a depth-first search
+    let mut visited = vec![false; in_zone.len()];
+    let mut clusters = Vec::new();
+    for start in 0..in_zone.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut cluster = Vec::new();
+        let mut stack = vec![start];
+        while let Some(current) = stack.pop() {
+            cluster.push(in_zone[current].0);
+            for next in 0..in_zone.len() {
+                if !visited[next]
+                    && is_close(
+                        &in_zone[current].1,
+                        &in_zone[next].1,
+                        machine.distance.interaction,
+                    )
+                {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        if cluster.len() > 1 || in_zone[start].2 {
+            cluster.sort_unstable();
+            clusters.push(cluster);
+        }
+    }
+    clusters
+}
+
+/// Gets the indices of all atoms in interaction-clusters (see [clusters])
+/// of exactly `qubits` atoms.
+fn cluster_targets(
+    atoms: &[Atom],
+    targets: &[Target],
+    qubits: usize,
     time: Fraction,
+    machine: &MachineConfig,
+) -> Vec<usize> {
+    clusters(atoms, targets, time, machine)
+        .into_iter()
+        .filter(|cluster| cluster.len() == qubits)
+        .flatten()
+        .collect()
+}
+
+/// Gets the duration of the passed `instruction`,
+/// where `start_position` gets the position of an atom (by id) when the instruction starts.
+pub(crate) fn get_duration<'a>(
+    instruction: &'a TimedInstruction,
+    start_position: impl FnOnce(&'a str) -> Option<Position>,
+    machine: &MachineConfig,
 ) -> Fraction {
     match instruction {
         TimedInstruction::Load { .. } => machine.time.load,
         TimedInstruction::Store { .. } => machine.time.store,
-        TimedInstruction::Move { position, id } => (|| {
-            let start = atoms
-                .iter()
-                .find(|a| &a.id == id)?
-                .timelines
-                .position
-                .get(time.f32().into());
-            let end = (position.0.f32(), position.1.f32());
-
-            Some(
+        TimedInstruction::Move { position, id } => start_position(id)
+            .map(|start| {
                 Diagonal(ConstantJerkFixedMaxVelocity::new_fixed(MaxVelocity(
                     machine.movement.max_speed.f32(),
                 )))
-                .duration((), start, Position { x: end.0, y: end.1 }),
-            )
-        })()
-        .map(Fraction::from)
-        .unwrap_or_default(),
+                .duration((), start, (*position).into())
+            })
+            .map(Fraction::from)
+            .unwrap_or_default(),
         TimedInstruction::Rz { .. } => machine.time.rz,
         TimedInstruction::Ry { .. } => machine.time.ry,
         TimedInstruction::Cz { .. } => machine.time.cz,
+        TimedInstruction::Mcz { qubits, .. } => machine
+            .time
+            .mcz
+            .get(qubits)
+            .copied()
+            .unwrap_or(machine.time.cz),
+        TimedInstruction::Gate { name, .. } => {
+            machine.time.gate.get(name).copied().unwrap_or_default()
+        }
+        TimedInstruction::Spawn { .. } | TimedInstruction::Lose { .. } => Fraction::ZERO,
+        TimedInstruction::Measure { .. } => machine.time.measure,
+        // Captions and highlights are shown alongside the other instructions
+        TimedInstruction::Caption { .. } | TimedInstruction::Highlight { .. } => Fraction::ZERO,
+    }
+}
+
+/// Adds an operation-animation with the passed `config` to the passed `timelines`
+fn add_operation(
+    timelines: &mut AtomTimelines,
+    time: f32,
+    duration: f32,
+    config: &OperationConfigConfigConfig,
+    visual: &VisualConfig,
+) {
+    timelines
+        .overlay_color
+        .add((time, duration, config.color.into()));
+    timelines
+        .size
+        .add((time, duration, config.radius.get(visual.atom.radius).f32()));
+}
+
+/// Gets the config to highlight atoms in interaction-clusters of the wrong size with.
+/// Defaults to a red [cz-config][naviz_parser::config::visual::OperationConfigConfig::cz]
+/// if no config was set.
+fn invalid_config(visual: &VisualConfig) -> Cow<OperationConfigConfigConfig> {
+    match &visual.operation.config.invalid {
+        Some(config) => Cow::Borrowed(config),
+        None => Cow::Owned(OperationConfigConfigConfig {
+            color: naviz_parser::common::color::Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            ..visual.operation.config.cz.clone()
+        }),
     }
 }
 
@@ -652,21 +1108,6 @@ fn insert_animation(
     duration: f32,
     visual: &VisualConfig,
 ) {
-    fn add_operation(
-        timelines: &mut AtomTimelines,
-        time: f32,
-        duration: f32,
-        config: &OperationConfigConfigConfig,
-        visual: &VisualConfig,
-    ) {
-        timelines
-            .overlay_color
-            .add((time, duration, config.color.into()));
-        timelines
-            .size
-            .add((time, duration, config.radius.get(visual.atom.radius).f32()));
-    }
-
     fn add_move(
         timelines: &mut AtomTimelines,
         time: f32,
@@ -726,7 +1167,7 @@ fn insert_animation(
                 visual,
             );
         }
-        TimedInstruction::Cz { .. } => {
+        TimedInstruction::Cz { .. } | TimedInstruction::Mcz { .. } => {
             add_operation(
                 timelines,
                 start_time,
@@ -735,6 +1176,37 @@ fn insert_animation(
                 visual,
             );
         }
+        TimedInstruction::Gate { name, .. } => {
+            // Gates without a config are not visualized
+            if let Some(config) = visual.operation.config.gate.get(name) {
+                add_operation(timelines, start_time, duration, config, visual);
+            }
+        }
+        TimedInstruction::Spawn { .. } => {
+            timelines.opacity.add((start_time, duration, 1.));
+        }
+        TimedInstruction::Lose { .. } => {
+            // Lost atoms fade out if configured, or disappear immediately otherwise
+            let fade = visual
+                .atom
+                .lost
+                .as_ref()
+                .map(|lost| lost.fade.f32())
+                .unwrap_or_default();
+            timelines.opacity.add((start_time, fade, 0.));
+        }
+        TimedInstruction::Measure { .. } => {
+            // Measurements without a config are not visualized
+            if let Some(config) = &visual.operation.config.measure {
+                add_operation(timelines, start_time, duration, config, visual);
+            }
+        }
+        TimedInstruction::Caption { .. } => {
+            // Captions do not target atoms
+        }
+        TimedInstruction::Highlight { .. } => {
+            // Highlights may overlap and are resolved per atom (see `Atom::resolve_highlights`)
+        }
     }
 }
 
@@ -758,3 +1230,205 @@ fn get_first_match_with_regex<'t, T>(input: &'t [(Regex, T)], id: &str) -> Optio
 fn get_first_match<'t, T>(input: &'t [(Regex, T)], id: &str) -> Option<&'t T> {
     get_first_match_with_regex(input, id).map(|(_, t)| t)
 }
+
+#[cfg(test)]
+mod test {
+    use naviz_parser::{config, input};
+    use naviz_repository::Repository;
+
+    use super::*;
+
+    fn machine() -> MachineConfig {
+        let input = "
+            name: \"Test\"
+            movement { max_speed: 100 }
+            time { load: 1 store: 1 ry: 1 rz: 1 cz: 1 mcz { 3: 2 } unit: \"us\" }
+            distance { interaction: 2 unit: \"um\" }
+            zone zone0 { from: (0, 0) to: (30, 10) }
+            trap trap0 { position: (0, 0) }
+        ";
+        let lexed = config::lexer::lex(input).expect("Failed to lex");
+        let parsed = config::parser::parse(&lexed).expect("Failed to parse");
+        let config: config::generic::Config = parsed.into();
+        config.try_into().expect("Failed to convert")
+    }
+
+    fn visual() -> VisualConfig {
+        Repository::empty()
+            .bundled_styles()
+            .expect("Failed to load bundled styles")
+            .get("tum")
+            .expect("Style does not exist")
+            .expect("Failed to load style")
+    }
+
+    fn animate(input: &str) -> Animator {
+        let lexed = input::lexer::lex(input).expect("Failed to lex");
+        let parsed = input::parser::parse(&lexed).expect("Failed to parse");
+        let instructions = Instructions::new(parsed).expect("Failed to convert");
+        Animator::new(machine(), visual(), instructions).expect("Failed to animate")
+    }
+
+    /// The colors of the atoms of the `animator` at the passed `time`
+    fn colors(animator: &Animator, time: f32) -> Vec<[u8; 4]> {
+        animator
+            .state(time.into())
+            .atoms
+            .into_iter()
+            .map(|atom| atom.color)
+            .collect()
+    }
+
+    /// The color of a trapped atom which has the passed `overlay` drawn over it
+    fn trapped_with(overlay: naviz_parser::common::color::Color) -> [u8; 4] {
+        Color::from(overlay)
+            .over(&visual().atom.trapped.color.into())
+            .0
+    }
+
+    #[test]
+    fn cz_clusters() {
+        let input = "
+            atom (0, 0) single
+            atom (10, 0) pair0
+            atom (11, 0) pair1
+            atom (20, 0) triple0
+            atom (21, 0) triple1
+            atom (22, 0) triple2
+            @0 cz ^.*$
+            @2 ccz ^.*$
+        ";
+        let animator = animate(input);
+        let visual = visual();
+        let trapped = Color::from(visual.atom.trapped.color).0;
+        let cz = trapped_with(visual.operation.config.cz.color);
+        let invalid = trapped_with(invalid_config(&visual).color);
+
+        // Only the pair is a 2-qubit cluster
+        assert_eq!(
+            colors(&animator, 0.5),
+            [trapped, cz, cz, invalid, invalid, invalid]
+        );
+        // Only the triple is a 3-qubit cluster
+        assert_eq!(
+            colors(&animator, 3.),
+            [trapped, invalid, invalid, cz, cz, cz]
+        );
+        // Single atoms are not part of any cluster and are not reported
+        assert_eq!(
+            animator.warnings(),
+            [
+                Warning::ClusterSize {
+                    time: Fraction::from(0),
+                    expected: 2,
+                    atoms: vec![
+                        "triple0".to_string(),
+                        "triple1".to_string(),
+                        "triple2".to_string()
+                    ],
+                },
+                Warning::ClusterSize {
+                    time: Fraction::from(2),
+                    expected: 3,
+                    atoms: vec!["pair0".to_string(), "pair1".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cz_out_of_range() {
+        let input = "
+            atom (0, 0) atom0
+            atom (10, 0) atom1
+            @0 cz {atom0, atom1}
+        ";
+        let animator = animate(input);
+        let invalid = trapped_with(invalid_config(&visual()).color);
+
+        // Both atoms are highlighted and reported as clusters of a single atom
+        assert_eq!(colors(&animator, 0.5), [invalid, invalid]);
+        assert_eq!(
+            animator.warnings(),
+            [
+                Warning::ClusterSize {
+                    time: Fraction::from(0),
+                    expected: 2,
+                    atoms: vec!["atom0".to_string()],
+                },
+                Warning::ClusterSize {
+                    time: Fraction::from(0),
+                    expected: 2,
+                    atoms: vec!["atom1".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn adjacent_highlights() {
+        let input = "
+            atom (0, 0) a
+            @0 highlight #ff0000 5 a
+            @5 highlight #00ff00 5 a
+        ";
+        let animator = animate(input);
+        let red = trapped_with("ff0000".parse().unwrap());
+        let green = trapped_with("00ff00".parse().unwrap());
+        let trapped = Color::from(visual().atom.trapped.color).0;
+
+        assert_eq!(colors(&animator, 2.), [red]);
+        assert_eq!(colors(&animator, 6.), [green]);
+        assert_eq!(colors(&animator, 8.), [green]);
+        assert_eq!(colors(&animator, 11.), [trapped]);
+        // Highlights also extend the animation
+        assert_eq!(animator.duration(), Fraction::from(10));
+    }
+
+    #[test]
+    fn nested_highlights() {
+        let input = "
+            atom (0, 0) a
+            @0 highlight #ff0000 10 a
+            @5 highlight #00ff00 2 a
+        ";
+        let animator = animate(input);
+        let red = trapped_with("ff0000".parse().unwrap());
+        let green = trapped_with("00ff00".parse().unwrap());
+        let trapped = Color::from(visual().atom.trapped.color).0;
+
+        assert_eq!(colors(&animator, 2.), [red]);
+        // The inner highlight wins while it is active
+        assert_eq!(colors(&animator, 6.), [green]);
+        // The outer highlight is restored afterwards
+        assert_eq!(colors(&animator, 7.), [red]);
+        assert_eq!(colors(&animator, 8.), [red]);
+        assert_eq!(colors(&animator, 10.), [trapped]);
+    }
+
+    #[test]
+    fn caption_duration() {
+        // Captions alone still last for their duration
+        let animator = animate("@0 caption \"hi\" 5");
+        assert_eq!(animator.duration(), Fraction::from(5));
+
+        // Trailing captions are not cut off
+        let input = "
+            atom (0, 0) atom0
+            @0 rz 1 atom0
+            @+ caption \"Summary\" 3
+        ";
+        let animator = animate(input);
+        assert_eq!(animator.duration(), Fraction::from(4));
+        assert_eq!(animator.state(3.5.into()).captions.len(), 1);
+
+        // Captions ending before the other instructions do not extend the animation
+        let input = "
+            atom (0, 0) atom0
+            @0 caption \"Start\" 1
+            @0 rz 1 atom0
+            @+ rz 1 atom0
+        ";
+        assert_eq!(animate(input).duration(), Fraction::from(2));
+    }
+}
@@ -1,6 +1,7 @@
 pub mod animator;
 pub mod color;
 pub mod interpolator;
+pub mod lint;
 pub mod position;
 pub mod timeline;
 pub mod to_float;
@@ -0,0 +1,604 @@
+//! Semantic checks of [Instructions] against a [MachineConfig].
+//!
+//! Use [lint] to find problems which do not prevent the [Animator][crate::animator::Animator]
+//! from being created, but likely make it show something other than what was intended.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+};
+
+use fraction::Fraction;
+use naviz_parser::{
+    config::{machine::MachineConfig, position::Position, visual::VisualConfig},
+    input::concrete::{Instructions, SetupInstruction, Target, TimedInstruction},
+};
+
+use crate::{
+    animator::{get_duration, schedule},
+    to_float::ToFloat,
+};
+
+/// The location of an instruction in [Instructions]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InstructionIndex {
+    /// Index into [Instructions::setup]
+    Setup(usize),
+    /// Indices into [Instructions::instructions]:
+    /// the absolute timeline, the relative timeline, and the instructions of the group
+    Timed(usize, usize, usize),
+}
+
+impl Display for InstructionIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Setup(idx) => write!(f, "setup-instruction {idx}"),
+            Self::Timed(absolute, relative, idx) => {
+                write!(f, "timed instruction {absolute}.{relative}.{idx}")
+            }
+        }
+    }
+}
+
+/// A problem found by [lint]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    /// The instruction the problem was found at,
+    /// or [None] if the problem is in the directives
+    pub index: Option<InstructionIndex>,
+    /// The problem
+    pub kind: LintKind,
+}
+
+/// The kinds of problems found by [lint]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+    /// An instruction references an atom that does not exist
+    UnknownAtom(String),
+    /// An operation targets something that is neither an atom nor a zone
+    UnknownTarget(String),
+    /// An operation targets a regex which does not match any atom
+    UnmatchedRegex(String),
+    /// An atom with the same id was already created
+    DuplicateAtom(String),
+    /// A generic gate has no time in the machine (and takes no time)
+    UnknownGate(String),
+    /// A generic gate has no config in the style (and is not visualized)
+    UnstyledGate(String),
+    /// An atom is moved while it is not loaded
+    NotLoaded(String),
+    /// An atom is loaded while it is already loaded
+    AlreadyLoaded(String),
+    /// An atom is stored while it is already stored
+    AlreadyStored(String),
+    /// An atom is moved faster than the maximum speed of the machine allows
+    /// (i.e., its duration was overridden with a too short duration)
+    TooFast {
+        /// The id of the atom
+        atom: String,
+        /// The duration of the move
+        duration: Fraction,
+        /// The minimal duration of the move
+        minimum: Fraction,
+    },
+    /// An instruction acts on an atom while another instruction is still acting on it
+    Overlap {
+        /// The id of the atom
+        atom: String,
+        /// The instruction which is still acting on the atom
+        other: InstructionIndex,
+    },
+    /// The machine is not one of the `#target`s of the instructions
+    TargetMismatch {
+        /// The id of the machine
+        machine: String,
+        /// The `#target`s of the instructions
+        targets: Vec<String>,
+    },
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAtom(id) => write!(f, "unknown atom `{id}`"),
+            Self::UnknownTarget(id) => write!(f, "unknown atom or zone `{id}`"),
+            Self::UnmatchedRegex(regex) => write!(f, "`{regex}` does not match any atom"),
+            Self::DuplicateAtom(id) => write!(f, "atom `{id}` already exists"),
+            Self::UnknownGate(name) => write!(f, "gate `{name}` has no time in the machine"),
+            Self::UnstyledGate(name) => write!(f, "gate `{name}` has no config in the style"),
+            Self::NotLoaded(id) => write!(f, "atom `{id}` is moved while not loaded"),
+            Self::AlreadyLoaded(id) => write!(f, "atom `{id}` is already loaded"),
+            Self::AlreadyStored(id) => write!(f, "atom `{id}` is already stored"),
+            Self::TooFast {
+                atom,
+                duration,
+                minimum,
+            } => write!(
+                f,
+                "atom `{atom}` is moved in {} while the machine needs at least {}",
+                duration.f32(),
+                minimum.f32()
+            ),
+            Self::Overlap { atom, other } => {
+                write!(f, "atom `{atom}` is still busy with {other}")
+            }
+            Self::TargetMismatch { machine, targets } => write!(
+                f,
+                "machine `{machine}` is not targeted (targets: {})",
+                targets.join(", ")
+            ),
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.index {
+            Some(index) => write!(f, "{index}: {}", self.kind),
+            None => write!(f, "directives: {}", self.kind),
+        }
+    }
+}
+
+/// The state of an atom while walking the instructions
+struct AtomState {
+    /// The position at the end of the last movement
+    position: Position,
+    /// Whether the atom is loaded
+    loaded: bool,
+    /// The time the last instruction on this atom ends and that instruction
+    busy: Option<(Fraction, InstructionIndex)>,
+}
+
+/// Checks the passed `instructions` against the passed `machine`.
+/// The `instructions` have to be [resolved][Instructions::resolve] using the `machine`.
+/// When the `visual` config is passed,
+/// also checks that the generic gates are configured in it.
+/// When the id of the `machine` is passed,
+/// also checks that the machine is one of the `#target`s of the `instructions`.
+///
+/// Returns all found problems in order of the start time of their instructions.
+pub fn lint(
+    instructions: &Instructions<Position>,
+    machine: &MachineConfig,
+    visual: Option<&VisualConfig>,
+    machine_id: Option<&str>,
+) -> Vec<Lint> {
+    let mut lints = Vec::new();
+
+    if let Some(machine_id) = machine_id {
+        let targets = &instructions.directives.targets;
+        if !targets.is_empty() && !targets.iter().any(|t| t == machine_id) {
+            lints.push(Lint {
+                index: None,
+                kind: LintKind::TargetMismatch {
+                    machine: machine_id.to_string(),
+                    targets: targets.clone(),
+                },
+            });
+        }
+    }
+
+    let mut atoms = HashMap::new();
+    for (idx, instruction) in instructions.setup.iter().enumerate() {
+        match instruction {
+            SetupInstruction::Atom { position, id } => {
+                let state = AtomState {
+                    position: *position,
+                    loaded: false,
+                    busy: None,
+                };
+                if atoms.insert(id.as_str(), state).is_some() {
+                    lints.push(Lint {
+                        index: Some(InstructionIndex::Setup(idx)),
+                        kind: LintKind::DuplicateAtom(id.clone()),
+                    });
+                }
+            }
+        }
+    }
+
+    schedule(
+        &instructions.instructions,
+        |start_time, (absolute, relative), group| {
+            let default_duration = |instruction| {
+                get_duration(
+                    instruction,
+                    |id| atoms.get(id).map(|a: &AtomState| a.position.into()),
+                    machine,
+                )
+            };
+            // The default and the (possibly overridden) durations of the instructions
+            let durations: Vec<_> = group
+                .instructions
+                .iter()
+                .map(|(instruction, duration)| {
+                    let default = default_duration(instruction);
+                    (default, duration.unwrap_or(default))
+                })
+                .collect();
+            let group_duration = durations
+                .iter()
+                .map(|(_, duration)| *duration)
+                .max()
+                .unwrap_or_default();
+
+            for (idx, ((instruction, _), (default, duration))) in
+                group.instructions.iter().zip(durations).enumerate()
+            {
+                let index = InstructionIndex::Timed(absolute, relative, idx);
+                let duration = if group.variable {
+                    duration
+                } else {
+                    group_duration
+                };
+                let mut lint = |kind| {
+                    lints.push(Lint {
+                        index: Some(index),
+                        kind,
+                    })
+                };
+
+                // The default duration of a move is the minimal duration the machine allows
+                if let TimedInstruction::Move { id, .. } = instruction {
+                    if duration < default {
+                        lint(LintKind::TooFast {
+                            atom: id.clone(),
+                            duration,
+                            minimum: default,
+                        });
+                    }
+                }
+
+                // Unknown gates take no time and are not visualized
+                if let TimedInstruction::Gate { name, .. } = instruction {
+                    if !machine.time.gate.contains_key(name) {
+                        lint(LintKind::UnknownGate(name.clone()));
+                    }
+                    if visual.is_some_and(|visual| !visual.operation.config.gate.contains_key(name))
+                    {
+                        lint(LintKind::UnstyledGate(name.clone()));
+                    }
+                }
+
+                // Check the referenced atoms and get the atoms the instruction acts on
+                let targeted: Vec<&str> = match instruction {
+                    TimedInstruction::Spawn { position, id } => {
+                        if atoms.contains_key(id.as_str()) {
+                            lint(LintKind::DuplicateAtom(id.clone()));
+                            vec![]
+                        } else {
+                            let state = AtomState {
+                                position: *position,
+                                loaded: false,
+                                busy: None,
+                            };
+                            atoms.insert(id, state);
+                            vec![id]
+                        }
+                    }
+                    TimedInstruction::Load { id, .. }
+                    | TimedInstruction::Store { id, .. }
+                    | TimedInstruction::Move { id, .. }
+                    | TimedInstruction::Lose { id } => {
+                        if atoms.contains_key(id.as_str()) {
+                            vec![id]
+                        } else {
+                            lint(LintKind::UnknownAtom(id.clone()));
+                            vec![]
+                        }
+                    }
+                    TimedInstruction::Rz { targets, .. }
+                    | TimedInstruction::Ry { targets, .. }
+                    | TimedInstruction::Cz { targets }
+                    | TimedInstruction::Mcz { targets, .. }
+                    | TimedInstruction::Gate { targets, .. }
+                    | TimedInstruction::Measure { targets }
+                    | TimedInstruction::Highlight { targets, .. } => {
+                        let mut targeted = HashSet::new();
+                        for target in targets {
+                            let target = match target {
+                                Target::Id(id) => id,
+                                Target::Regex(regex) => {
+                                    let matched: Vec<_> =
+                                        atoms.keys().filter(|id| regex.is_match(id)).collect();
+                                    if matched.is_empty() {
+                                        lint(LintKind::UnmatchedRegex(regex.to_string()));
+                                    }
+                                    targeted.extend(matched);
+                                    continue;
+                                }
+                            };
+                            if let Some((id, _)) = atoms.get_key_value(target.as_str()) {
+                                targeted.insert(*id);
+                            } else if let Some(zone) = machine.zone.get(target) {
+                                targeted.extend(atoms.iter().filter_map(|(id, a)| {
+                                    let (x, y) = a.position;
+                                    (x >= zone.from.0
+                                        && y >= zone.from.1
+                                        && x <= zone.to.0
+                                        && y <= zone.to.1)
+                                        .then_some(*id)
+                                }));
+                            } else {
+                                lint(LintKind::UnknownTarget(target.clone()));
+                            }
+                        }
+                        if let TimedInstruction::Highlight { .. } = instruction {
+                            // Highlights only change the appearance of atoms and do not occupy them
+                            vec![]
+                        } else {
+                            let mut targeted: Vec<_> = targeted.into_iter().collect();
+                            targeted.sort_unstable();
+                            targeted
+                        }
+                    }
+                    TimedInstruction::Caption { .. } => vec![],
+                };
+
+                for id in targeted {
+                    let Some(atom) = atoms.get_mut(id) else {
+                        continue;
+                    };
+
+                    if let Some((end, other)) = atom.busy {
+                        if end > start_time {
+                            lint(LintKind::Overlap {
+                                atom: id.to_string(),
+                                other,
+                            });
+                        }
+                    }
+                    let end = start_time + duration;
+                    if atom.busy.is_none_or(|(busy, _)| busy <= end) {
+                        atom.busy = Some((end, index));
+                    }
+
+                    match instruction {
+                        TimedInstruction::Load { position, .. } => {
+                            if atom.loaded {
+                                lint(LintKind::AlreadyLoaded(id.to_string()));
+                            }
+                            atom.loaded = true;
+                            atom.position = position.unwrap_or(atom.position);
+                        }
+                        TimedInstruction::Store { position, .. } => {
+                            if !atom.loaded {
+                                lint(LintKind::AlreadyStored(id.to_string()));
+                            }
+                            atom.loaded = false;
+                            atom.position = position.unwrap_or(atom.position);
+                        }
+                        TimedInstruction::Move { position, .. } => {
+                            if !atom.loaded {
+                                lint(LintKind::NotLoaded(id.to_string()));
+                            }
+                            atom.position = *position;
+                        }
+                        _ => {}
+                    }
+                }
+
+                // Lost atoms no longer exist
+                if let TimedInstruction::Lose { id } = instruction {
+                    atoms.remove(id.as_str());
+                }
+            }
+
+            group_duration
+        },
+    );
+
+    lints
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use naviz_parser::{config, input};
+    use naviz_repository::Repository;
+
+    fn machine() -> MachineConfig {
+        let input = "
+            name: \"Test\"
+            movement { max_speed: 100 }
+            time { load: 1 store: 1 ry: 1 rz: 1 cz: 1 gate { h: 1 } unit: \"us\" }
+            distance { interaction: 2 unit: \"um\" }
+            zone zone0 { from: (0, 0) to: (10, 10) }
+            trap trap0 { position: (0, 0) }
+        ";
+        let lexed = config::lexer::lex(input).expect("Failed to lex");
+        let parsed = config::parser::parse(&lexed).expect("Failed to parse");
+        let config: config::generic::Config = parsed.into();
+        config.try_into().expect("Failed to convert")
+    }
+
+    /// The minimal duration of a move from `from` to `to` on the [machine]
+    fn minimum_move(from: (u64, u64), to: (u64, u64)) -> Fraction {
+        let instruction = TimedInstruction::Move {
+            position: (to.0.into(), to.1.into()),
+            id: "atom".to_string(),
+        };
+        let start = (Fraction::from(from.0), Fraction::from(from.1));
+        get_duration(&instruction, |_| Some(start.into()), &machine())
+    }
+
+    /// The bundled `tum` style with a config for the gate `h`
+    fn visual() -> VisualConfig {
+        let mut visual: VisualConfig = Repository::empty()
+            .bundled_styles()
+            .expect("Failed to load bundled styles")
+            .get("tum")
+            .expect("Style does not exist")
+            .expect("Failed to load style");
+        let h = visual.operation.config.cz.clone();
+        visual.operation.config.gate.insert("h".to_string(), h);
+        visual
+    }
+
+    fn lint_str(input: &str, machine_id: Option<&str>) -> Vec<Lint> {
+        let lexed = input::lexer::lex(input).expect("Failed to lex");
+        let parsed = input::parser::parse(&lexed).expect("Failed to parse");
+        let instructions = Instructions::new(parsed).expect("Failed to convert");
+        let machine = machine();
+        let instructions = instructions
+            .resolve(&machine)
+            .expect("Failed to resolve positions");
+        lint(&instructions, &machine, Some(&visual()), machine_id)
+    }
+
+    #[test]
+    fn valid() {
+        let input = "
+            #target test
+            atom (1, 1) atom0
+            atom (20, 20) atom1
+            @0 load atom0
+            @+ move (20, 1) atom0
+            @+ move trap0 + (1, 1) atom0
+            @+ store atom0
+            @+ rz 1 {atom0, zone0, atom1}
+            @+ lose atom0
+            @+ spawn (5, 5) atom0
+            @+ measure zone0
+            #group data {atom0, ^atom1$}
+            @+ rz 1 data
+            @+ ry 1 ^atom.*$
+            @+ load atom0
+            @+ move (9, 9) atom0 ~10
+            @= caption \"moving\" 10 (9, 9)
+            @= highlight #ff0000 10 0.5 data
+            @+ gate h atom1
+        ";
+        assert_eq!(lint_str(input, Some("test")), []);
+    }
+
+    #[test]
+    fn problems() {
+        let input = "
+            #target other
+            atom (1, 1) atom0
+            atom (2, 2) atom0
+            atom (20, 20) atom1
+            @0 move (3, 3) atom1
+            @1 store atom1
+            @2 load atom0
+            @2.5 load atom0
+            @4 rz 1 {atom2, zone1}
+            @5 lose atom1
+            @6 move (1, 1) atom1
+            @6 spawn (5, 5) atom0
+            @8 move (9, 9) atom0 ~0.01
+            @9 rz 1 {^atom.*$, ^other.*$}
+        ";
+        let kinds: Vec<_> = lint_str(input, Some("test"))
+            .into_iter()
+            .map(|l| (l.index, l.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    None,
+                    LintKind::TargetMismatch {
+                        machine: "test".to_string(),
+                        targets: vec!["other".to_string()],
+                    }
+                ),
+                (
+                    Some(InstructionIndex::Setup(1)),
+                    LintKind::DuplicateAtom("atom0".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(0, 0, 0)),
+                    LintKind::NotLoaded("atom1".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(1, 0, 0)),
+                    LintKind::AlreadyStored("atom1".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(3, 0, 0)),
+                    LintKind::Overlap {
+                        atom: "atom0".to_string(),
+                        other: InstructionIndex::Timed(2, 0, 0),
+                    }
+                ),
+                (
+                    Some(InstructionIndex::Timed(3, 0, 0)),
+                    LintKind::AlreadyLoaded("atom0".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(4, 0, 0)),
+                    LintKind::UnknownTarget("atom2".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(4, 0, 0)),
+                    LintKind::UnknownTarget("zone1".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(6, 0, 0)),
+                    LintKind::UnknownAtom("atom1".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(7, 0, 0)),
+                    LintKind::DuplicateAtom("atom0".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(8, 0, 0)),
+                    LintKind::TooFast {
+                        atom: "atom0".to_string(),
+                        duration: Fraction::new(1u64, 100u64),
+                        minimum: minimum_move((2, 2), (9, 9)),
+                    }
+                ),
+                (
+                    Some(InstructionIndex::Timed(9, 0, 0)),
+                    LintKind::UnmatchedRegex("^other.*$".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_gates() {
+        let input = "
+            atom (1, 1) atom0
+            @0 gate h atom0
+            @1 gate frobnicate 1 atom0
+        ";
+        let kinds: Vec<_> = lint_str(input, None)
+            .into_iter()
+            .map(|l| (l.index, l.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    Some(InstructionIndex::Timed(1, 0, 0)),
+                    LintKind::UnknownGate("frobnicate".to_string())
+                ),
+                (
+                    Some(InstructionIndex::Timed(1, 0, 0)),
+                    LintKind::UnstyledGate("frobnicate".to_string())
+                ),
+            ]
+        );
+
+        // The style is only checked if it is passed
+        let lexed = input::lexer::lex(input).expect("Failed to lex");
+        let parsed = input::parser::parse(&lexed).expect("Failed to parse");
+        let machine = machine();
+        let instructions = Instructions::new(parsed)
+            .expect("Failed to convert")
+            .resolve(&machine)
+            .expect("Failed to resolve positions");
+        assert_eq!(
+            lint(&instructions, &machine, None, None)
+                .into_iter()
+                .map(|l| l.kind)
+                .collect::<Vec<_>>(),
+            [LintKind::UnknownGate("frobnicate".to_string())]
+        );
+    }
+}
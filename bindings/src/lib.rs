@@ -1,20 +1,41 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    thread,
+    time::Duration,
+};
 
 use naviz_animator::animator::Animator;
 use naviz_import::{ImportFormat, ImportOptions};
-use naviz_parser::config::{machine::MachineConfig, visual::VisualConfig};
-use naviz_video::{VideoExport, VideoProgress};
+use naviz_parser::{
+    config::{machine::MachineConfig, visual::VisualConfig},
+    include::{self, Include, IncludeError, Resolver, SourceFile, Sources},
+    input::concrete::Instructions,
+};
+use naviz_renderer::{renderer::get_layout, svg::to_svg};
+use naviz_video::{
+    animation::{AnimationFormat, AnimationOptions},
+    backend::RenderBackend,
+    cancel::CancelHandle,
+    encoder::{EncoderPreset, EncoderSettings},
+    time::TimeOptions,
+    VideoExport, VideoProgress,
+};
 use pyo3::{
     create_exception,
     exceptions::{PyException, PyIOError, PyValueError},
     prelude::*,
-    types::{PyDict, PyString},
+    types::{PyAny, PyDict, PyString},
 };
 use serde_pyobject::{from_pyobject, to_pyobject};
 
 create_exception!(naviz, ParseError, PyException);
 create_exception!(naviz, VideoExportError, PyException);
 
+/// The name of sources passed as strings.
+/// Includes in these sources are resolved relative to the working directory.
+const INPUT_NAME: &str = "<input>";
+
 #[pyclass]
 struct Repository(naviz_repository::Repository);
 
@@ -44,29 +65,247 @@ impl Repository {
         ))
     }
 
-    /// Get a config entry by `id` from this repository
+    /// Get a config entry by `id` from this repository.
+    /// Includes of the entry are resolved,
+    /// as included paths are relative to the entry.
     pub fn get(&self, id: &str) -> PyResult<Option<String>> {
-        if let Some(content) = self.0.get_raw(id) {
-            let content =
-                content.map_err(|_| PyIOError::new_err("Failed to get entry from repository"))?;
-            return String::from_utf8(content.into_owned())
-                .map(Some)
-                .map_err(|_| PyIOError::new_err("Invalid UTF-8"));
+        if let Some(file) = self.0.get_source(id) {
+            let file =
+                file.map_err(|_| PyIOError::new_err("Failed to get entry from repository"))?;
+            let sources = self
+                .0
+                .sources(file)
+                .map_err(|e| ParseError::new_err(format!("Failed to include in entry: {e}")))?;
+            return Ok(Some(sources.flatten()));
         }
         Ok(None)
     }
 }
 
+/// Loads the [Instructions] from the `input`.
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+fn load_input(input: &str, import_options: Option<Bound<PyDict>>) -> PyResult<Instructions> {
+    if let Some(import_options) = import_options {
+        // Import the input
+        let import_options: ImportOptions = from_pyobject(import_options)
+            .map_err(|e| PyValueError::new_err(format!("Invalid input options: {e}")))?;
+        import_options
+            .import(input.as_bytes())
+            .map_err(|e| ParseError::new_err(format!("Failed to import input: {e}")))
+    } else {
+        // Load the input
+        let sources: Sources<include::InputFormat> = Sources::load(
+            SourceFile::new(INPUT_NAME, input),
+            &mut include::file_system,
+        )
+        .map_err(|e| include_error(e, "input"))?;
+        let parsed = naviz_parser::input::parser::parse(&sources.tokens()).map_err(|e| {
+            let diagnostic = sources.token_error(&e);
+            ParseError::new_err(format!("Failed to parse input: {diagnostic}"))
+        })?;
+        Instructions::new(parsed).map_err(|e| {
+            let mut message = format!("Failed to convert input to instructions: {}", e.innermost());
+            for diagnostic in e.diagnostics(&sources) {
+                message.push_str(&format!("\n{diagnostic}"));
+            }
+            ParseError::new_err(message)
+        })
+    }
+}
+
+/// Converts an [IncludeError] when loading the sources of the passed `kind` (used in error-messages)
+fn include_error(error: IncludeError, kind: &str) -> PyErr {
+    match error {
+        IncludeError::Lex(diagnostic) => {
+            ParseError::new_err(format!("Failed to lex {kind}: {diagnostic}"))
+        }
+        e => ParseError::new_err(format!("Failed to include in {kind}: {e}")),
+    }
+}
+
+/// Lexes and parses the `source` of a config of the passed `kind`
+/// (`machine` or `style`; used in error-messages and to include configs by id)
+fn load_config(source: &str, kind: &str) -> PyResult<naviz_parser::config::generic::Config> {
+    // Only load the repository when some config is included by id
+    let mut repository = None;
+    let mut resolver = |from: &str, include: &Include| match include {
+        Include::Path(_) => include::file_system(from, include),
+        Include::Id(_) => {
+            let repository = match &mut repository {
+                Some(repository) => repository,
+                None => {
+                    let empty = naviz_repository::Repository::empty();
+                    let loaded = match kind {
+                        "machine" => empty
+                            .bundled_machines()
+                            .and_then(naviz_repository::Repository::user_dir_machines),
+                        _ => empty
+                            .bundled_styles()
+                            .and_then(naviz_repository::Repository::user_dir_styles),
+                    };
+                    repository.insert(loaded.map_err(|e| e.to_string())?)
+                }
+            };
+            repository.resolver().resolve(from, include)
+        }
+    };
+    let sources: Sources<include::ConfigFormat> =
+        Sources::load(SourceFile::new(INPUT_NAME, source), &mut resolver)
+            .map_err(|e| include_error(e, kind))?;
+    let config = naviz_parser::config::parser::parse(&sources.tokens()).map_err(|e| {
+        let diagnostic = sources.token_error(&e);
+        ParseError::new_err(format!("Failed to parse {kind}: {diagnostic}"))
+    })?;
+    Ok(config.into())
+}
+
+/// Loads the [MachineConfig] from the `machine`-config
+fn load_machine(machine: &str) -> PyResult<MachineConfig> {
+    let machine = load_config(machine, "machine")?;
+    machine
+        .try_into()
+        .map_err(|e| ParseError::new_err(format!("Failed to convert machine to config: {e}")))
+}
+
+/// Loads the [VisualConfig] from the `style`-config
+fn load_style(style: &str) -> PyResult<VisualConfig> {
+    let style = load_config(style, "style")?;
+    style
+        .try_into()
+        .map_err(|e| ParseError::new_err(format!("Failed to convert style to config: {e}")))
+}
+
+/// Creates an [Animator] from the `input` using the `machine` and `style` configs.
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+fn create_animator(
+    input: &str,
+    machine: &str,
+    style: &str,
+    import_options: Option<Bound<PyDict>>,
+) -> PyResult<Animator> {
+    let input = load_input(input, import_options)?;
+    let machine = load_machine(machine)?;
+    let style = load_style(style)?;
+
+    // Create animator
+    Animator::new(machine, style, input)
+        .map_err(|e| ParseError::new_err(format!("Invalid positions in input: {e}")))
+}
+
+/// Parses the passed `backend`-name into a [RenderBackend].
+/// Uses [RenderBackend::Auto] if no backend is passed.
+fn parse_backend(backend: Option<Bound<PyString>>) -> PyResult<RenderBackend> {
+    Ok(backend
+        .map(from_pyobject)
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid backend: {e}")))?
+        .unwrap_or_default())
+}
+
+/// Parses the passed `time_options` into [TimeOptions].
+/// Uses the default [TimeOptions] (the whole animation at normal speed) if none are passed.
+fn parse_time_options(time_options: Option<Bound<PyDict>>) -> PyResult<TimeOptions> {
+    Ok(time_options
+        .map(from_pyobject)
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid time options: {e}")))?
+        .unwrap_or_default())
+}
+
+/// Parses the passed `encoder` into [EncoderSettings].
+/// The `encoder` can either be the name of an [EncoderPreset]
+/// or a dict of [EncoderSettings].
+/// Uses the default [EncoderSettings] if no `encoder` is passed.
+fn parse_encoder(encoder: Option<Bound<PyAny>>) -> PyResult<EncoderSettings> {
+    let Some(encoder) = encoder else {
+        return Ok(Default::default());
+    };
+    if let Ok(preset) = encoder.downcast::<PyString>() {
+        let preset: EncoderPreset = from_pyobject(preset.clone())
+            .map_err(|e| PyValueError::new_err(format!("Invalid encoder preset: {e}")))?;
+        Ok(preset.settings())
+    } else {
+        from_pyobject(encoder)
+            .map_err(|e| PyValueError::new_err(format!("Invalid encoder settings: {e}")))
+    }
+}
+
+/// Converts a [VideoExportError][naviz_video::error::VideoExportError]
+/// into a Python [VideoExportError]
+fn video_export_error(error: naviz_video::error::VideoExportError) -> PyErr {
+    VideoExportError::new_err(format!("Video export failed: {error}"))
+}
+
+/// Creates a [VideoExport] and runs the passed `export` on it in a separate thread.
+/// While waiting, Python signals are checked;
+/// if a signal raises an exception (e.g., a `KeyboardInterrupt`),
+/// the export is cancelled, its partial output is deleted, and the exception is returned.
+fn run_export(
+    py: Python,
+    animator: Animator,
+    resolution: (u32, u32),
+    fps: u32,
+    time: TimeOptions,
+    backend: RenderBackend,
+    export: impl FnOnce(
+            &mut VideoExport,
+            Sender<VideoProgress>,
+        ) -> Result<(), naviz_video::error::VideoExportError>
+        + Send
+        + 'static,
+) -> PyResult<()> {
+    let cancel = CancelHandle::new();
+    let export_cancel = cancel.clone();
+    let export = thread::spawn(move || {
+        let mut video = futures::executor::block_on(VideoExport::new_with_backend(
+            animator, resolution, fps, backend,
+        ))?;
+        video.set_cancel_handle(export_cancel);
+        video.set_time_options(time);
+        let (tx, _rx) = std::sync::mpsc::channel();
+        export(&mut video, tx)
+    });
+
+    while !export.is_finished() {
+        py.allow_threads(|| thread::sleep(Duration::from_millis(50)));
+        if let Err(e) = py.check_signals() {
+            cancel.cancel_and_delete_output();
+            let _ = export.join();
+            return Err(e);
+        }
+    }
+
+    export
+        .join()
+        .map_err(|_| VideoExportError::new_err("Video export crashed"))?
+        .map_err(video_export_error)
+}
+
 /// Export a video from the `input` to the `output`-location
 /// at the specified `resolution`
 /// with the specified framerate (`fps`)
 /// using the `machine` and `style` configs
 /// (use the [Repository] to get configs by id).
+/// The `time_options` can restrict the export to a window (`start` and `end`),
+/// set the seconds of video per unit of animation-time (`scale`),
+/// and hold the first and last frame (`hold_start` and `hold_end`, in seconds).
 /// When `import_options` are specified,
 /// the `input` is imported from the specified format.
+/// The `backend` (`"Auto"`, `"Gpu"`, or `"Software"`) selects how frames are rendered;
+/// by default, the GPU is used if available and rendering falls back to the CPU otherwise.
+/// The `encoder` is either the name of a preset
+/// (`"Default"`, `"Lossless"`, `"H265"`, `"Vp9"`, `"Av1"`, `"ProRes4444"`, or `"WebmAlpha"`)
+/// or a dict with the `codec`, `quality` (CRF), `pixel_format`, and `extra_args` for `ffmpeg`.
+/// When `transparent` is set, frames are rendered with a transparent background;
+/// the alpha is only kept by encoders supporting it (e.g., `"ProRes4444"` or `"WebmAlpha"`).
+/// The export can be cancelled using a `KeyboardInterrupt`, which deletes the partial output.
 #[pyfunction]
-#[pyo3(signature = (input, output, resolution, fps, machine, style, import_options=None))]
+#[pyo3(signature = (input, output, resolution, fps, machine, style, import_options=None, backend=None, time_options=None, encoder=None, transparent=false))]
+#[allow(clippy::too_many_arguments)]
 fn export_video(
+    py: Python,
     input: &str,
     output: &str,
     resolution: (u32, u32),
@@ -74,76 +313,250 @@ fn export_video(
     machine: &str,
     style: &str,
     import_options: Option<Bound<PyDict>>,
+    backend: Option<Bound<PyString>>,
+    time_options: Option<Bound<PyDict>>,
+    encoder: Option<Bound<PyAny>>,
+    transparent: bool,
 ) -> PyResult<()> {
-    let input = if let Some(import_options) = import_options {
-        // Import the input
-        let import_options: ImportOptions = from_pyobject(import_options)
-            .map_err(|e| PyValueError::new_err(format!("Invalid input options: {e}")))?;
-        import_options
-            .import(input.as_bytes())
-            .map_err(|e| ParseError::new_err(format!("Failed to import input: {e:?}")))?
-    } else {
-        // Load the input
-        let input = naviz_parser::input::lexer::lex(input)
-            .map_err(|_| ParseError::new_err("Failed to lex input"))?;
-        let input = naviz_parser::input::parser::parse(&input)
-            .map_err(|_| ParseError::new_err("Failed to parse input"))?;
-        naviz_parser::input::concrete::Instructions::new(input)
-            .map_err(|_| ParseError::new_err("Failed to convert input to instructions"))?
+    let time = parse_time_options(time_options)?;
+    let encoder = parse_encoder(encoder)?;
+    let backend = parse_backend(backend)?;
+    let animator = create_animator(input, machine, style, import_options)?;
+
+    let output = PathBuf::from(output);
+    run_export(
+        py,
+        animator,
+        resolution,
+        fps,
+        time,
+        backend,
+        move |video, tx| {
+            video.set_encoder_settings(encoder);
+            video.set_transparent_background(transparent);
+            video.export_video(&output, tx)
+        },
+    )
+}
+
+/// Export each frame of the `input` as a numbered PNG-image into the `output`-directory
+/// at the specified `resolution`
+/// with the specified framerate (`fps`)
+/// using the `machine` and `style` configs
+/// (use the [Repository] to get configs by id).
+/// Does not require `ffmpeg`.
+/// The `time_options` select the exported part and its speed (see [export_video]).
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+/// The `backend` selects how frames are rendered (see [export_video]).
+/// When `transparent` is set, frames are rendered with a transparent background.
+/// The export can be cancelled using a `KeyboardInterrupt`, which deletes the written frames.
+#[pyfunction]
+#[pyo3(signature = (input, output, resolution, fps, machine, style, import_options=None, backend=None, time_options=None, transparent=false))]
+#[allow(clippy::too_many_arguments)]
+fn export_image_sequence(
+    py: Python,
+    input: &str,
+    output: &str,
+    resolution: (u32, u32),
+    fps: u32,
+    machine: &str,
+    style: &str,
+    import_options: Option<Bound<PyDict>>,
+    backend: Option<Bound<PyString>>,
+    time_options: Option<Bound<PyDict>>,
+    transparent: bool,
+) -> PyResult<()> {
+    let time = parse_time_options(time_options)?;
+    let backend = parse_backend(backend)?;
+    let animator = create_animator(input, machine, style, import_options)?;
+
+    let output = PathBuf::from(output);
+    run_export(
+        py,
+        animator,
+        resolution,
+        fps,
+        time,
+        backend,
+        move |video, tx| {
+            video.set_transparent_background(transparent);
+            video.export_image_sequence(&output, tx)
+        },
+    )
+}
+
+/// Export an animated image (GIF or APNG) from the `input` to the `output`-location
+/// at the specified `resolution`
+/// with the specified framerate (`fps`)
+/// using the `machine` and `style` configs
+/// (use the [Repository] to get configs by id).
+/// Does not require `ffmpeg`.
+/// The `format` (`"Gif"` or `"Apng"`) is deduced from the extension of `output` if not specified.
+/// The `options` can set the `palette` (`colors` and quantization-`speed`) used for GIFs
+/// and a `frame_step` to only encode every n-th frame.
+/// The `time_options` select the exported part and its speed (see [export_video]).
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+/// The `backend` selects how frames are rendered (see [export_video]).
+/// When `transparent` is set, frames are rendered with a transparent background.
+/// The export can be cancelled using a `KeyboardInterrupt`, which deletes the partial output.
+#[pyfunction]
+#[pyo3(signature = (input, output, resolution, fps, machine, style, format=None, options=None, import_options=None, backend=None, time_options=None, transparent=false))]
+#[allow(clippy::too_many_arguments)]
+fn export_animation(
+    py: Python,
+    input: &str,
+    output: &str,
+    resolution: (u32, u32),
+    fps: u32,
+    machine: &str,
+    style: &str,
+    format: Option<Bound<PyString>>,
+    options: Option<Bound<PyDict>>,
+    import_options: Option<Bound<PyDict>>,
+    backend: Option<Bound<PyString>>,
+    time_options: Option<Bound<PyDict>>,
+    transparent: bool,
+) -> PyResult<()> {
+    let output = PathBuf::from(output);
+    let format: AnimationFormat = match format {
+        Some(format) => from_pyobject(format)
+            .map_err(|e| PyValueError::new_err(format!("Invalid format: {e}")))?,
+        None => output
+            .extension()
+            .and_then(|e| AnimationFormat::from_extension(&e.to_string_lossy()))
+            .ok_or_else(|| {
+                PyValueError::new_err("Cannot deduce format from output; specify a format")
+            })?,
     };
+    let options: AnimationOptions = options
+        .map(from_pyobject)
+        .transpose()
+        .map_err(|e| PyValueError::new_err(format!("Invalid options: {e}")))?
+        .unwrap_or_default();
+    let time = parse_time_options(time_options)?;
+    let backend = parse_backend(backend)?;
+    let animator = create_animator(input, machine, style, import_options)?;
 
-    // Machine config
-    let machine = naviz_parser::config::lexer::lex(machine)
-        .map_err(|_| ParseError::new_err("Failed to lex machine"))?;
-    let machine = naviz_parser::config::parser::parse(&machine)
-        .map_err(|_| ParseError::new_err("Failed to parse machine"))?;
-    let machine: naviz_parser::config::generic::Config = machine.into();
-    let machine: MachineConfig = machine
-        .try_into()
-        .map_err(|_| ParseError::new_err("Failed to convert machine to config"))?;
-
-    // Visual config
-    let style = naviz_parser::config::lexer::lex(style)
-        .map_err(|_| ParseError::new_err("Failed to lex style"))?;
-    let style = naviz_parser::config::parser::parse(&style)
-        .map_err(|_| ParseError::new_err("Failed to parse style"))?;
-    let style: naviz_parser::config::generic::Config = style.into();
-    let style: VisualConfig = style
-        .try_into()
-        .map_err(|_| ParseError::new_err("Failed to convert machine to config"))?;
+    run_export(
+        py,
+        animator,
+        resolution,
+        fps,
+        time,
+        backend,
+        move |video, tx| {
+            video.set_transparent_background(transparent);
+            video.export_animation(&output, format, options, tx)
+        },
+    )
+}
 
-    // Create animator
-    let animator = Animator::new(machine, style, input);
-
-    // Setup video export and start exporting
-    let mut video = futures::executor::block_on(VideoExport::new(animator, resolution, fps));
-    let (tx, rx) = std::sync::mpsc::channel();
-    video.export_video(Path::new(output), tx);
-
-    // Wait until export is done
-    for m in rx {
-        if let VideoProgress::Done(status) = m {
-            if status.success() {
-                // Successful export
-                return Ok(());
-            }
-            // Unsuccessful export
-            if let Some(code) = status.code() {
-                return Err(VideoExportError::new_err(format!(
-                    "ffmpeg returned error code {code}",
-                )));
-            } else {
-                return Err(VideoExportError::new_err("ffmpeg exited abnormally"));
-            }
-        }
-    }
+/// Export the single frame at the specified `time` from the `input` as a PNG-image
+/// to the `output`-location
+/// at the specified `resolution`
+/// using the `machine` and `style` configs
+/// (use the [Repository] to get configs by id).
+/// Does not require `ffmpeg`.
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+/// The `backend` selects how the frame is rendered (see [export_video]).
+/// When `transparent` is set, the frame is rendered with a transparent background.
+#[pyfunction]
+#[pyo3(signature = (input, output, resolution, time, machine, style, import_options=None, backend=None, transparent=false))]
+#[allow(clippy::too_many_arguments)]
+fn export_frame(
+    input: &str,
+    output: &str,
+    resolution: (u32, u32),
+    time: f32,
+    machine: &str,
+    style: &str,
+    import_options: Option<Bound<PyDict>>,
+    backend: Option<Bound<PyString>>,
+    transparent: bool,
+) -> PyResult<()> {
+    let backend = parse_backend(backend)?;
+    let animator = create_animator(input, machine, style, import_options)?;
 
-    // No `VideoProgress::Done` was received,
-    // but the `tx`-channel was dropped.
-    // Video export must have crashed.
-    Err(VideoExportError::new_err(
-        "video export crashed during export",
+    // The framerate is irrelevant when exporting a single frame
+    let mut video = futures::executor::block_on(VideoExport::new_with_backend(
+        animator, resolution, 1, backend,
     ))
+    .map_err(video_export_error)?;
+    video.set_transparent_background(transparent);
+    video
+        .export_frame(time, Path::new(output))
+        .map_err(video_export_error)
+}
+
+/// Export a single frame at the specified `time` from the `input` as an SVG to the `output`-location.
+/// The frame is laid out as a video at the specified `resolution` would be
+/// and uses the `machine` and `style` configs
+/// (use the [Repository] to get configs by id).
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+/// When `transparent` is set, the background is transparent.
+#[pyfunction]
+#[pyo3(signature = (input, output, resolution, time, machine, style, import_options=None, transparent=false))]
+#[allow(clippy::too_many_arguments)]
+fn export_svg(
+    input: &str,
+    output: &str,
+    resolution: (u32, u32),
+    time: f32,
+    machine: &str,
+    style: &str,
+    import_options: Option<Bound<PyDict>>,
+    transparent: bool,
+) -> PyResult<()> {
+    let animator = create_animator(input, machine, style, import_options)?;
+
+    let config = animator.config();
+    let state = animator.state(time.into());
+    let svg = to_svg(
+        &config,
+        &state,
+        get_layout(&config, resolution, false),
+        resolution,
+        if transparent {
+            [0, 0, 0, 0]
+        } else {
+            animator.background()
+        },
+    );
+
+    std::fs::write(output, svg).map_err(|e| PyIOError::new_err(format!("Failed to write svg: {e}")))
+}
+
+/// Check the `input` against the `machine`-config
+/// (use the [Repository] to get configs by id)
+/// and return the found problems
+/// (e.g., unknown atoms or zones, or instructions overlapping on the same atom).
+/// When the `machine_id` is specified,
+/// also checks that the machine is one of the `#target`s of the `input`.
+/// When `import_options` are specified,
+/// the `input` is imported from the specified format.
+#[pyfunction]
+#[pyo3(signature = (input, machine, machine_id=None, import_options=None))]
+fn lint(
+    input: &str,
+    machine: &str,
+    machine_id: Option<&str>,
+    import_options: Option<Bound<PyDict>>,
+) -> PyResult<Vec<String>> {
+    let input = load_input(input, import_options)?;
+    let machine = load_machine(machine)?;
+    let input = input
+        .resolve(&machine)
+        .map_err(|e| ParseError::new_err(format!("Invalid positions in input: {e}")))?;
+    Ok(
+        naviz_animator::lint::lint(&input, &machine, None, machine_id)
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    )
 }
 
 /// Get the default import-settings for the specified import-`format`
@@ -162,7 +575,14 @@ pub fn default_import_settings<'py>(
 #[pymodule]
 fn naviz(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(export_video, m)?)?;
+    m.add_function(wrap_pyfunction!(export_image_sequence, m)?)?;
+    m.add_function(wrap_pyfunction!(export_frame, m)?)?;
+    m.add_function(wrap_pyfunction!(export_animation, m)?)?;
+    m.add_function(wrap_pyfunction!(export_svg, m)?)?;
     m.add_function(wrap_pyfunction!(default_import_settings, m)?)?;
+    m.add_function(wrap_pyfunction!(lint, m)?)?;
     m.add_class::<Repository>()?;
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
+    m.add("VideoExportError", m.py().get_type::<VideoExportError>())?;
     Ok(())
 }
@@ -0,0 +1,140 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use naviz_import::ImportError;
+use naviz_parser::{
+    diagnostic::Diagnostic,
+    input::{concrete::ParseInstructionsError, location::LocationError},
+};
+use naviz_video::error::VideoExportError;
+
+/// A [Result][std::result::Result] pre-filled with [Error]
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The exit codes of the cli.
+/// Usage errors are reported by [clap] with exit code `2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Exit {
+    /// Everything went fine
+    Success = 0,
+    /// A file could not be read or the repository could not be loaded
+    Io = 1,
+    /// The input, machine, or style is invalid or could not be found
+    Invalid = 3,
+    /// The export failed
+    Export = 4,
+}
+
+impl From<Exit> for ExitCode {
+    fn from(value: Exit) -> Self {
+        ExitCode::from(value as u8)
+    }
+}
+
+/// An error that can occur in the cli
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read
+    Read(PathBuf, io::Error),
+    /// The repository could not be loaded
+    Repository(ConfigKind, naviz_repository::error::Error),
+    /// No config with the passed id or path exists
+    NotFound(ConfigKind, String),
+    /// A config is invalid
+    Config(ConfigKind, naviz_repository::error::Error),
+    /// The instructions are invalid
+    Input(InputError),
+    /// The instructions could not be imported
+    Import(ImportError),
+    /// Linting the instructions against the machine found this many problems
+    Lint(usize),
+    /// A file could not be written
+    Write(PathBuf, io::Error),
+    /// The export failed
+    Export(VideoExportError),
+}
+
+/// The kind of a config-file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigKind {
+    Machine,
+    Style,
+}
+
+/// An error to do with the instruction input
+#[derive(Debug)]
+pub enum InputError {
+    UTF8(std::str::Utf8Error),
+    Lex(Box<Diagnostic>),
+    Parse(Box<Diagnostic>),
+    Include(Box<Diagnostic>),
+    Convert(ParseInstructionsError, Vec<Diagnostic>),
+    /// A position references a trap or zone which does not exist in the machine
+    Location(LocationError),
+}
+
+impl Error {
+    /// The [Exit]-code the cli should exit with when encountering this [Error]
+    pub fn exit(&self) -> Exit {
+        match self {
+            Self::Read(..) | Self::Repository(..) => Exit::Io,
+            Self::NotFound(..)
+            | Self::Config(..)
+            | Self::Input(_)
+            | Self::Import(_)
+            | Self::Lint(_) => Exit::Invalid,
+            Self::Write(..) | Self::Export(_) => Exit::Export,
+        }
+    }
+}
+
+impl Display for ConfigKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Machine => f.write_str("machine"),
+            Self::Style => f.write_str("style"),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, e) => write!(f, "Failed to read {}: {e}", path.display()),
+            Self::Repository(kind, e) => write!(f, "Failed to load {kind} repository: {e}"),
+            Self::NotFound(kind, id) => {
+                write!(f, "No {kind} with id or path \"{id}\" found")
+            }
+            Self::Config(kind, e) => write!(f, "Invalid {kind}: {e}"),
+            Self::Input(InputError::UTF8(e)) => write!(f, "Invalid instructions: {e}"),
+            Self::Input(InputError::Lex(diagnostic)) => {
+                write!(f, "Failed to lex instructions: {diagnostic}")
+            }
+            Self::Input(InputError::Parse(diagnostic)) => {
+                write!(f, "Failed to parse instructions: {diagnostic}")
+            }
+            Self::Input(InputError::Include(diagnostic)) => {
+                write!(f, "Failed to include instructions: {diagnostic}")
+            }
+            Self::Input(InputError::Convert(e, diagnostics)) => {
+                write!(f, "Invalid instructions: {}", e.innermost())?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{diagnostic}")?;
+                }
+                Ok(())
+            }
+            Self::Input(InputError::Location(e)) => {
+                write!(f, "Invalid positions in instructions: {e}")
+            }
+            Self::Import(e) => write!(f, "Failed to import instructions: {e}"),
+            Self::Lint(count) => write!(f, "Found {count} problem(s) in the instructions"),
+            Self::Write(path, e) => write!(f, "Failed to write {}: {e}", path.display()),
+            Self::Export(e) => write!(f, "Failed to export: {e}"),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Loading of instructions and configs from files or the repository.
+
+use std::{fs, path::Path};
+
+use naviz_import::{ImportError, ImportFormat, ImportOptions, IMPORT_FORMATS};
+use naviz_parser::{
+    config::{generic::Config, machine::MachineConfig, visual::VisualConfig},
+    diagnostic::Diagnostic,
+    include::{self, Include, IncludeError, Resolver, SourceFile, Sources},
+    input::concrete::Instructions,
+};
+use naviz_repository::{error::Error as RepositoryError, Repository};
+
+use crate::error::{ConfigKind, Error, InputError, Result};
+
+/// The format of an instruction-file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// The `.naviz`-format
+    Naviz,
+    /// A format which is imported using [naviz_import]
+    Import(ImportFormat),
+}
+
+impl InputFormat {
+    /// Parses an [InputFormat] from its name as passed on the command-line
+    /// (`naviz` or the [name][ImportFormat::name] of an [ImportFormat] with spaces replaced by `-`).
+    pub fn parse(name: &str) -> Result<Self, String> {
+        if name == "naviz" {
+            return Ok(Self::Naviz);
+        }
+        IMPORT_FORMATS
+            .iter()
+            .find(|format| format.name().replace(' ', "-") == name)
+            .map(|format| Self::Import(*format))
+            .ok_or_else(|| {
+                format!(
+                    "unknown format (possible values: {})",
+                    Self::names().collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+
+    /// The names of all [InputFormat]s, as accepted by [InputFormat::parse]
+    fn names() -> impl Iterator<Item = String> {
+        std::iter::once("naviz".to_string())
+            .chain(IMPORT_FORMATS.iter().map(|f| f.name().replace(' ', "-")))
+    }
+
+    /// Deduces the [InputFormat] from the extension of the passed `path`.
+    /// Uses [InputFormat::Naviz] if the extension is unknown.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        IMPORT_FORMATS
+            .iter()
+            .find(|format| format.file_extensions().contains(&extension))
+            .map(|format| Self::Import(*format))
+            .unwrap_or(Self::Naviz)
+    }
+}
+
+/// Reads the file at `path`
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| Error::Read(path.to_owned(), e))
+}
+
+/// Loads the [Instructions] from the file at `path`.
+/// If no `format` is passed, it is deduced from the file-extension
+/// (see [InputFormat::from_path]).
+/// Imported formats use their default [ImportOptions].
+pub fn instructions(path: &Path, format: Option<InputFormat>) -> Result<Instructions> {
+    let data = read(path)?;
+    match format.unwrap_or_else(|| InputFormat::from_path(path)) {
+        InputFormat::Naviz => parse_instructions(&data, path).map_err(Error::Input),
+        InputFormat::Import(format) => ImportOptions::from(format)
+            .import(&data)
+            .map_err(|e| match e {
+                ImportError::MqtNqParse(diagnostic) => {
+                    ImportError::MqtNqParse(with_file(diagnostic, path))
+                }
+                e => e,
+            })
+            .map_err(Error::Import),
+    }
+}
+
+/// Parses [Instructions] from the passed `.naviz`-`data` read from `path`.
+/// Includes are read relative to `path`.
+fn parse_instructions(data: &[u8], path: &Path) -> Result<Instructions, InputError> {
+    let source = std::str::from_utf8(data).map_err(InputError::UTF8)?;
+    let sources: Sources<include::InputFormat> = Sources::load(
+        SourceFile::new(path.display().to_string(), source),
+        &mut include::file_system,
+    )
+    .map_err(|e| match e {
+        IncludeError::Lex(diagnostic) => InputError::Lex(diagnostic),
+        e => InputError::Include(e.into_diagnostic()),
+    })?;
+    let input = naviz_parser::input::parser::parse(&sources.tokens())
+        .map_err(|e| InputError::Parse(sources.token_error(&e).into()))?;
+    Instructions::new(input).map_err(|e| {
+        let diagnostics = e.diagnostics(&sources);
+        InputError::Convert(e, diagnostics)
+    })
+}
+
+/// Sets the file of the `diagnostic` to `path`
+fn with_file(mut diagnostic: Box<Diagnostic>, path: &Path) -> Box<Diagnostic> {
+    diagnostic.file = Some(path.display().to_string());
+    diagnostic
+}
+
+/// Loads the repository for the passed [ConfigKind]
+/// (bundled configs and configs from the user-dir).
+pub fn repository(kind: ConfigKind) -> Result<Repository> {
+    let repository = Repository::empty();
+    match kind {
+        ConfigKind::Machine => repository
+            .bundled_machines()
+            .and_then(Repository::user_dir_machines),
+        ConfigKind::Style => repository
+            .bundled_styles()
+            .and_then(Repository::user_dir_styles),
+    }
+    .map_err(|e| Error::Repository(kind, e))
+}
+
+/// Loads the raw [Config] of the passed [ConfigKind] from `id_or_path`.
+/// If `id_or_path` is an existing file, the file is loaded,
+/// otherwise the config with the id `id_or_path` is loaded from the [repository].
+/// Included paths are read relative to the including file,
+/// included ids are loaded from the [repository].
+fn config(kind: ConfigKind, id_or_path: &str) -> Result<Config> {
+    let path = Path::new(id_or_path);
+    // Only load the repository when needed
+    let mut repo = None;
+    let file = if path.is_file() {
+        let source = String::from_utf8(read(path)?)
+            .map_err(|e| Error::Config(kind, RepositoryError::UTF8Error(e.utf8_error())))?;
+        SourceFile::new(path.display().to_string(), source)
+    } else {
+        repo.insert(repository(kind)?)
+            .get_source(id_or_path)
+            .ok_or_else(|| Error::NotFound(kind, id_or_path.to_string()))?
+            .map_err(|e| Error::Config(kind, e))?
+    };
+    let mut resolver = |from: &str, include: &Include| match include {
+        Include::Path(_) => include::file_system(from, include),
+        Include::Id(_) => {
+            let repo = match &mut repo {
+                Some(repo) => repo,
+                None => repo.insert(repository(kind).map_err(|e| e.to_string())?),
+            };
+            repo.resolver().resolve(from, include)
+        }
+    };
+    let sources = Sources::load(file, &mut resolver)
+        .map_err(|e| Error::Config(kind, RepositoryError::include_error(e)))?;
+    naviz_repository::config_from_sources(&sources).map_err(|e| Error::Config(kind, e))
+}
+
+/// Loads the [MachineConfig] from the passed id or path (see [config])
+pub fn machine(id_or_path: &str) -> Result<MachineConfig> {
+    config(ConfigKind::Machine, id_or_path)?
+        .try_into()
+        .map_err(|e| {
+            Error::Config(
+                ConfigKind::Machine,
+                naviz_repository::error::Error::ConfigReadError(e),
+            )
+        })
+}
+
+/// Loads the [VisualConfig] from the passed id or path (see [config])
+pub fn style(id_or_path: &str) -> Result<VisualConfig> {
+    config(ConfigKind::Style, id_or_path)?
+        .try_into()
+        .map_err(|e| {
+            Error::Config(
+                ConfigKind::Style,
+                naviz_repository::error::Error::ConfigReadError(e),
+            )
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn input_format_names() {
+        assert_eq!(InputFormat::parse("naviz"), Ok(InputFormat::Naviz));
+        assert_eq!(
+            InputFormat::parse("mqt-na"),
+            Ok(InputFormat::Import(ImportFormat::MqtNa))
+        );
+        assert!(InputFormat::parse("unknown").is_err());
+    }
+
+    #[test]
+    fn input_format_from_path() {
+        assert_eq!(
+            InputFormat::from_path(Path::new("circuit.naviz")),
+            InputFormat::Naviz
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("circuit.na")),
+            InputFormat::Import(ImportFormat::MqtNa)
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("circuit")),
+            InputFormat::Naviz
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        let error = parse_instructions(b"@0 frobnicate atom0", Path::new("test.naviz"))
+            .expect_err("Unknown instruction");
+        assert_eq!(
+            Error::Input(error).to_string(),
+            "Invalid instructions: unknown instruction `frobnicate`"
+        );
+
+        let input = "name: \"Test\"\nmovement { max_speed: \"fast\" }";
+        let lexed = naviz_parser::config::lexer::lex(input).expect("Failed to lex");
+        let parsed = naviz_parser::config::parser::parse(&lexed).expect("Failed to parse");
+        let config: Config = parsed.into();
+        let error = MachineConfig::try_from(config).expect_err("Wrong type");
+        assert_eq!(
+            Error::Config(ConfigKind::Machine, RepositoryError::ConfigReadError(error)).to_string(),
+            "Invalid machine: wrong type: expected number at movement -> max_speed"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Headless command-line interface for NAViz.
+//!
+//! Renders exports without a GUI
+//! and allows checking inputs and configs (e.g., in Makefiles or CI pipelines).
+//! See [Exit] for the exit codes.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use error::{ConfigKind, Error, Exit, InputError, Result};
+use load::InputFormat;
+use naviz_animator::{animator::Animator, lint::lint};
+use render::RenderOptions;
+use stats::Statistics;
+
+mod error;
+mod load;
+mod render;
+mod stats;
+
+/// Visualize atom movements of neutral atom quantum computers
+#[derive(Debug, Parser)]
+#[command(name = "naviz", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render the instructions into a video, an animation, or images
+    Render {
+        #[command(flatten)]
+        input: AnimationInput,
+        /// The file or directory to export to
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        options: RenderOptions,
+    },
+    /// List the machines or styles available in the repository
+    List {
+        /// Which configs to list
+        kind: ListKind,
+    },
+    /// Check that the instructions, machine, or style are valid.
+    /// Also lints the instructions against the machine if both are passed
+    #[command(group(
+        ArgGroup::new("checked")
+            .args(["input", "machine", "style"])
+            .required(true)
+            .multiple(true)
+    ))]
+    Validate {
+        /// The instructions to check
+        input: Option<PathBuf>,
+        /// The format of the instructions.
+        /// Deduced from the file-extension if not set.
+        #[arg(short, long, value_parser = InputFormat::parse)]
+        format: Option<InputFormat>,
+        /// The machine to check (repository-id or path)
+        #[arg(short, long)]
+        machine: Option<String>,
+        /// The style to check (repository-id or path)
+        #[arg(short, long)]
+        style: Option<String>,
+    },
+    /// Print the duration and statistics of the instructions
+    Info {
+        #[command(flatten)]
+        input: AnimationInput,
+    },
+}
+
+/// The inputs required to create an [Animator]
+#[derive(Debug, clap::Args)]
+struct AnimationInput {
+    /// The instructions (`.naviz` or an importable format)
+    input: PathBuf,
+    /// The format of the instructions.
+    /// Deduced from the file-extension if not set.
+    #[arg(short, long, value_parser = InputFormat::parse)]
+    format: Option<InputFormat>,
+    /// The machine to use (repository-id or path)
+    #[arg(short, long)]
+    machine: String,
+    /// The style to use (repository-id or path)
+    #[arg(short, long)]
+    style: String,
+}
+
+/// The configs to list
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ListKind {
+    Machines,
+    Styles,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => Exit::Success.into(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            e.exit().into()
+        }
+    }
+}
+
+/// Runs the passed [Command]
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Render {
+            input,
+            output,
+            options,
+        } => {
+            let (_, animator) = input.load()?;
+            render::render(animator, &output, &options)
+        }
+        Command::List { kind } => {
+            let kind = match kind {
+                ListKind::Machines => ConfigKind::Machine,
+                ListKind::Styles => ConfigKind::Style,
+            };
+            let repository = load::repository(kind)?;
+            let mut entries: Vec<_> = repository.list().collect();
+            entries.sort_unstable();
+            let width = entries.iter().map(|(id, ..)| id.len()).max().unwrap_or(0);
+            for (id, name, removable) in entries {
+                let source = if removable { " (user)" } else { "" };
+                println!("{id:<width$}  {name}{source}");
+            }
+            Ok(())
+        }
+        Command::Validate {
+            input,
+            format,
+            machine,
+            style,
+        } => {
+            let instructions = input
+                .map(|input| load::instructions(&input, format))
+                .transpose()?;
+            let machine = machine
+                .map(|machine| load::machine(&machine).map(|config| (machine, config)))
+                .transpose()?;
+            let visual = style.map(|style| load::style(&style)).transpose()?;
+            if let (Some(instructions), Some((machine, config))) = (instructions, machine) {
+                // Only check the `#target`s when the machine is loaded from the repository
+                let id = (!Path::new(&machine).is_file()).then_some(machine.as_str());
+                let instructions = instructions
+                    .resolve(&config)
+                    .map_err(|e| Error::Input(InputError::Location(e)))?;
+                let lints = lint(&instructions, &config, visual.as_ref(), id);
+                for lint in &lints {
+                    eprintln!("Warning: {lint}");
+                }
+                if !lints.is_empty() {
+                    return Err(Error::Lint(lints.len()));
+                }
+            }
+            Ok(())
+        }
+        Command::Info { input } => {
+            let (instructions, animator) = input.load()?;
+            let duration = animator.duration().try_into().unwrap_or(f32::NAN);
+            print!("{}", Statistics::new(&instructions, duration));
+            Ok(())
+        }
+    }
+}
+
+impl AnimationInput {
+    /// Loads the instructions and configs and creates an [Animator] from them.
+    /// Also returns the loaded instructions.
+    fn load(&self) -> Result<(naviz_parser::input::concrete::Instructions, Animator)> {
+        let instructions = load::instructions(&self.input, self.format)?;
+        let machine = load::machine(&self.machine)?;
+        let style = load::style(&self.style)?;
+        let animator = Animator::new(machine, style, instructions.clone())
+            .map_err(|e| Error::Input(InputError::Location(e)))?;
+        for warning in animator.warnings() {
+            eprintln!("Warning: {warning}");
+        }
+        Ok((instructions, animator))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::{error::ErrorKind, CommandFactory};
+
+    use super::*;
+
+    #[test]
+    fn cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn validate_requires_something_to_check() {
+        assert_eq!(
+            Cli::try_parse_from(["naviz", "validate"])
+                .expect_err("Validated nothing")
+                .kind(),
+            ErrorKind::MissingRequiredArgument
+        );
+        for args in [
+            &["naviz", "validate", "input.naviz"][..],
+            &["naviz", "validate", "-m", "machine"],
+            &["naviz", "validate", "-s", "style", "-m", "machine"],
+        ] {
+            assert!(matches!(
+                Cli::try_parse_from(args),
+                Ok(Cli {
+                    command: Command::Validate { .. }
+                })
+            ));
+        }
+    }
+}
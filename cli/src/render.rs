@@ -0,0 +1,333 @@
+//! Rendering of an [Animator] into the different export-formats.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+use clap::{Args, ValueEnum};
+use naviz_animator::animator::Animator;
+use naviz_renderer::{renderer::get_layout, svg::to_svg};
+use naviz_video::{
+    animation::{AnimationFormat, AnimationOptions, PaletteOptions},
+    backend::RenderBackend,
+    encoder::{EncoderPreset, EncoderSettings},
+    time::TimeOptions,
+    VideoExport, VideoProgress,
+};
+
+use crate::error::{Error, Result};
+
+/// The kind of export to create
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportKind {
+    /// A video encoded by `ffmpeg`
+    Video,
+    /// A directory of numbered PNG-images
+    Frames,
+    /// An animated GIF or APNG
+    Animation,
+    /// A single frame as a PNG-image
+    Frame,
+    /// A single frame as an SVG
+    Svg,
+}
+
+impl ExportKind {
+    /// Deduces the [ExportKind] from the extension of the `output`-path:
+    /// `svg` exports an [ExportKind::Svg], `png` an [ExportKind::Frame],
+    /// `gif` and `apng` an [ExportKind::Animation],
+    /// no extension an [ExportKind::Frames]-directory
+    /// and any other extension an [ExportKind::Video].
+    pub fn from_path(output: &Path) -> Self {
+        match output
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .as_deref()
+        {
+            None => Self::Frames,
+            Some("svg") => Self::Svg,
+            Some("png") => Self::Frame,
+            Some("gif" | "apng") => Self::Animation,
+            Some(_) => Self::Video,
+        }
+    }
+}
+
+/// The backend to render with (see [RenderBackend])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// Render on the GPU if available, otherwise on the CPU
+    #[default]
+    Auto,
+    /// Always render on the GPU
+    Gpu,
+    /// Always render on the CPU
+    Software,
+}
+
+impl From<Backend> for RenderBackend {
+    fn from(value: Backend) -> Self {
+        match value {
+            Backend::Auto => RenderBackend::Auto,
+            Backend::Gpu => RenderBackend::Gpu,
+            Backend::Software => RenderBackend::Software,
+        }
+    }
+}
+
+/// The preset for the `ffmpeg`-encoder (see [EncoderPreset])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoder {
+    /// Choose the codec from the file-extension and convert to yuv420p
+    #[default]
+    Default,
+    /// Lossless FFV1 (use an mkv-container)
+    Lossless,
+    /// High-quality H.265
+    H265,
+    /// High-quality VP9 (use a webm-container)
+    Vp9,
+    /// High-quality AV1
+    Av1,
+    /// ProRes 4444 with alpha (use a mov-container)
+    Prores4444,
+    /// VP9 with alpha (use a webm-container)
+    WebmAlpha,
+}
+
+impl From<Encoder> for EncoderPreset {
+    fn from(value: Encoder) -> Self {
+        match value {
+            Encoder::Default => EncoderPreset::Default,
+            Encoder::Lossless => EncoderPreset::Lossless,
+            Encoder::H265 => EncoderPreset::H265,
+            Encoder::Vp9 => EncoderPreset::Vp9,
+            Encoder::Av1 => EncoderPreset::Av1,
+            Encoder::Prores4444 => EncoderPreset::ProRes4444,
+            Encoder::WebmAlpha => EncoderPreset::WebmAlpha,
+        }
+    }
+}
+
+/// Options for rendering
+#[derive(Debug, Clone, Args)]
+pub struct RenderOptions {
+    /// What to export.
+    /// Deduced from the extension of the output if not set
+    /// (`svg`: svg, `png`: frame, `gif`/`apng`: animation, none: frames, other: video)
+    #[arg(short, long)]
+    pub kind: Option<ExportKind>,
+    /// The resolution to render at
+    #[arg(short, long, default_value = "1920x1080", value_parser = parse_resolution)]
+    pub resolution: (u32, u32),
+    /// The framerate to render at
+    #[arg(long, default_value_t = 30)]
+    pub fps: u32,
+    /// The time of the frame to export (for single frames)
+    #[arg(short, long, default_value_t = 0.)]
+    pub time: f32,
+    /// The backend to render with
+    #[arg(short, long, value_enum, default_value_t)]
+    pub backend: Backend,
+    /// The time to start the export at
+    #[arg(long, default_value_t = 0.)]
+    pub start: f32,
+    /// The time to end the export at (end of the animation if not set)
+    #[arg(long)]
+    pub end: Option<f32>,
+    /// Seconds of video per unit of animation-time
+    #[arg(long, default_value_t = 1.)]
+    pub scale: f32,
+    /// Seconds to hold the first frame for
+    #[arg(long, default_value_t = 0.)]
+    pub hold_start: f32,
+    /// Seconds to hold the last frame for
+    #[arg(long, default_value_t = 0.)]
+    pub hold_end: f32,
+    /// Only encode every n-th frame (for animations)
+    #[arg(long, default_value_t = 1)]
+    pub frame_step: u32,
+    /// The maximum number of colors per frame (for GIFs)
+    #[arg(long, default_value_t = 256)]
+    pub colors: u16,
+    /// Render with a transparent background
+    /// (alpha is only kept by image-sequences, frames, animations, SVGs,
+    /// and videos with an alpha-capable encoder)
+    #[arg(long)]
+    pub transparent: bool,
+    /// The encoder preset (for videos)
+    #[arg(short, long, value_enum, default_value_t)]
+    pub encoder: Encoder,
+    /// The codec to encode with, overriding the preset (for videos)
+    #[arg(long)]
+    pub codec: Option<String>,
+    /// The constant rate factor to encode with, overriding the preset (for videos)
+    #[arg(long)]
+    pub crf: Option<u32>,
+    /// The pixel format to encode with, overriding the preset (for videos)
+    #[arg(long)]
+    pub pixel_format: Option<String>,
+    /// An additional argument for ffmpeg (for videos; can be repeated)
+    #[arg(long = "ffmpeg-arg", allow_hyphen_values = true)]
+    pub ffmpeg_args: Vec<String>,
+    /// Do not print progress
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+impl RenderOptions {
+    /// The [TimeOptions] selected by these options
+    fn time_options(&self) -> TimeOptions {
+        TimeOptions {
+            start: self.start,
+            end: self.end,
+            scale: self.scale,
+            hold_start: self.hold_start,
+            hold_end: self.hold_end,
+        }
+    }
+
+    /// The [EncoderSettings] selected by these options:
+    /// The settings of the preset, overridden by the explicitly passed values
+    fn encoder_settings(&self) -> EncoderSettings {
+        let mut settings = EncoderPreset::from(self.encoder).settings();
+        if let Some(codec) = &self.codec {
+            settings.codec = Some(codec.clone());
+        }
+        if let Some(crf) = self.crf {
+            settings.quality = Some(crf);
+        }
+        if let Some(pixel_format) = &self.pixel_format {
+            settings.pixel_format = Some(pixel_format.clone());
+        }
+        settings.extra_args.extend(self.ffmpeg_args.iter().cloned());
+        settings
+    }
+}
+
+/// Parses a resolution in the format `<width>x<height>`
+fn parse_resolution(resolution: &str) -> Result<(u32, u32), String> {
+    let (width, height) = resolution
+        .split_once('x')
+        .ok_or("expected format <width>x<height>")?;
+    let parse = |v: &str| v.trim().parse::<u32>().map_err(|e| e.to_string());
+    Ok((parse(width)?, parse(height)?))
+}
+
+/// Renders the passed [Animator] to `output` using the passed [RenderOptions]
+pub fn render(animator: Animator, output: &Path, options: &RenderOptions) -> Result<()> {
+    let kind = options
+        .kind
+        .unwrap_or_else(|| ExportKind::from_path(output));
+
+    if kind == ExportKind::Svg {
+        let config = animator.config();
+        let state = animator.state(options.time.into());
+        let svg = to_svg(
+            &config,
+            &state,
+            get_layout(&config, options.resolution, false),
+            options.resolution,
+            if options.transparent {
+                [0, 0, 0, 0]
+            } else {
+                animator.background()
+            },
+        );
+        return std::fs::write(output, svg).map_err(|e| Error::Write(output.to_owned(), e));
+    }
+
+    let mut video = futures::executor::block_on(VideoExport::new_with_backend(
+        animator,
+        options.resolution,
+        options.fps,
+        options.backend.into(),
+    ))
+    .map_err(Error::Export)?;
+    video.set_time_options(options.time_options());
+    video.set_encoder_settings(options.encoder_settings());
+    video.set_transparent_background(options.transparent);
+
+    let (tx, rx) = channel();
+    let quiet = options.quiet;
+    let progress = thread::spawn(move || print_progress(rx, quiet));
+
+    let result = match kind {
+        ExportKind::Video => video.export_video(output, tx),
+        ExportKind::Frames => video.export_image_sequence(output, tx),
+        ExportKind::Animation => {
+            let format = output
+                .extension()
+                .and_then(|e| AnimationFormat::from_extension(&e.to_string_lossy()))
+                .unwrap_or(AnimationFormat::Gif);
+            let animation_options = AnimationOptions {
+                palette: PaletteOptions {
+                    colors: options.colors,
+                    ..Default::default()
+                },
+                frame_step: options.frame_step,
+            };
+            video.export_animation(output, format, animation_options, tx)
+        }
+        ExportKind::Frame => {
+            drop(tx);
+            video.export_frame(options.time, output)
+        }
+        ExportKind::Svg => unreachable!("Handled above"),
+    };
+
+    let _ = progress.join();
+    result.map_err(Error::Export)
+}
+
+/// Prints the [VideoProgress] received on `progress` to stderr (unless `quiet`)
+/// until the sender is dropped.
+/// Errors are not printed, as they are returned from the export.
+fn print_progress(progress: Receiver<VideoProgress>, quiet: bool) {
+    let mut stderr = io::stderr();
+    for update in progress {
+        let (label, time, duration) = match update {
+            VideoProgress::Render(time, duration) => ("Rendering", time, duration),
+            VideoProgress::Encode(time, duration) => ("Encoding ", time, duration),
+            VideoProgress::Done(_) | VideoProgress::Error(_) => continue,
+        };
+        if !quiet {
+            let percent = if duration > 0. {
+                (time / duration * 100.).clamp(0., 100.)
+            } else {
+                100.
+            };
+            let _ = write!(stderr, "\r{label} {percent:5.1}%");
+        }
+    }
+    if !quiet {
+        let _ = writeln!(stderr);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_kind_from_path() {
+        let kind = |path: &str| ExportKind::from_path(Path::new(path));
+        assert_eq!(kind("out.mp4"), ExportKind::Video);
+        assert_eq!(kind("out.webm"), ExportKind::Video);
+        assert_eq!(kind("frames"), ExportKind::Frames);
+        assert_eq!(kind("out.GIF"), ExportKind::Animation);
+        assert_eq!(kind("out.apng"), ExportKind::Animation);
+        assert_eq!(kind("out.png"), ExportKind::Frame);
+        assert_eq!(kind("out.svg"), ExportKind::Svg);
+    }
+
+    #[test]
+    fn resolution() {
+        assert_eq!(parse_resolution("1920x1080"), Ok((1920, 1080)));
+        assert!(parse_resolution("1920").is_err());
+        assert!(parse_resolution("ax1080").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Statistics about loaded [Instructions].
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use naviz_parser::input::concrete::{Instructions, TimedInstruction};
+
+/// Statistics about [Instructions] and their animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    /// The duration of the animation
+    pub duration: f32,
+    /// The number of atoms (declared in the setup or spawned)
+    pub atoms: usize,
+    /// The number of instruction groups
+    /// (single instructions count as groups of size `1`)
+    pub groups: usize,
+    /// The number of [TimedInstruction]s per [name][TimedInstruction::str]
+    pub instructions: BTreeMap<&'static str, usize>,
+}
+
+impl Statistics {
+    /// Collects the [Statistics] of the passed [Instructions]
+    /// animated with the passed `duration`.
+    pub fn new(instructions: &Instructions, duration: f32) -> Self {
+        let groups = instructions
+            .instructions
+            .iter()
+            .flat_map(|(_, timeline)| timeline)
+            .map(|(_, _, group)| group);
+
+        let mut counts = BTreeMap::new();
+        let mut group_count = 0;
+        for group in groups {
+            group_count += 1;
+            for (instruction, _) in &group.instructions {
+                *counts
+                    .entry(TimedInstruction::str(instruction))
+                    .or_default() += 1;
+            }
+        }
+
+        Self {
+            duration,
+            atoms: instructions.setup.len() + counts.get("spawn").copied().unwrap_or_default(),
+            groups: group_count,
+            instructions: counts,
+        }
+    }
+
+    /// The total number of [TimedInstruction]s
+    pub fn instruction_count(&self) -> usize {
+        self.instructions.values().sum()
+    }
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "duration:     {}", self.duration)?;
+        writeln!(f, "atoms:        {}", self.atoms)?;
+        writeln!(f, "groups:       {}", self.groups)?;
+        writeln!(f, "instructions: {}", self.instruction_count())?;
+        for (name, count) in &self.instructions {
+            writeln!(f, "  {name:<10} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use naviz_parser::input::{concrete::Instructions, lexer::lex, parser::parse};
+
+    use super::*;
+
+    #[test]
+    fn count_instructions() {
+        let input = r#"
+            atom (0, 0) a
+            atom (0, 1) b
+            @+ load a
+            @+ [
+                rz 1 a
+                rz 1 b
+            ]
+            @+ cz {a, b}
+            @+ spawn (1, 0) c
+        "#;
+        let instructions = Instructions::new(parse(&lex(input).unwrap()).unwrap()).unwrap();
+        let statistics = Statistics::new(&instructions, 4.);
+
+        assert_eq!(
+            statistics,
+            Statistics {
+                duration: 4.,
+                atoms: 3,
+                groups: 4,
+                instructions: BTreeMap::from([("cz", 1), ("load", 1), ("rz", 2), ("spawn", 1)]),
+            }
+        );
+        assert_eq!(statistics.instruction_count(), 5);
+    }
+}
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
 use egui::Ui;
-use naviz_animator::animator::Animator;
+use naviz_animator::{animator::Animator, lint::lint};
 use naviz_parser::{
     config::{machine::MachineConfig, visual::VisualConfig},
-    input::concrete::Instructions,
+    input::{concrete::Instructions, location::LocationError},
 };
 use naviz_renderer::{buffer_updater::BufferUpdater, renderer::Renderer};
 use naviz_state::{config::Config, state::State};
@@ -22,6 +22,14 @@ pub struct AnimatorAdapter {
     visual: Option<VisualConfig>,
     instructions: Option<Instructions>,
 
+    /// The error the [Animator] could not be created with
+    /// (i.e., positions referencing traps or zones the machine does not have)
+    error: Option<LocationError>,
+
+    /// Whether the machine or instructions changed
+    /// since the problems were last gotten using [AnimatorAdapter::take_problems]
+    check_problems: bool,
+
     /// Force Zen-mode.
     /// See [Renderer::force_zen].
     force_zen: bool,
@@ -69,24 +77,40 @@ impl AnimatorState {
     pub fn background(&self) -> [u8; 4] {
         self.background
     }
+
+    /// Renders this [AnimatorState] into an SVG-document
+    /// using the same layout as the [Renderer] would use at the passed `screen_resolution`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn to_svg(&self, screen_resolution: (u32, u32)) -> String {
+        naviz_renderer::svg::to_svg(
+            &self.config,
+            &self.state,
+            naviz_renderer::renderer::get_layout(&self.config, screen_resolution, self.force_zen),
+            screen_resolution,
+            self.background,
+        )
+    }
 }
 
 impl AnimatorAdapter {
     /// Sets the machine config
     pub fn set_machine_config(&mut self, config: MachineConfig) {
         self.machine = Some(config);
+        self.check_problems = true;
         self.recreate_animator(false);
     }
 
     /// Sets the visual config
     pub fn set_visual_config(&mut self, config: VisualConfig) {
         self.visual = Some(config);
+        self.check_problems = true;
         self.recreate_animator(false);
     }
 
     /// Sets the instructions
     pub fn set_instructions(&mut self, instructions: Instructions) {
         self.instructions = Some(instructions);
+        self.check_problems = true;
         self.recreate_animator(true);
     }
 
@@ -95,6 +119,38 @@ impl AnimatorAdapter {
         self.instructions.as_ref()
     }
 
+    /// Gets the problems found in the instructions
+    /// (by [lint]ing them against the machine with the passed `machine_id` and the style,
+    /// and from the [warnings][Animator::warnings] of the [Animator])
+    /// if the machine, style, or instructions changed since the last call.
+    /// If the [Animator] could not be created,
+    /// the error is returned as the only problem instead.
+    ///
+    /// Returns [None] if nothing changed or not all inputs are set yet.
+    pub fn take_problems(&mut self, machine_id: Option<&str>) -> Option<Vec<String>> {
+        if !self.check_problems {
+            return None;
+        }
+        if let Some(error) = &self.error {
+            self.check_problems = false;
+            return Some(vec![error.to_string()]);
+        }
+        let (Some(animator), Some(machine), Some(instructions)) =
+            (&self.animator, &self.machine, &self.instructions)
+        else {
+            return None;
+        };
+        let instructions = instructions.resolve(machine).ok()?;
+        self.check_problems = false;
+        Some(
+            lint(&instructions, machine, self.visual.as_ref(), machine_id)
+                .iter()
+                .map(ToString::to_string)
+                .chain(animator.warnings().iter().map(ToString::to_string))
+                .collect(),
+        )
+    }
+
     /// Whether to force the zen-mode.
     /// See [Renderer::set_force_zen].
     pub fn set_force_zen(&mut self, force_zen: bool) {
@@ -123,7 +179,18 @@ impl AnimatorAdapter {
         if let (Some(machine), Some(visual), Some(instructions)) =
             (&self.machine, &self.visual, &self.instructions)
         {
-            let animator = Animator::new(machine.clone(), visual.clone(), instructions.clone());
+            let animator =
+                match Animator::new(machine.clone(), visual.clone(), instructions.clone()) {
+                    Ok(animator) => animator,
+                    Err(error) => {
+                        // Report the error instead of showing outdated instructions
+                        self.error = Some(error);
+                        self.check_problems = true;
+                        self.animator = None;
+                        return;
+                    }
+                };
+            self.error = None;
             self.update_full = true;
             if reset_time || self.animator.is_none() {
                 // Recreate progress bar while keeping the old speed
@@ -176,17 +243,13 @@ impl AnimatorAdapter {
     }
 
     /// Creates an [Animator] from this [AnimatorAdapter],
-    /// or [None] if not enough inputs were set.
+    /// or [None] if not enough inputs were set or the [Animator] could not be created.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn animator(&self) -> Option<Animator> {
         if let (Some(machine), Some(visual), Some(instructions)) =
             (&self.machine, &self.visual, &self.instructions)
         {
-            Some(Animator::new(
-                machine.clone(),
-                visual.clone(),
-                instructions.clone(),
-            ))
+            Animator::new(machine.clone(), visual.clone(), instructions.clone()).ok()
         } else {
             None
         }
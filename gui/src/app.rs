@@ -1,5 +1,8 @@
 use core::str;
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use std::{
     path::{Path, PathBuf},
@@ -10,13 +13,18 @@ use std::{
 use eframe::egui_wgpu::CallbackTrait;
 use log::error;
 use naviz_import::{ImportError, ImportOptions};
-use naviz_parser::config::{machine::MachineConfig, visual::VisualConfig};
+use naviz_parser::{
+    config::{machine::MachineConfig, visual::VisualConfig},
+    include::{self, Include, IncludeError, Resolver, SourceFile, Sources},
+};
 use naviz_renderer::renderer::Renderer;
 use naviz_repository::Repository;
 use naviz_state::{config::Config, state::State};
 #[cfg(not(target_arch = "wasm32"))]
-use naviz_video::{VideoExport, VideoProgress};
+use naviz_video::{cancel::CancelHandle, VideoExport, VideoProgress};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::export_dialog::ExportOptions;
 use crate::{
     animator_adapter::{AnimatorAdapter, AnimatorState},
     aspect_panel::AspectPanel,
@@ -31,9 +39,14 @@ use crate::{
     future_helper::FutureHelper,
     init::{IdOrManual, InitOptions, Persistence},
     menu::MenuBar,
+    opened_files::OpenedFiles,
     util::WEB,
 };
 
+/// The name of data which was not read from a file
+/// (e.g., pasted instructions or persisted configs)
+const UNNAMED: &str = "<input>";
+
 /// The main App to draw using [egui]/[eframe].
 ///
 /// Can be dereferenced to [AppState] to interface with and update the state.
@@ -54,6 +67,10 @@ pub struct AppState {
     current_style_id: Option<String>,
     persistence: Persistence,
     cache: AppCache,
+    /// Whether the current instructions were imported from another format
+    instructions_imported: bool,
+    /// The files opened by the user, used to resolve includes
+    opened_files: OpenedFiles,
 }
 
 /// Caches some states of the app for operations such as sorting.
@@ -234,6 +251,8 @@ impl AppState {
             current_style_id: None,
             persistence: Default::default(),
             cache: Default::default(),
+            instructions_imported: false,
+            opened_files: Default::default(),
         };
 
         app.update_machines();
@@ -307,37 +326,98 @@ impl AppState {
     ) -> Result<(), ImportError> {
         let instructions = import_options.import(data)?;
         self.animator_adapter.set_instructions(instructions);
+        self.instructions_imported = true;
         self.update_machines(); // update compatible machines
         Ok(())
     }
 
     /// Open the naviz-instructions from `data`
     pub fn open(&mut self, data: &[u8]) -> Result<()> {
-        let input = naviz_parser::input::lexer::lex(
-            str::from_utf8(data)
-                .map_err(|e| Error::FileOpen(InputType::Instruction(InputError::UTF8(e))))?,
+        self.open_named(UNNAMED, data)
+    }
+
+    /// Open the naviz-instructions from `data` of the file with the passed `name`.
+    /// Includes are resolved relative to `name` (see [OpenedFiles::resolve]).
+    pub fn open_named(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let error = |e| Error::FileOpen(InputType::Instruction(e));
+        let source = str::from_utf8(data).map_err(|e| error(InputError::UTF8(e)))?;
+        let sources: Sources<include::InputFormat> = Sources::load(
+            SourceFile::new(name, source),
+            &mut |from: &str, include: &Include| self.opened_files.resolve(from, include),
         )
-        .map_err(|e| Error::FileOpen(InputType::Instruction(InputError::Lex(e.into_inner()))))?;
-        let input = naviz_parser::input::parser::parse(&input).map_err(|e| {
-            Error::FileOpen(InputType::Instruction(InputError::Parse(e.into_inner())))
+        .map_err(|e| {
+            error(match e {
+                IncludeError::Lex(diagnostic) => InputError::Lex(diagnostic),
+                e => InputError::Include(e.into_diagnostic()),
+            })
+        })?;
+        let input = naviz_parser::input::parser::parse(&sources.tokens())
+            .map_err(|e| error(InputError::Parse(sources.token_error(&e).into())))?;
+        let input = naviz_parser::input::concrete::Instructions::new(input).map_err(|e| {
+            let diagnostics = e.diagnostics(&sources);
+            error(InputError::Convert(e, diagnostics))
         })?;
-        let input = naviz_parser::input::concrete::Instructions::new(input)
-            .map_err(|e| Error::FileOpen(InputType::Instruction(InputError::Convert(e))))?;
         self.animator_adapter.set_instructions(input);
+        self.instructions_imported = false;
         self.update_machines(); // update compatible machines
         self.select_compatible_machine()?;
         Ok(())
     }
 
-    /// Opens a file by [FileType].
-    pub fn open_by_type(&mut self, file_type: FileType, data: &[u8]) -> Result<()> {
+    /// Opens the file with the passed `name` by [FileType].
+    /// The file is [remembered][AppState::remember_file] to be included by other files.
+    pub fn open_by_type(&mut self, file_type: FileType, name: &str, data: Arc<[u8]>) -> Result<()> {
+        self.remember_file(name, data.clone());
         match file_type {
-            FileType::Instructions => self.open(data),
-            FileType::Machine => self.set_machine_manually(data),
-            FileType::Style => self.set_style_manually(data),
+            FileType::Instructions => self.open_named(name, &data),
+            FileType::Machine => self.set_machine_from_file(name, &data),
+            FileType::Style => self.set_style_from_file(name, &data),
         }
     }
 
+    /// Remembers the file with the passed `name` and `data`,
+    /// so that it can be included by files opened later.
+    pub fn remember_file(&mut self, name: &str, data: Arc<[u8]>) {
+        self.opened_files.insert(name, data);
+    }
+
+    /// Loads a config of the passed [ConfigFormat] from `data` of the file with the passed `name`.
+    /// Paths are included relative to `name` (see [OpenedFiles::resolve]),
+    /// ids are included from the repository of the [ConfigFormat].
+    /// Returns the config and its source with all includes resolved.
+    fn load_config<C>(&self, format: ConfigFormat, name: &str, data: &[u8]) -> Result<(C, String)>
+    where
+        naviz_parser::config::generic::Config:
+            TryInto<C, Error = naviz_parser::config::error::Error>,
+    {
+        let error = |e| Error::FileOpen(InputType::Config(format, e));
+        let repository = match format {
+            ConfigFormat::Machine => &self.machine_repository,
+            ConfigFormat::Style => &self.style_repository,
+        };
+        let source = str::from_utf8(data).map_err(|e| error(ConfigError::UTF8(e)))?;
+        let sources: Sources<include::ConfigFormat> = Sources::load(
+            SourceFile::new(name, source),
+            &mut |from: &str, include: &Include| match include {
+                Include::Path(_) => self.opened_files.resolve(from, include),
+                Include::Id(_) => repository.resolver().resolve(from, include),
+            },
+        )
+        .map_err(|e| {
+            error(match e {
+                IncludeError::Lex(diagnostic) => ConfigError::Lex(diagnostic),
+                e => ConfigError::Include(e.into_diagnostic()),
+            })
+        })?;
+        let config = naviz_parser::config::parser::parse(&sources.tokens())
+            .map_err(|e| error(ConfigError::Parse(sources.token_error(&e).into())))?;
+        let config: naviz_parser::config::generic::Config = config.into();
+        let config = config
+            .try_into()
+            .map_err(|e| error(ConfigError::Convert(e)))?;
+        Ok((config, sources.flatten()))
+    }
+
     /// Selects any compatible machine for the currently opened machine.
     /// Returns `true` if a compatible machine could be found and was loaded,
     /// or `false` otherwise.
@@ -407,34 +487,17 @@ impl AppState {
 
     /// Set the current machine to the one specified in `data`.
     pub fn set_machine_manually(&mut self, data: &[u8]) -> Result<()> {
-        let machine = naviz_parser::config::lexer::lex(str::from_utf8(data).map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Machine,
-                ConfigError::UTF8(e),
-            ))
-        })?)
-        .map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Machine,
-                ConfigError::Lex(e.into_inner()),
-            ))
-        })?;
-        let machine = naviz_parser::config::parser::parse(&machine).map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Machine,
-                ConfigError::Parse(e.into_inner()),
-            ))
-        })?;
-        let machine: naviz_parser::config::generic::Config = machine.into();
-        let machine: MachineConfig = machine.try_into().map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Machine,
-                ConfigError::Convert(e),
-            ))
-        })?;
+        self.set_machine_from_file(UNNAMED, data)
+    }
+
+    /// Set the current machine to the one specified in `data` of the file with the passed `name`.
+    /// Includes are resolved relative to `name` (see [AppState::load_config]).
+    pub fn set_machine_from_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let (machine, source): (MachineConfig, _) =
+            self.load_config(ConfigFormat::Machine, name, data)?;
         self.set_loaded_machine(None::<String>, machine);
-        // keep machine in persistence
-        self.persistence.machine = Some(IdOrManual::Manual(data.into()));
+        // keep machine in persistence (with resolved includes, as the included files may be gone)
+        self.persistence.machine = Some(IdOrManual::Manual(source.into_bytes().into()));
         Ok(())
     }
 
@@ -464,31 +527,17 @@ impl AppState {
 
     /// Set the current style to the one specified in `data`.
     pub fn set_style_manually(&mut self, data: &[u8]) -> Result<()> {
-        let visual = naviz_parser::config::lexer::lex(str::from_utf8(data).map_err(|e| {
-            Error::FileOpen(InputType::Config(ConfigFormat::Style, ConfigError::UTF8(e)))
-        })?)
-        .map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Style,
-                ConfigError::Lex(e.into_inner()),
-            ))
-        })?;
-        let visual = naviz_parser::config::parser::parse(&visual).map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Style,
-                ConfigError::Parse(e.into_inner()),
-            ))
-        })?;
-        let visual: naviz_parser::config::generic::Config = visual.into();
-        let visual: VisualConfig = visual.try_into().map_err(|e| {
-            Error::FileOpen(InputType::Config(
-                ConfigFormat::Style,
-                ConfigError::Convert(e),
-            ))
-        })?;
+        self.set_style_from_file(UNNAMED, data)
+    }
+
+    /// Set the current style to the one specified in `data` of the file with the passed `name`.
+    /// Includes are resolved relative to `name` (see [AppState::load_config]).
+    pub fn set_style_from_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let (visual, source): (VisualConfig, _) =
+            self.load_config(ConfigFormat::Style, name, data)?;
         self.set_loaded_style(None::<String>, visual);
-        // keep style in persistence
-        self.persistence.style = Some(IdOrManual::Manual(data.into()));
+        // keep style in persistence (with resolved includes, as the included files may be gone)
+        self.persistence.style = Some(IdOrManual::Manual(source.into_bytes().into()));
         Ok(())
     }
 
@@ -562,25 +611,61 @@ impl AppState {
     }
 
     /// Starts an export of the visualization to the specified `target`-path
-    /// with the specified `resolution` and `fps`.
+    /// with the specified [ExportOptions].
     /// Updates will be sent over the `progress`-channel.
+    /// The export can be cancelled using the `cancel`-handle.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn export(
         &self,
         target: PathBuf,
-        resolution: (u32, u32),
-        fps: u32,
+        options: ExportOptions,
         progress: Sender<VideoProgress>,
+        cancel: CancelHandle,
     ) {
         if let Some(animator) = self.animator_adapter.animator() {
-            let video = VideoExport::new(animator, resolution, fps);
-            thread::spawn(move || {
-                let mut video = futures::executor::block_on(video);
-                video.export_video(&target, progress);
+            let video = VideoExport::new(animator, options.resolution, options.fps);
+            thread::spawn(move || match futures::executor::block_on(video) {
+                // Errors during the export are reported over the `progress`-channel
+                Ok(mut video) => {
+                    video.set_cancel_handle(cancel);
+                    video.set_time_options(options.time);
+                    video.set_encoder_settings(options.encoder);
+                    video.set_transparent_background(options.transparent);
+                    let _ = video.export_video(&target, progress);
+                }
+                Err(error) => {
+                    let _ = progress.send(VideoProgress::Error(error));
+                }
             });
         }
     }
 
+    /// Exports the currently displayed frame as an SVG to the specified `target`-path.
+    /// The layout is computed for the specified `resolution`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_svg(&self, target: &Path, resolution: (u32, u32)) -> Result<()> {
+        if let Some(animator_state) = self.animator_adapter.peek() {
+            std::fs::write(target, animator_state.to_svg(resolution))
+                .map_err(Error::FrameExport)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the current instructions were imported from another format
+    /// (and can therefore be [saved as `.naviz`][Self::save_instructions]).
+    pub fn instructions_imported(&self) -> bool {
+        self.instructions_imported
+    }
+
+    /// Saves the current instructions in the `.naviz`-format to the specified `target`-path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_instructions(&self, target: &Path) -> Result<()> {
+        if let Some(instructions) = self.animator_adapter.get_instructions() {
+            std::fs::write(target, instructions.to_string()).map_err(Error::InstructionsSave)?;
+        }
+        Ok(())
+    }
+
     /// Updates the cached list of machines
     fn update_machines(&mut self) {
         self.cache.update_machines(
@@ -661,6 +746,16 @@ impl eframe::App for App {
             );
         });
 
+        // Report problems in newly loaded instructions or machines
+        let machine_id = self.get_current_machine_id().map(str::to_string);
+        if let Some(problems) = self.animator_adapter.take_problems(machine_id.as_deref()) {
+            if !problems.is_empty() {
+                self.ui
+                    .errors
+                    .add("Problems in instructions", problems.join("\n"));
+            }
+        }
+
         self.ui.errors.draw(ctx);
     }
 
@@ -1,7 +1,7 @@
 use std::str::Utf8Error;
 
 use naviz_import::ImportError;
-use naviz_parser::{config, input::concrete::ParseInstructionsError, ParseErrorInner};
+use naviz_parser::{config, diagnostic::Diagnostic, input::concrete::ParseInstructionsError};
 
 /// A [Result][std::result::Result] pre-filled with [Error]
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -16,6 +16,12 @@ pub enum Error {
     Import(ImportError),
     /// Error when interacting with the repository.
     Repository(RepositoryError, ConfigFormat),
+    /// Error while exporting a single frame.
+    #[cfg(not(target_arch = "wasm32"))]
+    FrameExport(std::io::Error),
+    /// Error while saving the instructions as `.naviz`.
+    #[cfg(not(target_arch = "wasm32"))]
+    InstructionsSave(std::io::Error),
 }
 
 /// An Error occurred while opening one of the input-types
@@ -29,13 +35,14 @@ pub enum InputType {
 #[derive(Debug)]
 pub enum InputError {
     UTF8(Utf8Error),
-    Lex(ParseErrorInner),
-    Parse(ParseErrorInner),
-    Convert(ParseInstructionsError),
+    Lex(Box<Diagnostic>),
+    Parse(Box<Diagnostic>),
+    Include(Box<Diagnostic>),
+    Convert(ParseInstructionsError, Vec<Diagnostic>),
 }
 
 /// A config-format
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ConfigFormat {
     Machine,
     Style,
@@ -45,8 +52,9 @@ pub enum ConfigFormat {
 #[derive(Debug)]
 pub enum ConfigError {
     UTF8(Utf8Error),
-    Lex(ParseErrorInner),
-    Parse(ParseErrorInner),
+    Lex(Box<Diagnostic>),
+    Parse(Box<Diagnostic>),
+    Include(Box<Diagnostic>),
     Convert(config::error::Error),
 }
 /// An error to do with the [Repository][naviz_repository::Repository]
@@ -88,13 +96,50 @@ impl Error {
             Self::Repository(RepositoryError::Import(_), ConfigFormat::Style) => "Failed to import machine to user-dir",
             Self::Repository(RepositoryError::Remove(_), ConfigFormat::Machine) => "Failed to remove machine from user-dir",
             Self::Repository(RepositoryError::Remove(_), ConfigFormat::Style) => "Failed to remove style from user-dir",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::FrameExport(_) => "Failed to export frame",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::InstructionsSave(_) => "Failed to save instructions",
         }
     }
 
     /// A longer text representation of this [Error].
     /// Contains details.
     pub fn body(&self) -> String {
-        // Just print out debug for now
-        format!("{self:#?}")
+        if let Self::FileOpen(InputType::Instruction(InputError::Convert(e, diagnostics))) = self {
+            // Point to the definitions the error occurred in
+            return std::iter::once(e.innermost().to_string())
+                .chain(diagnostics.iter().map(Diagnostic::to_string))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        match self.diagnostic() {
+            Some(diagnostic) => diagnostic.to_string(),
+            // Just print out debug for now
+            None => format!("{self:#?}"),
+        }
+    }
+
+    /// The [Diagnostic] of this [Error], if it is located in some source-text.
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            Self::FileOpen(InputType::Instruction(InputError::Lex(diagnostic)))
+            | Self::FileOpen(InputType::Instruction(InputError::Parse(diagnostic)))
+            | Self::FileOpen(InputType::Instruction(InputError::Include(diagnostic)))
+            | Self::FileOpen(InputType::Config(_, ConfigError::Lex(diagnostic)))
+            | Self::FileOpen(InputType::Config(_, ConfigError::Parse(diagnostic)))
+            | Self::FileOpen(InputType::Config(_, ConfigError::Include(diagnostic))) => {
+                Some(diagnostic)
+            }
+            Self::Import(e) => e.diagnostic(),
+            Self::Repository(
+                RepositoryError::Load(_, e)
+                | RepositoryError::Open(e)
+                | RepositoryError::Import(e)
+                | RepositoryError::Remove(e),
+                _,
+            ) => e.diagnostic(),
+            _ => None,
+        }
     }
 }
@@ -1,6 +1,6 @@
 use std::{borrow::Cow, sync::atomic::AtomicU32};
 
-use egui::{Id, Window};
+use egui::{Id, RichText, Window};
 
 /// Display errors to the user.
 ///
@@ -69,7 +69,8 @@ impl Error {
         Window::new(&*self.title)
             .id(Id::new(&self.id))
             .open(&mut open)
-            .show(ctx, |ui| ui.label(&self.message));
+            // Monospace to align the carets of diagnostics
+            .show(ctx, |ui| ui.label(RichText::new(&self.message).monospace()));
         open
     }
 }
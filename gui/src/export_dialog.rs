@@ -1,14 +1,35 @@
-use std::{
-    process::ExitStatus,
-    sync::{
-        atomic::AtomicU32,
-        mpsc::{channel, Receiver, Sender, TryRecvError},
-        Arc, Mutex,
-    },
+use std::sync::{
+    atomic::AtomicU32,
+    mpsc::{channel, Receiver, Sender, TryRecvError},
+    Arc, Mutex,
+};
+
+use egui::{
+    Align2, ComboBox, Context, DragValue, Grid, Id, Layout, ProgressBar, Spinner, TextEdit, Ui,
+    Window,
+};
+use naviz_video::{
+    cancel::CancelHandle,
+    encoder::{EncoderPreset, EncoderSettings},
+    error::VideoExportError,
+    time::TimeOptions,
+    VideoProgress,
 };
 
-use egui::{Align2, Context, DragValue, Grid, Id, Layout, ProgressBar, Spinner, Window};
-use naviz_video::VideoProgress;
+/// The options of a video export selected in the [ExportSettings]
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Resolution to render at
+    pub resolution: (u32, u32),
+    /// FPS to render at
+    pub fps: u32,
+    /// The part of the animation to render and its speed
+    pub time: TimeOptions,
+    /// The settings for `ffmpeg`
+    pub encoder: EncoderSettings,
+    /// Whether to render with a transparent background
+    pub transparent: bool,
+}
 
 /// Settings-Dialog for the export
 pub struct ExportSettings {
@@ -16,15 +37,33 @@ pub struct ExportSettings {
     resolution: (u32, u32),
     /// FPS to render at
     fps: u32,
+    /// The part of the animation to render and its speed
+    time: TimeOptions,
+    /// The last selected encoder-preset
+    encoder_preset: EncoderPreset,
+    /// The settings for `ffmpeg`.
+    /// The [extra arguments][EncoderSettings::extra_args] are edited in `extra_args`.
+    encoder: EncoderSettings,
+    /// The extra arguments for `ffmpeg`, separated by whitespace
+    extra_args: String,
+    /// Whether to render with a transparent background
+    transparent: bool,
     /// Whether the export settings dialog is shown
     show: bool,
 }
 
 impl Default for ExportSettings {
     fn default() -> Self {
+        let encoder_preset = EncoderPreset::default();
+        let encoder = encoder_preset.settings();
         Self {
             resolution: (1920, 1080),
             fps: 30,
+            time: Default::default(),
+            extra_args: encoder.extra_args.join(" "),
+            encoder_preset,
+            encoder,
+            transparent: false,
             show: false,
         }
     }
@@ -62,6 +101,109 @@ impl ExportSettings {
                             ui.label("FPS:");
                             ui.add(DragValue::new(&mut self.fps));
                             ui.end_row();
+
+                            ui.label("Start:");
+                            ui.add(
+                                DragValue::new(&mut self.time.start)
+                                    .speed(0.1)
+                                    .range(0. ..=f32::MAX),
+                            );
+                            ui.end_row();
+
+                            ui.label("End:");
+                            ui.horizontal(|ui| {
+                                let mut until_end = self.time.end.is_none();
+                                if ui.checkbox(&mut until_end, "Until end").changed() {
+                                    self.time.end = (!until_end).then_some(self.time.start);
+                                }
+                                if let Some(end) = &mut self.time.end {
+                                    ui.add(
+                                        DragValue::new(end)
+                                            .speed(0.1)
+                                            .range(self.time.start..=f32::MAX),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Speed:");
+                            ui.add(
+                                DragValue::new(&mut self.time.scale)
+                                    .speed(0.01)
+                                    .range(0.001..=f32::MAX)
+                                    .suffix(" s per time unit"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Hold first frame:");
+                            ui.add(
+                                DragValue::new(&mut self.time.hold_start)
+                                    .speed(0.1)
+                                    .range(0. ..=f32::MAX)
+                                    .suffix(" s"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Hold last frame:");
+                            ui.add(
+                                DragValue::new(&mut self.time.hold_end)
+                                    .speed(0.1)
+                                    .range(0. ..=f32::MAX)
+                                    .suffix(" s"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Background:");
+                            ui.checkbox(&mut self.transparent, "Transparent")
+                                .on_hover_text(
+                                    "Alpha is only kept by encoder presets supporting it \
+                                     (e.g., ProRes 4444 or WebM with alpha)",
+                                );
+                            ui.end_row();
+
+                            ui.label("Encoder preset:");
+                            ComboBox::from_id_salt("export_encoder_preset")
+                                .selected_text(self.encoder_preset.name())
+                                .show_ui(ui, |ui| {
+                                    for preset in EncoderPreset::ALL {
+                                        if ui
+                                            .selectable_value(
+                                                &mut self.encoder_preset,
+                                                preset,
+                                                preset.name(),
+                                            )
+                                            .clicked()
+                                        {
+                                            self.encoder = preset.settings();
+                                            self.extra_args = self.encoder.extra_args.join(" ");
+                                        }
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Codec:");
+                            optional_text_edit(ui, &mut self.encoder.codec);
+                            ui.end_row();
+
+                            ui.label("Quality (CRF):");
+                            ui.horizontal(|ui| {
+                                let mut codec_default = self.encoder.quality.is_none();
+                                if ui.checkbox(&mut codec_default, "Default").changed() {
+                                    self.encoder.quality = (!codec_default).then_some(23);
+                                }
+                                if let Some(quality) = &mut self.encoder.quality {
+                                    ui.add(DragValue::new(quality).range(0..=63));
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Pixel format:");
+                            optional_text_edit(ui, &mut self.encoder.pixel_format);
+                            ui.end_row();
+
+                            ui.label("ffmpeg arguments:");
+                            ui.text_edit_singleline(&mut self.extra_args);
+                            ui.end_row();
                         })
                         .response
                         .rect
@@ -89,10 +231,41 @@ impl ExportSettings {
         self.resolution
     }
 
-    /// Gets the currently selected fps.
+    /// Gets the currently selected [ExportOptions].
     /// Note: Changes with user-input when shown.
-    pub fn fps(&self) -> u32 {
-        self.fps
+    pub fn options(&self) -> ExportOptions {
+        ExportOptions {
+            resolution: self.resolution,
+            fps: self.fps,
+            time: self.time,
+            encoder: EncoderSettings {
+                extra_args: self
+                    .extra_args
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                ..self.encoder.clone()
+            },
+            transparent: self.transparent,
+        }
+    }
+
+    /// Gets the file-extension recommended for the selected encoder-preset
+    /// (see [EncoderPreset::extension]).
+    pub fn extension(&self) -> Option<&'static str> {
+        self.encoder_preset.extension()
+    }
+}
+
+/// Draws a text-edit for an optional string.
+/// An empty text is [None].
+fn optional_text_edit(ui: &mut Ui, value: &mut Option<String>) {
+    let mut text = value.clone().unwrap_or_default();
+    if ui
+        .add(TextEdit::singleline(&mut text).hint_text("auto"))
+        .changed()
+    {
+        *value = (!text.is_empty()).then_some(text);
     }
 }
 
@@ -105,11 +278,13 @@ pub struct ExportProgresses {
 
 impl ExportProgresses {
     /// Adds a new progress-window and returns a channel to send [VideoProgress]-events over
-    pub fn add(&mut self) -> Sender<VideoProgress> {
+    /// and the [CancelHandle] which is triggered when the user cancels the export
+    pub fn add(&mut self) -> (Sender<VideoProgress>, CancelHandle) {
         let (progress_tx, progress_rx) = channel();
-        let progress = Arc::new(Mutex::new(ExportProgress::new(progress_rx)));
+        let cancel = CancelHandle::new();
+        let progress = Arc::new(Mutex::new(ExportProgress::new(progress_rx, cancel.clone())));
         self.progresses.push(progress.clone());
-        progress_tx
+        (progress_tx, cancel)
     }
 
     /// Draws all the [ExportProgress]es
@@ -135,9 +310,12 @@ enum ExportProgressState {
         /// The encoding-progress (`current_time`, `duration`)
         encode: (f32, f32),
     },
-    /// The video exporter is finished with the specified [ExitStatus]
-    Done(ExitStatus),
-    /// Channel dropped during export (while not [Done][ExportProgressState::Done]);
+    /// The video exporter finished successfully
+    Done,
+    /// The video exporter failed with the contained error
+    Error(VideoExportError),
+    /// Channel dropped during export
+    /// (while not [Done][ExportProgressState::Done] or [Error][ExportProgressState::Error]);
     /// Unknown what the current status is.
     /// Should not happen in normal operation.
     Unknown,
@@ -181,6 +359,10 @@ pub struct ExportProgress {
     state: ExportProgressState,
     /// The receiver for [VideoProgress]-events
     receiver: Receiver<VideoProgress>,
+    /// The handle to cancel the export with
+    cancel: CancelHandle,
+    /// Whether to delete the partial output when cancelling
+    delete_on_cancel: bool,
     /// The unique id for the window (generated by [EXPORT_PROGRESS_ID_GEN])
     window_id: String,
     /// The unique id for the grid in the window (generated by [EXPORT_PROGRESS_ID_GEN])
@@ -189,11 +371,14 @@ pub struct ExportProgress {
 
 impl ExportProgress {
     /// Create a new [ExportProgress]-dialog that takes events from the `receiver`-channel
-    fn new(receiver: Receiver<VideoProgress>) -> Self {
+    /// and cancels the export using the `cancel`-handle
+    fn new(receiver: Receiver<VideoProgress>, cancel: CancelHandle) -> Self {
         let num = EXPORT_PROGRESS_ID_GEN.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         Self {
             state: ExportProgressState::Creating,
             receiver,
+            cancel,
+            delete_on_cancel: true,
             window_id: format!("export_progress_{num}"),
             grid_id: format!("export_progress_grid_{num}"),
         }
@@ -201,8 +386,10 @@ impl ExportProgress {
 
     /// Draws this [ExportProgress].
     ///
-    /// When the state is [Done][ExportProgressState::Done] or [Unknown][ExportProgressState::Unknown],
+    /// When the state is [Done][ExportProgressState::Done], [Error][ExportProgressState::Error],
+    /// or [Unknown][ExportProgressState::Unknown],
     /// the user may close the dialog.
+    /// Otherwise, the user may cancel the export.
     ///
     /// This function returns `true` when the user closed the window.
     /// This [ExportProgress] may then be disposed.
@@ -215,12 +402,15 @@ impl ExportProgress {
             Ok(VideoProgress::Render(cur, max)) => {
                 self.state.set_render(cur, max);
             }
-            Ok(VideoProgress::Done(status)) => {
-                self.state = ExportProgressState::Done(status);
+            Ok(VideoProgress::Done(_)) => {
+                self.state = ExportProgressState::Done;
+            }
+            Ok(VideoProgress::Error(error)) => {
+                self.state = ExportProgressState::Error(error);
             }
             Err(TryRecvError::Empty) => {}
             Err(TryRecvError::Disconnected) => match self.state {
-                ExportProgressState::Done(_) => {}
+                ExportProgressState::Done | ExportProgressState::Error(_) => {}
                 _ => self.state = ExportProgressState::Unknown,
             },
         };
@@ -231,12 +421,13 @@ impl ExportProgress {
             .resizable(false)
             .max_width(480.)
             .collapsible(false);
-        match self.state {
+        match &self.state {
             ExportProgressState::Creating => {
                 window.show(ctx, |ui| {
                     ui.allocate_ui([128., 98.].into(), |ui| {
                         ui.centered_and_justified(|ui| ui.add(Spinner::new().size(76.)))
-                    })
+                    });
+                    draw_cancel(ui, &self.cancel, &mut self.delete_on_cancel);
                 });
                 false
             }
@@ -252,21 +443,28 @@ impl ExportProgress {
                         ui.label(format!("{:.1}", encode.0));
                         ui.end_row();
                     });
+                    draw_cancel(ui, &self.cancel, &mut self.delete_on_cancel);
                 });
                 false
             }
-            ExportProgressState::Done(status) => {
+            ExportProgressState::Done => {
                 let mut open = true;
                 window.open(&mut open).show(ctx, |ui| {
-                    if status.success() {
-                        ui.label("Finished exporting!");
-                    } else if let Some(code) = status.code() {
-                        ui.label(format!(
-                            "Error during export! FFmpeg exited with code {code}."
-                        ));
-                    } else {
-                        ui.label("Error during export!");
-                    }
+                    ui.label("Finished exporting!");
+                });
+                !open
+            }
+            ExportProgressState::Error(VideoExportError::Cancelled) => {
+                let mut open = true;
+                window.open(&mut open).show(ctx, |ui| {
+                    ui.label("Export cancelled.");
+                });
+                !open
+            }
+            ExportProgressState::Error(error) => {
+                let mut open = true;
+                window.open(&mut open).show(ctx, |ui| {
+                    ui.label(format!("Error during export: {error}."));
                 });
                 !open
             }
@@ -280,3 +478,22 @@ impl ExportProgress {
         }
     }
 }
+
+/// Draws the controls to cancel an export using the `cancel`-handle.
+/// `delete_output` is the user's choice whether to delete the partial output.
+fn draw_cancel(ui: &mut Ui, cancel: &CancelHandle, delete_output: &mut bool) {
+    let cancelled = cancel.is_cancelled();
+    ui.add_enabled_ui(!cancelled, |ui| {
+        ui.checkbox(delete_output, "Delete partial output");
+        ui.with_layout(Layout::top_down_justified(egui::Align::Center), |ui| {
+            let label = if cancelled { "Cancelling…" } else { "Cancel" };
+            if ui.button(label).clicked() {
+                if *delete_output {
+                    cancel.cancel_and_delete_output();
+                } else {
+                    cancel.cancel();
+                }
+            }
+        });
+    });
+}
@@ -15,6 +15,7 @@ mod future_helper;
 mod import;
 pub mod init;
 mod menu;
+mod opened_files;
 mod progress_bar;
 mod util;
 pub use app::App;
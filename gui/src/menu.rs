@@ -45,8 +45,8 @@ pub struct MenuBar {
 
 /// An event which can be triggered by asynchronous actions like the user choosing a file
 enum MenuEvent {
-    /// A file of the specified [FileType] with the specified content was opened
-    FileOpen(FileType, Arc<[u8]>),
+    /// A file of the specified [FileType] with the specified name and content was opened
+    FileOpen(FileType, String, Arc<[u8]>),
     /// A file should be imported
     FileImport(ImportOptions, Arc<[u8]>),
     /// The machine at the specified `path` should be imported
@@ -60,7 +60,12 @@ enum MenuEvent {
 impl MenuEvent {
     /// Creates a [MenuEvent::FileOpen] for [MenuBar::choose_file]
     async fn file_open(file_type: FileType, handle: FileHandle) -> Self {
-        Self::FileOpen(file_type, handle.read().await.into())
+        // Use the full path where available to resolve includes relative to it
+        #[cfg(not(target_arch = "wasm32"))]
+        let name = handle.path().display().to_string();
+        #[cfg(target_arch = "wasm32")]
+        let name = handle.file_name();
+        Self::FileOpen(file_type, name, handle.read().await.into())
     }
 
     /// Creates a [MenuEvent::ImportMachine] or [MenuEvent::ImportStyle] for [MenuBar::choose_file]
@@ -103,7 +108,7 @@ impl MenuBar {
             for file_type in [FileType::Instructions, FileType::Machine, FileType::Style] {
                 // File extension is known?
                 if file_type.extensions().contains(&extension) {
-                    return state.open_by_type(file_type, &contents);
+                    return state.open_by_type(file_type, name, contents);
                 }
             }
 
@@ -123,11 +128,19 @@ impl MenuBar {
 
     /// Handles any files dropped onto the application.
     /// Will use [Self::load_file_by_extension] to load the file.
+    /// All dropped files are remembered before loading any of them,
+    /// so that files dropped together can include each other.
     fn handle_file_drop(&mut self, ctx: &egui::Context, state: &mut AppState) -> Result<()> {
-        for file in ctx.input_mut(|input| std::mem::take(&mut input.raw.dropped_files)) {
-            if let Some(contents) = file.bytes {
-                self.load_file_by_extension(&file.name, contents, state)?;
-            }
+        let files: Vec<_> = ctx
+            .input_mut(|input| std::mem::take(&mut input.raw.dropped_files))
+            .into_iter()
+            .filter_map(|file| Some((file.name, file.bytes?)))
+            .collect();
+        for (name, contents) in &files {
+            state.remember_file(name, contents.clone());
+        }
+        for (name, contents) in files {
+            self.load_file_by_extension(&name, contents, state)?;
         }
         Ok(())
     }
@@ -178,7 +191,9 @@ impl MenuBar {
     fn process_events(&mut self, state: &mut AppState, errors: &mut Errors) {
         while let Ok(event) = self.event_channel.1.try_recv() {
             match event {
-                MenuEvent::FileOpen(file_type, data) => state.open_by_type(file_type, &data),
+                MenuEvent::FileOpen(file_type, name, data) => {
+                    state.open_by_type(file_type, &name, data)
+                }
                 MenuEvent::FileImport(import_options, data) => {
                     state.import(import_options, &data).map_err(Error::Import)
                 }
@@ -204,7 +219,7 @@ impl MenuBar {
     ) {
         self.process_events(state, errors);
 
-        self.export_menu.process_events(state);
+        self.export_menu.process_events(state, errors);
 
         self.show_import_dialog(state, future_helper, ctx)
             .pipe_void(errors);
@@ -230,7 +245,10 @@ impl MenuBar {
                 });
 
                 self.export_menu
-                    .draw_button(state.visualization_loaded(), ui);
+                    .draw_save_button(state.instructions_imported(), future_helper, ui);
+
+                self.export_menu
+                    .draw_button(state.visualization_loaded(), future_helper, ui);
 
                 if !WEB {
                     // Quit-button only on native
@@ -629,7 +647,9 @@ pub mod export {
 
     use crate::{
         app::AppState,
-        export_dialog::{ExportProgresses, ExportSettings},
+        errors::{ErrorEmitter, Errors},
+        export_dialog::{ExportOptions, ExportProgresses, ExportSettings},
+        file_type::{FileFilter, FileType},
         future_helper::FutureHelper,
     };
 
@@ -638,7 +658,11 @@ pub mod export {
     /// Menu components concerning export
     pub struct ExportMenu {
         /// Channel for selected export-settings
-        export_channel: SendReceivePair<(PathBuf, (u32, u32), u32)>,
+        export_channel: SendReceivePair<(PathBuf, ExportOptions)>,
+        /// Channel for selected svg-export-targets
+        svg_export_channel: SendReceivePair<PathBuf>,
+        /// Channel for selected targets to save the instructions to
+        save_channel: SendReceivePair<PathBuf>,
         /// The export-settings-dialog to show when the user wants to export a video
         export_settings: ExportSettings,
         /// The export-progress-dialogs to show
@@ -650,20 +674,52 @@ pub mod export {
         pub fn new() -> Self {
             Self {
                 export_channel: channel(),
+                svg_export_channel: channel(),
+                save_channel: channel(),
                 export_settings: Default::default(),
                 export_progresses: Default::default(),
             }
         }
 
         /// Processes events concerning export
-        pub fn process_events(&mut self, state: &mut AppState) {
-            if let Ok((target, resolution, fps)) = self.export_channel.1.try_recv() {
-                state.export(target, resolution, fps, self.export_progresses.add());
+        pub fn process_events(&mut self, state: &mut AppState, errors: &mut Errors) {
+            if let Ok((target, options)) = self.export_channel.1.try_recv() {
+                let (progress, cancel) = self.export_progresses.add();
+                state.export(target, options, progress, cancel);
+            }
+            if let Ok(target) = self.svg_export_channel.1.try_recv() {
+                state
+                    .export_svg(&target, self.export_settings.resolution())
+                    .pipe_void(errors);
+            }
+            if let Ok(target) = self.save_channel.1.try_recv() {
+                state.save_instructions(&target).pipe_void(errors);
+            }
+        }
+
+        /// Draws the menu button to save imported instructions as `.naviz`
+        pub fn draw_save_button(
+            &mut self,
+            enabled: bool,
+            future_helper: &FutureHelper,
+            ui: &mut egui::Ui,
+        ) {
+            if ui
+                .add_enabled(enabled, Button::new("Save as .naviz…"))
+                .clicked()
+            {
+                self.save(future_helper);
+                ui.close_menu();
             }
         }
 
         /// Draws the menu button concerning export
-        pub fn draw_button(&mut self, enabled: bool, ui: &mut egui::Ui) {
+        pub fn draw_button(
+            &mut self,
+            enabled: bool,
+            future_helper: &FutureHelper,
+            ui: &mut egui::Ui,
+        ) {
             if ui
                 .add_enabled(enabled, Button::new("Export Video"))
                 .clicked()
@@ -671,6 +727,13 @@ pub mod export {
                 self.export_settings.show();
                 ui.close_menu();
             }
+            if ui
+                .add_enabled(enabled, Button::new("Export frame as SVG…"))
+                .clicked()
+            {
+                self.export_svg(future_helper);
+                ui.close_menu();
+            }
         }
 
         /// Draws the windows concerning video export
@@ -684,19 +747,54 @@ pub mod export {
 
         /// Show the file-saving dialog and get the path to export to if a file was selected
         fn export(&self, future_helper: &FutureHelper) {
-            let resolution = self.export_settings.resolution();
-            let fps = self.export_settings.fps();
+            let options = self.export_settings.options();
+            let extension = self.export_settings.extension();
             future_helper.execute_maybe_to(
                 async move {
-                    rfd::AsyncFileDialog::new()
+                    let mut dialog = rfd::AsyncFileDialog::new();
+                    if let Some(extension) = extension {
+                        dialog = dialog.add_filter(extension, &[extension]);
+                    }
+                    dialog
                         .save_file()
                         .await
                         .map(|handle| handle.path().to_path_buf())
-                        .map(|target| (target, resolution, fps))
+                        .map(|target| (target, options))
                 },
                 self.export_channel.0.clone(),
             );
         }
+
+        /// Show the file-saving dialog and get the path to save the instructions to
+        /// if a file was selected
+        fn save(&self, future_helper: &FutureHelper) {
+            future_helper.execute_maybe_to(
+                async move {
+                    let file_type = FileType::Instructions;
+                    rfd::AsyncFileDialog::new()
+                        .add_filter(file_type.name(), file_type.extensions())
+                        .save_file()
+                        .await
+                        .map(|handle| handle.path().to_path_buf())
+                },
+                self.save_channel.0.clone(),
+            );
+        }
+
+        /// Show the file-saving dialog and get the path to export the current frame to
+        /// if a file was selected
+        fn export_svg(&self, future_helper: &FutureHelper) {
+            future_helper.execute_maybe_to(
+                async move {
+                    rfd::AsyncFileDialog::new()
+                        .add_filter("SVG", &["svg"])
+                        .save_file()
+                        .await
+                        .map(|handle| handle.path().to_path_buf())
+                },
+                self.svg_export_channel.0.clone(),
+            );
+        }
     }
 }
 
@@ -709,7 +807,7 @@ pub mod export {
 
     use egui::Context;
 
-    use crate::{app::AppState, future_helper::FutureHelper};
+    use crate::{app::AppState, errors::Errors, future_helper::FutureHelper};
 
     pub struct ExportMenu {}
 
@@ -718,9 +816,23 @@ pub mod export {
             Self {}
         }
 
-        pub fn process_events(&mut self, _state: &mut AppState) {}
+        pub fn process_events(&mut self, _state: &mut AppState, _errors: &mut Errors) {}
 
-        pub fn draw_button(&mut self, _enabled: bool, _ui: &mut egui::Ui) {}
+        pub fn draw_save_button(
+            &mut self,
+            _enabled: bool,
+            _future_helper: &FutureHelper,
+            _ui: &mut egui::Ui,
+        ) {
+        }
+
+        pub fn draw_button(
+            &mut self,
+            _enabled: bool,
+            _future_helper: &FutureHelper,
+            _ui: &mut egui::Ui,
+        ) {
+        }
 
         pub fn draw_windows(&mut self, _future_helper: &FutureHelper, _ctx: &Context) {}
     }
@@ -0,0 +1,51 @@
+//! [OpenedFiles] to resolve includes against the files the user opened.
+
+use std::{collections::HashMap, path::Path, str, sync::Arc};
+
+use naviz_parser::include::{self, Include, SourceFile};
+
+use crate::util::WEB;
+
+/// The files the user opened or dropped onto the application, by name.
+///
+/// Includes are resolved against these files first,
+/// as the web-build cannot read included files from a file-system.
+#[derive(Default)]
+pub struct OpenedFiles(HashMap<String, Arc<[u8]>>);
+
+impl OpenedFiles {
+    /// Remembers the file with the passed `name` (or path) and `contents`
+    pub fn insert(&mut self, name: impl Into<String>, contents: Arc<[u8]>) {
+        self.0.insert(name.into(), contents);
+    }
+
+    /// Resolves the `include` in the file `from`.
+    ///
+    /// Paths are looked up relative to `from` in the opened files.
+    /// Otherwise, native builds read the file from the file-system,
+    /// while the web-build looks for an opened file with the same file name
+    /// (files dropped onto the web-build do not have a directory).
+    /// Ids are not resolved.
+    pub fn resolve(&self, from: &str, include: &Include) -> Result<SourceFile, String> {
+        let Include::Path(included) = include else {
+            return include::file_system(from, include);
+        };
+
+        let path = include::relative_path(from, included);
+        let opened = self.0.get_key_value(&path).or_else(|| {
+            let file_name = Path::new(&path).file_name();
+            self.0
+                .iter()
+                .find(|(name, _)| WEB && Path::new(name).file_name() == file_name)
+        });
+
+        match opened {
+            Some((name, contents)) => Ok(SourceFile::new(
+                name.as_str(),
+                str::from_utf8(contents).map_err(|e| e.to_string())?,
+            )),
+            None if !WEB => include::read_relative(from, included),
+            None => Err("the file was not opened; open or drop it to include it".to_string()),
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use std::str::Utf8Error;
+use std::{fmt::Display, str::Utf8Error};
 
-use naviz_parser::input::concrete::Instructions;
+use naviz_parser::{diagnostic::Diagnostic, input::concrete::Instructions};
 
 pub mod mqt;
 pub mod separated_display;
@@ -29,11 +29,33 @@ pub enum ImportError {
     /// Something was not valid UTF-8
     InvalidUtf8(Utf8Error),
     /// An error occurred while parsing [mqt::na]
-    MqtNqParse(mqt::na::format::ParseErrorInner),
+    MqtNqParse(Box<Diagnostic>),
     /// An error occurred while converting [mqt::na]
     MqtNqConvert(mqt::na::convert::OperationConversionError),
 }
 
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUtf8(e) => e.fmt(f),
+            Self::MqtNqParse(diagnostic) => diagnostic.fmt(f),
+            Self::MqtNqConvert(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl ImportError {
+    /// The [Diagnostic] of this [ImportError], if it is a parse-error
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            Self::MqtNqParse(diagnostic) => Some(diagnostic),
+            _ => None,
+        }
+    }
+}
+
 impl ImportFormat {
     /// A human-readable name of this [ImportFormat]
     pub fn name(&self) -> &'static str {
@@ -70,15 +92,16 @@ impl ImportOptions {
     /// Imports the `data` using the options in `self`
     pub fn import(self, data: &[u8]) -> Result<Instructions, ImportError> {
         match self {
-            Self::MqtNa(options) => mqt::na::convert::convert(
-                &mqt::na::format::parse(
-                    std::str::from_utf8(data).map_err(ImportError::InvalidUtf8)?,
+            Self::MqtNa(options) => {
+                let source = std::str::from_utf8(data).map_err(ImportError::InvalidUtf8)?;
+                mqt::na::convert::convert(
+                    &mqt::na::format::parse(source).map_err(|e| {
+                        ImportError::MqtNqParse(Box::new(Diagnostic::from_str_error(source, &e)))
+                    })?,
+                    options,
                 )
-                .map_err(|e| e.into_inner())
-                .map_err(ImportError::MqtNqParse)?,
-                options,
-            )
-            .map_err(ImportError::MqtNqConvert),
+                .map_err(ImportError::MqtNqConvert)
+            }
         }
     }
 }
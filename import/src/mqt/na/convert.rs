@@ -3,8 +3,12 @@
 use std::{borrow::Cow, collections::HashMap};
 
 use fraction::{ConstZero, Fraction};
-use naviz_parser::input::concrete::{
-    InstructionGroup, Instructions, RelativeTimeline, SetupInstruction, TimedInstruction,
+use naviz_parser::input::{
+    concrete::{
+        InstructionGroup, Instructions, RelativeTimeline, SetupInstruction, Target,
+        TimedInstruction,
+    },
+    location::Location,
 };
 
 use super::format::{Number, Operation, OperationArgs, OperationList, Position};
@@ -101,7 +105,10 @@ pub fn convert(
                         operation,
                         &mut position_cache,
                         &options.global_zones,
-                    )?,
+                    )?
+                    .into_iter()
+                    .map(|instruction| (instruction, None))
+                    .collect(),
                 },
             ));
         }
@@ -131,6 +138,19 @@ pub enum OperationConversionError {
     MissingArgument,
 }
 
+impl std::fmt::Display for OperationConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NotATimedInstruction => "operation is not timed",
+            Self::InvalidName => "unknown operation",
+            Self::UnknownPosition => "no atom is known at the position",
+            Self::MismatchedArgumentLengths => "the position lists have different lengths",
+            Self::SuperfluousArgument => "operation does not take an argument",
+            Self::MissingArgument => "operation is missing an argument",
+        })
+    }
+}
+
 /// Tries to convert an [Operation] to a [TimedInstruction].
 /// Will use and update the passed [PositionCache]
 /// and use the passed [GlobalZoneNames].
@@ -195,6 +215,7 @@ fn convert_operation_instruction(
     targets: Vec<String>,
     argument: Option<Number>,
 ) -> Result<TimedInstruction, OperationConversionError> {
+    let targets = targets.into_iter().map(Target::Id).collect();
     Ok(match name {
         "cz" => {
             if argument.is_some() {
@@ -232,6 +253,12 @@ impl From<Position> for (Fraction, Fraction) {
     }
 }
 
+impl From<Position> for Location {
+    fn from(value: Position) -> Self {
+        Location::Position(value.into())
+    }
+}
+
 /// Creates a new id to initialize the atom at the passed `position`
 /// and insert it into the `position_cache`.
 /// Will use and increment the passed `counter`.
@@ -290,8 +317,9 @@ fn assert_same_lengths<A, B>(a: &[A], b: &[B]) -> Result<(), OperationConversion
 mod test {
     use std::sync::Arc;
 
-    use naviz_parser::input::concrete::{
-        InstructionGroup, Instructions, SetupInstruction, TimedInstruction,
+    use naviz_parser::input::{
+        concrete::{InstructionGroup, Instructions, SetupInstruction, Target, TimedInstruction},
+        location::Location,
     };
 
     use crate::mqt::na::{
@@ -388,19 +416,19 @@ mod test {
         let expected = Instructions {
             setup: vec![
                 SetupInstruction::Atom {
-                    position: (9.into(), 8.into()),
+                    position: Location::Position((9.into(), 8.into())),
                     id: "atom0".to_string(),
                 },
                 SetupInstruction::Atom {
-                    position: (1.into(), 2.into()),
+                    position: Location::Position((1.into(), 2.into())),
                     id: "atom1".to_string(),
                 },
                 SetupInstruction::Atom {
-                    position: (8.into(), 8.into()),
+                    position: Location::Position((8.into(), 8.into())),
                     id: "atom2".to_string(),
                 },
                 SetupInstruction::Atom {
-                    position: (0.into(), 0.into()),
+                    position: Location::Position((0.into(), 0.into())),
                     id: "atom3".to_string(),
                 },
             ],
@@ -411,15 +439,18 @@ mod test {
                     0.into(),
                     InstructionGroup {
                         variable: false,
-                        instructions: vec![TimedInstruction::Ry {
-                            value: 57.into(),
-                            targets: vec![
-                                "atom0".to_string(),
-                                "atom1".to_string(),
-                                "atom2".to_string(),
-                                "atom3".to_string(),
-                            ],
-                        }],
+                        instructions: vec![(
+                            TimedInstruction::Ry {
+                                value: 57.into(),
+                                targets: vec![
+                                    Target::Id("atom0".to_string()),
+                                    Target::Id("atom1".to_string()),
+                                    Target::Id("atom2".to_string()),
+                                    Target::Id("atom3".to_string()),
+                                ],
+                            },
+                            None,
+                        )],
                     },
                 )],
             )],
@@ -6,7 +6,8 @@ use fraction::{Decimal, Fraction};
 use operation::operation;
 use winnow::{
     ascii::{multispace0, Caseless},
-    combinator::{preceded, repeat, terminated},
+    combinator::{eof, preceded, repeat, terminated},
+    error::{StrContext, StrContextValue},
     stream::{AsBStr, AsChar, Compare, ParseSlice, Stream, StreamIsPartial},
     Parser,
 };
@@ -125,9 +126,14 @@ where
     I::Token: AsChar + Clone,
     I::IterOffsets: Clone,
 {
-    preceded(
-        multispace0,
-        repeat::<_, _, Vec<_>, _, _>(.., terminated(operation, multispace0)),
+    terminated(
+        preceded(
+            multispace0,
+            repeat::<_, _, Vec<_>, _, _>(.., terminated(operation, multispace0)),
+        ),
+        eof.context(StrContext::Expected(StrContextValue::Description(
+            "operation",
+        ))),
     )
     .output_into()
     .parse(input)
@@ -165,6 +165,19 @@ pub mod value {
     }
 }
 
+/// An arithmetic operator
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operator {
+    /// Addition (`+`)
+    Add,
+    /// Subtraction (`-`)
+    Subtract,
+    /// Multiplication (`*`)
+    Multiply,
+    /// Division (`/`)
+    Divide,
+}
+
 /// A generic token (that is common to all formats)
 /// Derived Tokens should be able to convert from [GenericToken] and to [`Option<GenericToken>`]
 pub enum GenericToken<T> {
@@ -184,6 +197,10 @@ pub enum GenericToken<T> {
     SetClose,
     /// A separator between elements (e.g., in tuples)
     ElementSeparator,
+    /// An arithmetic operator
+    Operator(Operator),
+    /// The assignment-symbol of a binding (`let <name> = <value>`)
+    Assign,
 }
 
 /// Functions to parse [GenericToken]s.
@@ -345,4 +362,36 @@ pub mod token {
             .output_into()
             .parse_next(input)
     }
+
+    /// Tries to parse a [GenericToken::Operator].
+    /// Note that a `-` directly followed by a digit is a negative [number][value::number]
+    /// and therefore has to be tried first.
+    pub fn operator<Tok, I: Stream + StreamIsPartial + Compare<&'static str>>(
+        input: &mut I,
+    ) -> ModalResult<Tok>
+    where
+        GenericToken<I::Slice>: Into<Tok>,
+    {
+        alt((
+            "+".value(Operator::Add),
+            "-".value(Operator::Subtract),
+            "*".value(Operator::Multiply),
+            "/".value(Operator::Divide),
+        ))
+        .map(GenericToken::Operator)
+        .output_into()
+        .parse_next(input)
+    }
+
+    /// Tries to parse a [GenericToken::Assign].
+    pub fn assign<Tok, I: Stream + StreamIsPartial + Compare<&'static str>>(
+        input: &mut I,
+    ) -> ModalResult<Tok>
+    where
+        GenericToken<I::Slice>: Into<Tok>,
+    {
+        "=".map(|_| GenericToken::Assign)
+            .output_into()
+            .parse_next(input)
+    }
 }
@@ -4,22 +4,28 @@
 //! - [Conversion from lexed Value to parsed Value][try_into_value::TryIntoValue]
 //! - Helper functions to parse values
 
-use super::{color::Color, lexer::GenericToken, percentage::Percentage};
-use fraction::Fraction;
+use super::{
+    color::Color,
+    lexer::{GenericToken, Operator},
+    percentage::Percentage,
+};
+use constants::Binding;
+use fraction::{Fraction, Zero};
 use regex::Regex;
 use std::fmt::Debug;
 use token::{
-    element_separator, ignore_comments, set_close, set_open, tuple_close, tuple_open,
-    value_or_identifier,
+    assign, element_separator, identifier, ignore_comments, operator, set_close, set_open,
+    tuple_close, tuple_open, value_or_identifier,
 };
 use try_into_value::TryIntoValue;
 use winnow::{
-    combinator::{alt, separated, terminated},
+    combinator::{alt, cut_err, preceded, repeat, separated, terminated},
     error::ParserError,
     stream::{Stream, StreamIsPartial},
     ModalResult, Parser,
 };
 
+pub mod constants;
 pub mod try_into_value;
 
 /// A parsed value.
@@ -43,6 +49,9 @@ pub enum Value {
     Set(Vec<Value>),
     /// A tuple
     Tuple(Vec<Value>),
+    /// An arithmetic operation on two values.
+    /// Has to be evaluated by the consumer (e.g., when the values of variables are known).
+    Operation(Box<Value>, Operator, Box<Value>),
 }
 
 /// For tests, allow comparing [Value]s.
@@ -60,6 +69,7 @@ impl PartialEq for Value {
             (Value::Identifier(a), Value::Identifier(b)) => a == b,
             (Value::Set(a), Value::Set(b)) => a == b,
             (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::Operation(a, o, b), Value::Operation(c, p, d)) => a == c && o == p && b == d,
             _ => false,
         }
     }
@@ -102,7 +112,7 @@ pub fn tuple<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue
         tuple_open,
         element_separator,
         tuple_close,
-        any_value,
+        expression,
         ignore_comments,
         Value::Tuple,
     )
@@ -117,7 +127,7 @@ pub fn set<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
         set_open,
         element_separator,
         set_close,
-        any_value,
+        expression,
         ignore_comments,
         Value::Set,
     )
@@ -126,11 +136,121 @@ pub fn set<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
 
 /// Try to parse any [Value] from the stream.
 /// Does not only parse single-token values like [value_or_identifier],
-/// but also parses composite values such as sets or tuples.
+/// but also parses composite values such as sets or tuples,
+/// and [expression]s.
 pub fn any_value<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
     input: &mut &[Tok],
 ) -> ModalResult<Value> {
-    alt((value_or_identifier, tuple, set)).parse_next(input)
+    expression.parse_next(input)
+}
+
+/// Try to parse an arithmetic expression of [Value]s
+/// joined by [Operator][GenericToken::Operator]s (respecting operator precedence).
+/// Operations are returned as [Value::Operation]s
+/// (see [evaluate][constants::evaluate]);
+/// a single operand is returned as-is.
+/// A one-element [tuple] can be used to group an expression (e.g., `(a + b) * 2`).
+pub fn expression<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
+    input: &mut &[Tok],
+) -> ModalResult<Value> {
+    (
+        term,
+        repeat(
+            0..,
+            (
+                terminated(
+                    operator.verify(|o| matches!(o, Operator::Add | Operator::Subtract)),
+                    ignore_comments,
+                ),
+                term,
+            ),
+        ),
+    )
+        .map(|(first, rest)| fold_operations(first, rest))
+        .parse_next(input)
+}
+
+/// Try to parse a term of an [expression]:
+/// operands joined by multiplicative [Operator][GenericToken::Operator]s.
+fn term<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
+    input: &mut &[Tok],
+) -> ModalResult<Value> {
+    (
+        operand,
+        repeat(
+            0..,
+            (
+                terminated(
+                    operator.verify(|o| matches!(o, Operator::Multiply | Operator::Divide)),
+                    ignore_comments,
+                ),
+                operand,
+            ),
+        ),
+    )
+        .map(|(first, rest)| fold_operations(first, rest))
+        .parse_next(input)
+}
+
+/// Try to parse an operand of an [expression].
+/// A leading `-` negates the operand (`-a` is parsed as `0 - a`).
+fn operand<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
+    input: &mut &[Tok],
+) -> ModalResult<Value> {
+    terminated(
+        alt((
+            value_or_identifier,
+            tuple,
+            set,
+            preceded(
+                terminated(
+                    operator.verify(|o| *o == Operator::Subtract),
+                    ignore_comments,
+                ),
+                operand,
+            )
+            .map(|value| {
+                Value::Operation(
+                    Box::new(Value::Number(Fraction::zero())),
+                    Operator::Subtract,
+                    Box::new(value),
+                )
+            }),
+        )),
+        ignore_comments,
+    )
+    .parse_next(input)
+}
+
+/// Folds the operands and operators into left-associative [Value::Operation]s
+fn fold_operations(first: Value, rest: Vec<(Operator, Value)>) -> Value {
+    rest.into_iter().fold(first, |a, (operator, b)| {
+        Value::Operation(Box::new(a), operator, Box::new(b))
+    })
+}
+
+/// Try to parse a binding (`let <name> = <expression>` or `const <name> = <expression>`).
+/// The expression is not evaluated (see [Constants][constants::Constants]).
+pub fn binding<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
+    input: &mut &[Tok],
+) -> ModalResult<(Binding, String, Value)> {
+    (
+        terminated(
+            identifier.verify_map(|keyword| match keyword.as_str() {
+                "let" => Some(Binding::Let),
+                "const" => Some(Binding::Const),
+                _ => None,
+            }),
+            ignore_comments,
+        ),
+        cut_err((
+            terminated(identifier, ignore_comments),
+            terminated(assign, ignore_comments),
+            expression,
+        )),
+    )
+        .map(|(binding, (name, _, value))| (binding, name, value))
+        .parse_next(input)
 }
 
 pub mod token {
@@ -243,6 +363,28 @@ pub mod token {
             .parse_next(input)
     }
 
+    /// Try to parse a single [GenericToken::Operator].
+    pub fn operator<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
+        input: &mut &[Tok],
+    ) -> ModalResult<Operator> {
+        one_of(|t: Tok| matches!(t.into(), Some(GenericToken::Operator(_))))
+            .output_into()
+            .map(|t| match t {
+                Some(GenericToken::Operator(o)) => o,
+                _ => unreachable!(), // Parser only matches operator
+            })
+            .parse_next(input)
+    }
+
+    /// Try to parse a single [GenericToken::Assign].
+    pub fn assign<Tok: Into<Option<GenericToken<S>>> + Clone + Debug, S: TryIntoValue>(
+        input: &mut &[Tok],
+    ) -> ModalResult<()> {
+        one_of(|t: Tok| matches!(t.into(), Some(GenericToken::Assign)))
+            .void()
+            .parse_next(input)
+    }
+
     /// Try to parse a single [GenericToken::ElementSeparator].
     pub fn element_separator<
         Tok: Into<Option<GenericToken<S>>> + Clone + Debug,
@@ -0,0 +1,258 @@
+//! Evaluation of arithmetic [Operation][Value::Operation]s
+//! (on numbers and element-wise on tuples)
+//! and [Constants] defined by bindings (`let <name> = <value>` or `const <name> = <value>`).
+
+use super::Value;
+use crate::common::lexer::Operator;
+use fraction::{Fraction, Zero};
+use std::{collections::HashMap, error::Error, fmt::Display};
+
+/// The kind of a binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// `let`: The constant may be redefined by a later `let`
+    Let,
+    /// `const`: The constant may not be redefined
+    Const,
+}
+
+/// An error while evaluating a [Value]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvaluationError {
+    /// An operand of an arithmetic operation is not a number
+    NonNumericOperand,
+    /// An arithmetic operation divides by zero
+    DivisionByZero,
+    /// A constant was bound to a value which is not a number
+    NonNumericConstant {
+        /// Name of the constant
+        name: String,
+    },
+    /// A constant defined using `const` was redefined
+    ConstantRedefinition {
+        /// Name of the constant
+        name: String,
+    },
+}
+
+impl Display for EvaluationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NonNumericOperand => {
+                f.write_str("operand of arithmetic operation is not a number")
+            }
+            Self::DivisionByZero => f.write_str("division by zero"),
+            Self::NonNumericConstant { name } => write!(f, "constant `{name}` is not a number"),
+            Self::ConstantRedefinition { name } => {
+                write!(f, "constant `{name}` was already defined using `const`")
+            }
+        }
+    }
+}
+
+impl Error for EvaluationError {}
+
+impl Operator {
+    /// Applies this [Operator] to the operands `a` and `b`.
+    /// Returns [None] when dividing by zero.
+    pub fn apply(self, a: Fraction, b: Fraction) -> Option<Fraction> {
+        match self {
+            Self::Add => Some(a + b),
+            Self::Subtract => Some(a - b),
+            Self::Multiply => Some(a * b),
+            Self::Divide => (!b.is_zero()).then(|| a / b),
+        }
+    }
+}
+
+/// Evaluates the [Operation][Value::Operation]s in the `value`
+/// (recursing into [Set][Value::Set]s and [Tuple][Value::Tuple]s).
+/// [Identifier][Value::Identifier]s are resolved using `identifier`
+/// (e.g., to replace constants by their values).
+/// Tuples containing only a single number are replaced by the number
+/// (i.e., they are used as parentheses).
+pub fn evaluate<E: From<EvaluationError>>(
+    value: Value,
+    identifier: &impl Fn(String) -> Result<Value, E>,
+) -> Result<Value, E> {
+    Ok(match value {
+        Value::Identifier(id) => identifier(id)?,
+        Value::Operation(a, operator, b) => operate(
+            evaluate(*a, identifier)?,
+            operator,
+            evaluate(*b, identifier)?,
+        )?,
+        Value::Set(values) => Value::Set(
+            values
+                .into_iter()
+                .map(|v| evaluate(v, identifier))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Tuple(values) => {
+            let values = values
+                .into_iter()
+                .map(|v| evaluate(v, identifier))
+                .collect::<Result<Vec<_>, _>>()?;
+            match values[..] {
+                [Value::Number(n)] => Value::Number(n),
+                _ => Value::Tuple(values),
+            }
+        }
+        value => value,
+    })
+}
+
+/// Applies the `operator` to the already evaluated operands `a` and `b`:
+/// - Numbers are calculated
+/// - Tuples of the same length are added or subtracted element-wise
+///   (e.g., `(1, 2) + (3, 0)`)
+/// - Adding a tuple to or subtracting a tuple from an unresolved
+///   [Identifier][Value::Identifier] keeps the [Operation][Value::Operation],
+///   such that it can be resolved later (e.g., `trap0 + (3, 0)`)
+fn operate(a: Value, operator: Operator, b: Value) -> Result<Value, EvaluationError> {
+    let additive = matches!(operator, Operator::Add | Operator::Subtract);
+    let unresolved = |value: &Value| matches!(value, Value::Identifier(_) | Value::Operation(..));
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => operator
+            .apply(a, b)
+            .map(Value::Number)
+            .ok_or(EvaluationError::DivisionByZero),
+        (Value::Tuple(a), Value::Tuple(b)) if additive && a.len() == b.len() => a
+            .into_iter()
+            .zip(b)
+            .map(|(a, b)| operate(a, operator, b))
+            .collect::<Result<_, _>>()
+            .map(Value::Tuple),
+        (a, b @ Value::Tuple(_)) if additive && unresolved(&a) => {
+            Ok(Value::Operation(Box::new(a), operator, Box::new(b)))
+        }
+        (a @ Value::Tuple(_), b) if operator == Operator::Add && unresolved(&b) => {
+            Ok(Value::Operation(Box::new(a), operator, Box::new(b)))
+        }
+        _ => Err(EvaluationError::NonNumericOperand),
+    }
+}
+
+/// Numeric constants defined by bindings.
+#[derive(Debug, Clone, Default)]
+pub struct Constants(HashMap<String, (Binding, Fraction)>);
+
+impl Constants {
+    /// Gets the value of the constant with the passed `name`
+    pub fn get(&self, name: &str) -> Option<Fraction> {
+        self.0.get(name).map(|(_, value)| *value)
+    }
+
+    /// [Evaluates][evaluate] the `value`,
+    /// replacing references to constants by their values.
+    pub fn evaluate(&self, value: Value) -> Result<Value, EvaluationError> {
+        evaluate(value, &|id| {
+            Ok(self.get(&id).map_or(Value::Identifier(id), Value::Number))
+        })
+    }
+
+    /// Defines the constant `name` with the already evaluated `value`.
+    /// The `value` has to be a number.
+    /// Constants defined using [Binding::Const] may not be redefined.
+    pub fn define(
+        &mut self,
+        binding: Binding,
+        name: String,
+        value: Value,
+    ) -> Result<(), EvaluationError> {
+        if let Some((Binding::Const, _)) = self.0.get(&name) {
+            return Err(EvaluationError::ConstantRedefinition { name });
+        }
+        match value {
+            Value::Number(n) => {
+                self.0.insert(name, (binding, n));
+                Ok(())
+            }
+            _ => Err(EvaluationError::NonNumericConstant { name }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluate_constants() {
+        let number = |n: u64| Box::new(Value::Number(Fraction::from(n)));
+        let identifier = |i: &str| Box::new(Value::Identifier(i.to_string()));
+
+        let mut constants = Constants::default();
+        constants
+            .define(Binding::Const, "pitch".to_string(), *number(3))
+            .expect("Failed to define constant");
+
+        // `(pitch + 1) / 3` inside a tuple
+        let value = Value::Tuple(vec![
+            Value::Operation(
+                Box::new(Value::Tuple(vec![Value::Operation(
+                    identifier("pitch"),
+                    Operator::Add,
+                    number(1),
+                )])),
+                Operator::Divide,
+                number(3),
+            ),
+            *identifier("atom0"),
+        ]);
+        assert_eq!(
+            constants.evaluate(value),
+            Ok(Value::Tuple(vec![
+                Value::Number(Fraction::new(4u64, 3u64)),
+                Value::Identifier("atom0".to_string()),
+            ]))
+        );
+
+        assert_eq!(
+            constants.evaluate(Value::Operation(number(1), Operator::Divide, number(0))),
+            Err(EvaluationError::DivisionByZero)
+        );
+        assert_eq!(
+            constants.evaluate(Value::Operation(
+                identifier("atom0"),
+                Operator::Add,
+                number(0)
+            )),
+            Err(EvaluationError::NonNumericOperand)
+        );
+        assert_eq!(
+            constants.evaluate(Value::Operation(
+                Box::new(Value::Operation(
+                    identifier("trap0"),
+                    Operator::Add,
+                    Box::new(Value::Tuple(vec![*number(1), *identifier("pitch")])),
+                )),
+                Operator::Subtract,
+                Box::new(Value::Tuple(vec![*number(1), *number(1)])),
+            )),
+            Ok(Value::Operation(
+                Box::new(Value::Operation(
+                    identifier("trap0"),
+                    Operator::Add,
+                    Box::new(Value::Tuple(vec![*number(1), *number(3)])),
+                )),
+                Operator::Subtract,
+                Box::new(Value::Tuple(vec![*number(1), *number(1)])),
+            ))
+        );
+        assert_eq!(
+            constants.evaluate(Value::Operation(
+                Box::new(Value::Tuple(vec![*number(1), *number(2)])),
+                Operator::Add,
+                Box::new(Value::Tuple(vec![*identifier("pitch"), *number(0)])),
+            )),
+            Ok(Value::Tuple(vec![*number(4), *number(2)]))
+        );
+        assert_eq!(
+            constants.define(Binding::Let, "pitch".to_string(), *number(1)),
+            Err(EvaluationError::ConstantRedefinition {
+                name: "pitch".to_string()
+            })
+        );
+    }
+}
@@ -36,11 +36,18 @@ pub struct Error {
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{}", self.kind)?;
-        Itertools::intersperse(self.path.iter().rev(), &" -> ").try_for_each(|e| write!(f, "{e}"))
+        write!(f, "{}", self.kind)?;
+        if !self.path.is_empty() {
+            f.write_str(" at ")?;
+            Itertools::intersperse(self.path.iter().rev(), &" -> ")
+                .try_for_each(|e| write!(f, "{e}"))?;
+        }
+        Ok(())
     }
 }
 
+impl std::error::Error for Error {}
+
 impl Error {
     /// The type of error
     pub fn kind(&self) -> &ErrorKind {
@@ -73,6 +73,8 @@ impl Config {
                     maps.set.push((s, value));
                     None
                 }
+                // Operations are evaluated by the parser
+                parser::Value::Operation(..) => unreachable!("Unevaluated operation in config"),
             },
             // Insert a block (parses as a new struct):
             parser::ConfigItem::Block(key, value) => {
@@ -142,6 +144,59 @@ pub fn get_item_struct<T: TryFrom<Config, Error = Error>>(
     .tag(name)
 }
 
+/// Get a value from a [Config].
+/// Will return [None] if not found
+/// and all errors the target-type returns during conversion using [TryInto::try_into].
+#[inline]
+pub fn get_optional_item<T>(config: &mut Config, name: &'static str) -> Result<Option<T>, Error>
+where
+    ConfigItem: TryInto<T, Error = Error>,
+{
+    config
+        .0
+        .remove(name)
+        .map(|item| item.try_into().tag(name))
+        .transpose()
+}
+
+/// Get a struct from a [Config].
+/// Will return [None] if not found
+/// and all errors the target-type returns during conversion using [TryInto::try_into].
+#[inline]
+pub fn get_optional_item_struct<T: TryFrom<Config, Error = Error>>(
+    config: &mut Config,
+    name: &'static str,
+) -> Result<Option<T>, Error> {
+    match config.0.remove(name) {
+        None => Ok(None),
+        Some(ConfigItem::Struct(s)) => s.try_into().map(Some),
+        Some(_) => Err(ErrorKind::WrongType("block").into()),
+    }
+    .tag(name)
+}
+
+/// Get all fields of a struct from a [Config] as a map from field-name to value.
+/// Will return an empty map if the struct does not exist
+/// and all errors the value-type returns during conversion using [TryInto::try_into].
+#[inline]
+pub fn get_optional_item_fields<T>(
+    config: &mut Config,
+    name: &'static str,
+) -> Result<HashMap<String, T>, Error>
+where
+    ConfigItem: TryInto<T, Error = Error>,
+{
+    match config.0.remove(name) {
+        None => Ok(HashMap::new()),
+        Some(ConfigItem::Struct(Config(fields, _))) => fields
+            .into_iter()
+            .map(|(key, value)| value.try_into().map(|value| (key, value)))
+            .collect(),
+        Some(_) => Err(ErrorKind::WrongType("block").into()),
+    }
+    .tag(name)
+}
+
 impl TryFrom<ConfigItem> for String {
     type Error = Error;
     fn try_from(value: ConfigItem) -> Result<Self, Self::Error> {
@@ -310,6 +365,28 @@ where
     .tag(name)
 }
 
+/// Get a map from in [Maps] of the struct in the specified field (by `name`),
+/// or an empty map if the field does not exist.
+///
+/// See [get_item_map].
+#[inline]
+pub fn get_optional_item_map<K, V, M, MI>(
+    config: &mut Config,
+    name: &'static str,
+) -> Result<M, Error>
+where
+    K: MappedProperty<Output = MI>,
+    V: FilteredFrom<Value>,
+    M: MapOrVec<K, V>,
+    MI: MapOrVec<K, Value>,
+{
+    if config.0.contains_key(name) {
+        get_item_map(config, name)
+    } else {
+        Ok(M::default())
+    }
+}
+
 /// A trait that marks types which are either a [Vec<(K, V)>] or a [HashMap<K, V>].
 pub trait MapOrVec<K, V>: IntoIterator<Item = (K, V)> + Default + Extend<(K, V)> {}
 impl<K, V> MapOrVec<K, V> for Vec<(K, V)> {}
@@ -2,13 +2,16 @@
 //!
 //! Use [lex] to lex some input.
 
+use std::ops::Range;
+
 use crate::{common, ParseError};
 use token::*;
 use winnow::{
     ascii::multispace0,
-    combinator::{delimited, repeat},
+    combinator::{delimited, eof, repeat, terminated},
+    error::{StrContext, StrContextValue},
     prelude::*,
-    stream::{AsChar, Compare, FindSlice, SliceLen, Stream, StreamIsPartial},
+    stream::{AsChar, Compare, FindSlice, LocatingSlice, SliceLen, Stream, StreamIsPartial},
 };
 
 // Re-export the common lexer
@@ -35,6 +38,12 @@ pub enum Token<T> {
     TupleClose,
     /// A separator between elements (e.g., in tuples)
     ElementSeparator,
+    /// An arithmetic operator
+    Operator(Operator),
+    /// The assignment-symbol of a binding (`let <name> = <value>`)
+    Assign,
+    /// A directive (`#<directive>`), e.g., `#include`
+    Directive(T),
 }
 
 impl<T> From<GenericToken<T>> for Token<T> {
@@ -49,6 +58,8 @@ impl<T> From<GenericToken<T>> for Token<T> {
             GenericToken::SetOpen => Self::BlockOrSetOpen,
             GenericToken::SetClose => Self::BlockOrSetClose,
             GenericToken::ElementSeparator => Self::ElementSeparator,
+            GenericToken::Operator(o) => Self::Operator(o),
+            GenericToken::Assign => Self::Assign,
         }
     }
 }
@@ -65,6 +76,8 @@ impl<T> From<Token<T>> for Option<GenericToken<T>> {
             Token::BlockOrSetOpen => Some(GenericToken::SetOpen),
             Token::BlockOrSetClose => Some(GenericToken::SetClose),
             Token::ElementSeparator => Some(GenericToken::ElementSeparator),
+            Token::Operator(o) => Some(GenericToken::Operator(o)),
+            Token::Assign => Some(GenericToken::Assign),
             _ => None,
         }
     }
@@ -86,7 +99,19 @@ where
     <I as Stream>::Token: AsChar + Clone,
     I::Slice: SliceLen,
 {
-    repeat(0.., delimited(multispace0, token, multispace0)).parse(input)
+    terminated(
+        repeat(0.., delimited(multispace0, token, multispace0)),
+        eof.context(StrContext::Expected(StrContextValue::Description("token"))),
+    )
+    .parse(input)
+}
+
+/// Lexes the `input` like [lex], but returns the byte-ranges of the lexed [Token]s.
+/// Can be used to locate errors of the [parser][crate::config::parser].
+pub fn token_spans(input: &str) -> Vec<Range<usize>> {
+    repeat(0.., delimited(multispace0, token.span(), multispace0))
+        .parse_next(&mut LocatingSlice::new(input))
+        .unwrap_or_default()
 }
 
 /// Lexers to lex individual [Token]s.
@@ -100,6 +125,7 @@ pub mod token {
     use winnow::{
         combinator::alt,
         stream::{AsChar, Compare, FindSlice, SliceLen},
+        token::take_while,
     };
 
     pub use common::lexer::token::*;
@@ -125,6 +151,20 @@ pub mod token {
         ":".map(|_| Token::Separator).parse_next(input)
     }
 
+    /// Tries to parse a [Token::Directive].
+    /// Valid directive characters are `[0-9a-zA-Z_]`.
+    /// Must be tried after [value] to not lex colors as directives.
+    pub fn directive<I: Stream + StreamIsPartial + Compare<&'static str>>(
+        input: &mut I,
+    ) -> ModalResult<Token<<I as Stream>::Slice>>
+    where
+        I::Token: AsChar + Clone,
+    {
+        ("#", take_while(1.., ('0'..='9', 'a'..='z', 'A'..='Z', '_')))
+            .map(|(_, directive)| Token::Directive(directive))
+            .parse_next(input)
+    }
+
     /// Tries to parse any [Token].
     pub fn token<
         I: Stream
@@ -149,7 +189,10 @@ pub mod token {
             element_separator,
             comment,
             value,
+            directive,
             identifier,
+            operator,
+            assign,
         ))
         .parse_next(input)
     }
@@ -162,6 +205,7 @@ mod test {
     #[test]
     pub fn simple_example() {
         let input = r#"
+        #include "shared.namachine"
         property1: "some string"
         property2: 1.2
         ^regex$ /* comment */ : identifier // other comment
@@ -175,6 +219,8 @@ mod test {
         "#;
 
         let expected = vec![
+            Token::Directive("include"),
+            Token::Value(Value::String("shared.namachine")),
             Token::Identifier("property1"),
             Token::Separator,
             Token::Value(Value::String("some string")),
@@ -2,8 +2,11 @@
 //! See documentation of file-format.
 
 use super::{
-    error::Error,
-    generic::{get_item, get_item_named_struct, get_item_struct, Config},
+    error::{Error, ErrorKind, TagError},
+    generic::{
+        get_item, get_item_named_struct, get_item_struct, get_optional_item,
+        get_optional_item_fields, get_optional_item_map, Config,
+    },
     parser::Value,
     position::Position,
 };
@@ -68,6 +71,13 @@ pub struct TimeConfig {
     pub ry: Fraction,
     pub rz: Fraction,
     pub cz: Fraction,
+    /// Time of measurements; defaults to `0`
+    pub measure: Fraction,
+    /// Times of multi-qubit cz-operations by their number of qubits.
+    /// Falls back to [TimeConfig::cz] for missing numbers of qubits.
+    pub mcz: HashMap<usize, Fraction>,
+    /// Times of generic gates by their name
+    pub gate: HashMap<String, Fraction>,
     pub unit: String,
 }
 
@@ -80,11 +90,27 @@ impl TryFrom<Config> for TimeConfig {
             ry: get_item(&mut value, "ry")?,
             rz: get_item(&mut value, "rz")?,
             cz: get_item(&mut value, "cz")?,
+            measure: get_optional_item(&mut value, "measure")?.unwrap_or_default(),
+            mcz: get_optional_item_map::<Fraction, Fraction, Vec<_>, _>(&mut value, "mcz")?
+                .into_iter()
+                .map(|(qubits, time)| Ok((qubit_count(qubits)?, time)))
+                .collect::<Result<_, Error>>()
+                .tag("mcz")?,
+            gate: get_optional_item_fields(&mut value, "gate")?,
             unit: get_item(&mut value, "unit")?,
         })
     }
 }
 
+/// Converts a number of qubits from a [Fraction] into a [usize].
+/// Returns [ErrorKind::WrongType] if it is not a non-negative integer.
+fn qubit_count(qubits: Fraction) -> Result<usize, Error> {
+    match (qubits.is_sign_positive(), qubits.denom(), qubits.numer()) {
+        (true, Some(1), Some(&qubits)) => Ok(qubits as usize),
+        _ => Err(ErrorKind::WrongType("integer").into()),
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, Clone)]
 pub struct DistanceConfig {
@@ -157,6 +183,15 @@ mod test {
                 ry: Fraction::new(1u64, 10u64),
                 rz: Fraction::new(3u64, 1u64),
                 cz: Fraction::new(1u64, 1u64),
+                measure: Fraction::new(5u64, 1u64),
+                mcz: HashMap::from([
+                    (3, Fraction::new(3u64, 2u64)),
+                    (4, Fraction::new(2u64, 1u64)),
+                ]),
+                gate: HashMap::from([
+                    ("h".to_string(), Fraction::new(1u64, 2u64)),
+                    ("u3".to_string(), Fraction::new(2u64, 1u64)),
+                ]),
                 unit: "us".to_string(),
             },
             distance: DistanceConfig {
@@ -202,4 +237,36 @@ mod test {
 
         assert_eq!(config, expected);
     }
+
+    #[test]
+    fn parentheses() {
+        let input = r#"
+            const pitch = 14
+            let r = (pitch + 1)
+            name: "Parentheses"
+            movement { max_speed: (2 * 15) }
+            time { load: r store: (r) ry: 1 rz: 1 cz: 1 unit: "us" }
+            distance { interaction: (r - 3) / 2 unit: "um" }
+            zone zone0 { from: (0, 0) to: (2 * r, (r)) }
+            trap trap0 { position: ((pitch), 0) }
+        "#;
+
+        let lexed = lexer::lex(input).expect("Failed to lex");
+        let parsed = parser::parse(lexed.as_slice()).expect("Failed to parse");
+        let generic: Config = parsed.into();
+        let config: MachineConfig = generic.try_into().expect("Failed to load config");
+
+        assert_eq!(config.movement.max_speed, Fraction::from(30));
+        assert_eq!(config.time.load, Fraction::from(15));
+        assert_eq!(config.time.store, Fraction::from(15));
+        assert_eq!(config.distance.interaction, Fraction::from(6));
+        assert_eq!(
+            config.zone["zone0"].to,
+            (Fraction::from(30), Fraction::from(15))
+        );
+        assert_eq!(
+            config.trap["trap0"].position,
+            (Fraction::from(14), Fraction::from(0))
+        );
+    }
 }
@@ -1,10 +1,13 @@
 use super::lexer::Token;
 use crate::{common, ParseError};
+use constants::Constants;
 use std::fmt::Debug;
 use token::{block_close, block_open, identifier, ignore_comments, separator};
 use try_into_value::TryIntoValue;
-use winnow::combinator::{alt, preceded, repeat, terminated};
+use winnow::combinator::{alt, eof, opt, terminated};
+use winnow::error::{ContextError, ErrMode, FromExternalError, StrContext, StrContextValue};
 use winnow::prelude::*;
+use winnow::stream::Stream;
 
 // Re-export the common parser
 pub use common::parser::*;
@@ -25,70 +28,131 @@ pub enum ConfigItem {
 pub type Config = Vec<ConfigItem>;
 
 /// Parse a full stream of [Token]s into a [Config].
+/// Bindings (`let <name> = <value>` or `const <name> = <value>`) are evaluated
+/// and references to them are replaced by their values
+/// (see [Constants][constants::Constants]).
 pub fn parse<S: TryIntoValue + Clone + Debug + PartialEq>(
     input: &[Token<S>],
 ) -> Result<Config, ParseError<&[Token<S>]>> {
-    config.parse(input)
-}
-
-/// Try to parse a [Config] from a stream of [Token]s.
-pub fn config<S: TryIntoValue + Clone + Debug + PartialEq>(
-    input: &mut &[Token<S>],
-) -> ModalResult<Config> {
-    preceded(
-        ignore_comments,
-        repeat(0.., terminated(config_item, ignore_comments)),
+    let constants = Constants::default();
+    let config = terminated(
+        config(&constants),
+        eof.context(StrContext::Expected(StrContextValue::Description(
+            "config item",
+        ))),
     )
-    .parse_next(input)
+    .parse(input);
+    config
 }
 
-/// Try to parse a single [ConfigItem] from a stream of [Token]s.
-pub fn config_item<S: TryIntoValue + Clone + Debug + PartialEq>(
-    input: &mut &[Token<S>],
-) -> ModalResult<ConfigItem> {
-    alt((property, block, named_block)).parse_next(input)
+/// Create a parser for a [Config] with the `constants` defined in the surrounding blocks.
+/// Constants bound in this [Config] are only visible in this [Config]
+/// after their binding.
+pub fn config<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    constants: &Constants,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<Config> + '_ {
+    move |input| {
+        let mut constants = constants.clone();
+        let mut items = Vec::new();
+        ignore_comments.parse_next(input)?;
+        loop {
+            let start = input.checkpoint();
+            if let Some((binding, name, value)) =
+                opt(terminated(binding, ignore_comments)).parse_next(input)?
+            {
+                constants
+                    .evaluate(value)
+                    .and_then(|value| constants.define(binding, name, value))
+                    .map_err(|e| {
+                        input.reset(&start);
+                        ErrMode::Cut(ContextError::from_external_error(input, e))
+                    })?;
+                continue;
+            }
+            match opt(terminated(config_item(&constants), ignore_comments)).parse_next(input)? {
+                Some(item) => items.push(item),
+                None => return Ok(items),
+            }
+        }
+    }
 }
 
-/// Try to parse a [ConfigItem::Property] from a stream of [Token]s.
-pub fn property<S: TryIntoValue + Clone + Debug + PartialEq>(
-    input: &mut &[Token<S>],
-) -> ModalResult<ConfigItem> {
-    (
-        terminated(any_value, ignore_comments),
-        terminated(separator, ignore_comments),
-        any_value,
-    )
-        .map(|(k, _, v)| ConfigItem::Property(k, v))
+/// Create a parser for a single [ConfigItem] with the passed `constants`.
+pub fn config_item<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    constants: &Constants,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<ConfigItem> + '_ {
+    move |input| {
+        alt((
+            property(constants),
+            block(constants),
+            named_block(constants),
+        ))
         .parse_next(input)
+    }
 }
 
-/// Try to parse a [ConfigItem::Block] from a stream of [Token]s.
-pub fn block<S: TryIntoValue + Clone + Debug + PartialEq>(
-    input: &mut &[Token<S>],
-) -> ModalResult<ConfigItem> {
-    (
-        terminated(identifier, ignore_comments),
-        terminated(block_open, ignore_comments),
-        terminated(config, ignore_comments),
-        block_close,
-    )
-        .map(|(i, _, c, _)| ConfigItem::Block(i, c))
-        .parse_next(input)
+/// Create a parser for a [Value] which is [evaluated][Constants::evaluate]
+/// using the passed `constants`.
+/// Fails without backtracking if the evaluation fails.
+pub fn evaluated_value<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    constants: &Constants,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<Value> + '_ {
+    move |input| {
+        let start = input.checkpoint();
+        let value = any_value.parse_next(input)?;
+        constants.evaluate(value).map_err(|e| {
+            input.reset(&start);
+            ErrMode::Cut(ContextError::from_external_error(input, e))
+        })
+    }
 }
 
-/// Try to parse a [ConfigItem::NamedBlock] from a stream of [Token]s.
-pub fn named_block<S: TryIntoValue + Clone + Debug + PartialEq>(
-    input: &mut &[Token<S>],
-) -> ModalResult<ConfigItem> {
-    (
-        terminated(identifier, ignore_comments),
-        terminated(any_value, ignore_comments),
-        terminated(block_open, ignore_comments),
-        terminated(config, ignore_comments),
-        block_close,
-    )
-        .map(|(i, n, _, c, _)| ConfigItem::NamedBlock(i, n, c))
-        .parse_next(input)
+/// Create a parser for a [ConfigItem::Property] with the passed `constants`.
+pub fn property<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    constants: &Constants,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<ConfigItem> + '_ {
+    move |input| {
+        (
+            terminated(any_value, ignore_comments),
+            terminated(separator, ignore_comments),
+            evaluated_value(constants),
+        )
+            .map(|(k, _, v)| ConfigItem::Property(k, v))
+            .parse_next(input)
+    }
+}
+
+/// Create a parser for a [ConfigItem::Block] with the passed `constants`.
+pub fn block<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    constants: &Constants,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<ConfigItem> + '_ {
+    move |input| {
+        (
+            terminated(identifier, ignore_comments),
+            terminated(block_open, ignore_comments),
+            terminated(config(constants), ignore_comments),
+            block_close,
+        )
+            .map(|(i, _, c, _)| ConfigItem::Block(i, c))
+            .parse_next(input)
+    }
+}
+
+/// Create a parser for a [ConfigItem::NamedBlock] with the passed `constants`.
+pub fn named_block<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    constants: &Constants,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<ConfigItem> + '_ {
+    move |input| {
+        (
+            terminated(identifier, ignore_comments),
+            terminated(any_value, ignore_comments),
+            terminated(block_open, ignore_comments),
+            terminated(config(constants), ignore_comments),
+            block_close,
+        )
+            .map(|(i, n, _, c, _)| ConfigItem::NamedBlock(i, n, c))
+            .parse_next(input)
+    }
 }
 
 /// Parse single [Token]s into their abstract config counterparts.
@@ -226,4 +290,72 @@ mod test {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    pub fn bindings() {
+        let input = r#"
+            const pitch = 3
+            let offset = pitch / 2
+            position: (pitch * 2, -offset)
+            block {
+                let offset = (offset + 1) * pitch
+                value: offset
+            }
+            value: offset
+        "#;
+
+        let number = |n: u64, d: u64| Value::Number(Fraction::new(n, d));
+        let expected = vec![
+            ConfigItem::Property(
+                Value::Identifier("position".to_string()),
+                Value::Tuple(vec![
+                    number(6, 1),
+                    Value::Number(Fraction::new_neg(3u64, 2u64)),
+                ]),
+            ),
+            ConfigItem::Block(
+                "block".to_string(),
+                vec![ConfigItem::Property(
+                    Value::Identifier("value".to_string()),
+                    number(15, 2),
+                )],
+            ),
+            ConfigItem::Property(Value::Identifier("value".to_string()), number(3, 2)),
+        ];
+
+        let tokens = super::super::lexer::lex(input).expect("Failed to lex");
+        let actual = parse(&tokens).expect("Failed to parse");
+
+        assert_eq!(actual, expected);
+
+        let tokens = super::super::lexer::lex("const a = 1\nlet a = 2").expect("Failed to lex");
+        parse(&tokens).expect_err("Redefined constant");
+    }
+
+    #[test]
+    pub fn bindings_do_not_replace_keys() {
+        let input = r#"
+            const radius = 2
+            const zone0 = 1
+            radius: radius
+            zone zone0 {}
+        "#;
+
+        let expected = vec![
+            ConfigItem::Property(
+                Value::Identifier("radius".to_string()),
+                Value::Number(Fraction::from(2)),
+            ),
+            ConfigItem::NamedBlock(
+                "zone".to_string(),
+                Value::Identifier("zone0".to_string()),
+                vec![],
+            ),
+        ];
+
+        let tokens = super::super::lexer::lex(input).expect("Failed to lex");
+        let actual = parse(&tokens).expect("Failed to parse");
+
+        assert_eq!(actual, expected);
+    }
 }
@@ -5,11 +5,15 @@ use crate::common::{color::Color, percentage::Percentage};
 
 use super::{
     error::{Error, ErrorKind},
-    generic::{get_item, get_item_map, get_item_named_struct, get_item_struct, Config, ConfigItem},
+    generic::{
+        get_item, get_item_map, get_item_named_struct, get_item_struct, get_optional_item_fields,
+        get_optional_item_struct, Config, ConfigItem,
+    },
     parser::Value,
 };
 use fraction::Fraction;
 use regex::Regex;
+use std::collections::HashMap;
 
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, Clone)]
@@ -22,6 +26,8 @@ pub struct VisualConfig {
     pub coordinate: CoordinateConfig,
     pub sidebar: SidebarConfig,
     pub time: TimeConfig,
+    /// Config for captions, or [None] to draw captions like the atom labels at the top
+    pub caption: Option<CaptionConfig>,
     pub viewport: ViewportConfig,
 }
 
@@ -37,6 +43,7 @@ impl TryFrom<Config> for VisualConfig {
             coordinate: get_item_struct(&mut value, "coordinate")?,
             sidebar: get_item_struct(&mut value, "sidebar")?,
             time: get_item_struct(&mut value, "time")?,
+            caption: get_optional_item_struct(&mut value, "caption")?,
             viewport: get_item_struct(&mut value, "viewport")?,
         })
     }
@@ -49,6 +56,8 @@ pub struct AtomConfig {
     pub shuttling: ShuttlingConfig,
     pub legend: AtomLegendConfig,
     pub radius: Fraction,
+    /// Config for lost atoms, or [None] to remove lost atoms immediately
+    pub lost: Option<LostConfig>,
 }
 
 impl TryFrom<Config> for AtomConfig {
@@ -59,6 +68,23 @@ impl TryFrom<Config> for AtomConfig {
             shuttling: get_item_struct(&mut value, "shuttling")?,
             legend: get_item_struct(&mut value, "legend")?,
             radius: get_item(&mut value, "radius")?,
+            lost: get_optional_item_struct(&mut value, "lost")?,
+        })
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone)]
+pub struct LostConfig {
+    /// Duration of the fade-out of lost atoms
+    pub fade: Fraction,
+}
+
+impl TryFrom<Config> for LostConfig {
+    type Error = Error;
+    fn try_from(mut value: Config) -> Result<Self, Self::Error> {
+        Ok(Self {
+            fade: get_item(&mut value, "fade")?,
         })
     }
 }
@@ -266,6 +292,13 @@ pub struct OperationConfigConfig {
     pub ry: OperationConfigConfigConfig,
     pub rz: OperationConfigConfigConfig,
     pub cz: OperationConfigConfigConfig,
+    /// Config for atoms in interaction-clusters of the wrong size,
+    /// or [None] to use a default
+    pub invalid: Option<OperationConfigConfigConfig>,
+    /// Config for measurements, or [None] to not visualize measurements
+    pub measure: Option<OperationConfigConfigConfig>,
+    /// Configs of generic gates by their name
+    pub gate: HashMap<String, OperationConfigConfigConfig>,
 }
 
 impl TryFrom<Config> for OperationConfigConfig {
@@ -275,6 +308,9 @@ impl TryFrom<Config> for OperationConfigConfig {
             ry: get_item_struct(&mut value, "ry")?,
             rz: get_item_struct(&mut value, "rz")?,
             cz: get_item_struct(&mut value, "cz")?,
+            invalid: get_optional_item_struct(&mut value, "invalid")?,
+            measure: get_optional_item_struct(&mut value, "measure")?,
+            gate: get_optional_item_fields(&mut value, "gate")?,
         })
     }
 }
@@ -298,6 +334,16 @@ impl TryFrom<Config> for OperationConfigConfigConfig {
     }
 }
 
+impl TryFrom<ConfigItem> for OperationConfigConfigConfig {
+    type Error = Error;
+    fn try_from(value: ConfigItem) -> Result<Self, Self::Error> {
+        match value {
+            ConfigItem::Struct(s) => s.try_into(),
+            _ => Err(ErrorKind::WrongType("block").into()),
+        }
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, Clone, Copy)]
 pub enum NumberOrPercentage {
@@ -595,6 +641,24 @@ impl TryFrom<Config> for TimeConfig {
     }
 }
 
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone)]
+pub struct CaptionConfig {
+    /// Where to place captions without a position
+    pub position: TopBottomPosition,
+    pub font: FontConfig,
+}
+
+impl TryFrom<Config> for CaptionConfig {
+    type Error = Error;
+    fn try_from(mut value: Config) -> Result<Self, Self::Error> {
+        Ok(Self {
+            position: get_item(&mut value, "position")?,
+            font: get_item_struct(&mut value, "font")?,
+        })
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, Clone)]
 pub struct ViewportConfig {
@@ -657,6 +721,9 @@ mod test {
                     },
                 },
                 radius: Fraction::new(32u64, 1u64),
+                lost: Some(LostConfig {
+                    fade: Fraction::new(1u64, 2u64),
+                }),
             },
             zone: ZoneConfig {
                 config: vec![
@@ -738,6 +805,45 @@ mod test {
                         name: "cz".to_string(),
                         radius: NumberOrPercentage::Number(Fraction::new(13u64, 1u64)),
                     },
+                    invalid: Some(OperationConfigConfigConfig {
+                        color: Color {
+                            r: 255,
+                            g: 0,
+                            b: 255,
+                            a: 255,
+                        },
+                        name: "invalid".to_string(),
+                        radius: NumberOrPercentage::Percentage(Percentage(Fraction::new(
+                            150u64, 1u64,
+                        ))),
+                    }),
+                    measure: Some(OperationConfigConfigConfig {
+                        color: Color {
+                            r: 255,
+                            g: 255,
+                            b: 0,
+                            a: 255,
+                        },
+                        name: "measure".to_string(),
+                        radius: NumberOrPercentage::Percentage(Percentage(Fraction::new(
+                            110u64, 1u64,
+                        ))),
+                    }),
+                    gate: HashMap::from([(
+                        "h".to_string(),
+                        OperationConfigConfigConfig {
+                            color: Color {
+                                r: 255,
+                                g: 0,
+                                b: 0,
+                                a: 255,
+                            },
+                            name: "H".to_string(),
+                            radius: NumberOrPercentage::Percentage(Percentage(Fraction::new(
+                                120u64, 1u64,
+                            ))),
+                        },
+                    )]),
                 },
                 legend: LegendConfig {
                     display: true,
@@ -868,6 +974,19 @@ mod test {
                     },
                 },
             },
+            caption: Some(CaptionConfig {
+                position: TopBottomPosition::Bottom,
+                font: FontConfig {
+                    family: "Caption Font".to_string(),
+                    size: Fraction::new(16u64, 1u64),
+                    color: Color {
+                        r: 0,
+                        g: 0,
+                        b: 0,
+                        a: 255,
+                    },
+                },
+            }),
             viewport: ViewportConfig {
                 margin: Fraction::new(4u64, 1u64),
                 color: Color {
@@ -0,0 +1,222 @@
+//! Human-readable [Diagnostic]s for errors in some source-text.
+//!
+//! Use [Diagnostic::from_str_error] for errors of parsers operating directly on the source
+//! (e.g., the lexers)
+//! and [Diagnostic::from_token_error] for errors of parsers operating on lexed tokens.
+
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use winnow::error::{ContextError, StrContext};
+
+use crate::ParseError;
+
+/// A located error in some source-text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The name of the file the source was read from, if known
+    pub file: Option<String>,
+    /// The line of the error (`1`-based)
+    pub line: usize,
+    /// The column of the error in characters (`1`-based)
+    pub column: usize,
+    /// The byte-range of the error in the source
+    pub span: Range<usize>,
+    /// A short description of the error
+    pub message: String,
+    /// Descriptions of what was expected at the location of the error
+    pub expected: Vec<String>,
+    /// The line of the error with a caret-line below it marking the span
+    pub snippet: String,
+}
+
+impl Diagnostic {
+    /// Creates a new [Diagnostic] with the passed `message`
+    /// for the byte-range `span` in the `source`.
+    pub fn new(source: &str, span: Range<usize>, message: impl Into<String>) -> Self {
+        let mut start = span.start.min(source.len());
+        while !source.is_char_boundary(start) {
+            start -= 1;
+        }
+        let end = span.end.clamp(start, source.len());
+
+        let line_start = source[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |idx| start + idx);
+        let prefix = &source[line_start..start];
+
+        // Keep tabs so the caret lines up with the source-line
+        let indent: String = prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = source[start..end.min(line_end)].chars().count().max(1);
+        let snippet = format!(
+            "{}\n{indent}{}",
+            source[line_start..line_end].trim_end_matches('\r'),
+            "^".repeat(carets)
+        );
+
+        Self {
+            file: None,
+            line: source[..start].matches('\n').count() + 1,
+            column: prefix.chars().count() + 1,
+            span: start..end,
+            message: message.into(),
+            expected: Vec::new(),
+            snippet,
+        }
+    }
+
+    /// Creates a [Diagnostic] from the `error` of a parser operating directly on the `source`.
+    pub fn from_str_error(source: &str, error: &ParseError<&str>) -> Self {
+        let start = error.offset();
+        let end = source[start..]
+            .chars()
+            .next()
+            .map_or(start, |c| start + c.len_utf8());
+        Self::new(
+            source,
+            start..end,
+            message(error.inner(), "unexpected input"),
+        )
+        .with_expected(error.inner())
+    }
+
+    /// Creates a [Diagnostic] from the `error` of a parser operating on tokens lexed from `source`.
+    /// `token_spans` are the byte-ranges of the tokens in the `source`
+    /// (see e.g. [input::lexer::token_spans][crate::input::lexer::token_spans]).
+    /// Errors after the last token point to the end of the `source`.
+    pub fn from_token_error<T>(
+        source: &str,
+        token_spans: &[Range<usize>],
+        error: &ParseError<&[T]>,
+    ) -> Self {
+        let span = token_spans
+            .get(error.offset())
+            .cloned()
+            .unwrap_or(source.len()..source.len());
+        Self::from_token_error_at(source, span, error)
+    }
+
+    /// Creates a [Diagnostic] from the `error` of a parser operating on tokens,
+    /// where the token the error occurred at was already located at `span` in the `source`
+    /// (e.g., by [Sources][crate::include::Sources]).
+    pub(crate) fn from_token_error_at<T>(
+        source: &str,
+        span: Range<usize>,
+        error: &ParseError<&[T]>,
+    ) -> Self {
+        Self::new(source, span, message(error.inner(), "unexpected token"))
+            .with_expected(error.inner())
+    }
+
+    /// Sets the name of the file the source was read from
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Adds the expected values from the contexts of the `error`
+    fn with_expected(mut self, error: &ContextError) -> Self {
+        self.expected = error
+            .context()
+            .filter_map(|context| match context {
+                StrContext::Expected(expected) => Some(expected.to_string()),
+                _ => None,
+            })
+            .collect();
+        self
+    }
+}
+
+/// The message for the `error`:
+/// Its cause (e.g., a failed evaluation) if it has one, or the passed `default` otherwise
+fn message(error: &ContextError, default: &str) -> String {
+    error
+        .cause()
+        .map_or_else(|| default.to_string(), ToString::to_string)
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.line.to_string();
+        let gutter = " ".repeat(line.len());
+        let file = self.file.as_deref().unwrap_or("<input>");
+
+        writeln!(f, "{}", self.message)?;
+        writeln!(f, "{gutter}--> {file}:{}:{}", self.line, self.column)?;
+        writeln!(f, "{gutter} |")?;
+        let mut snippet = self.snippet.lines();
+        if let Some(source) = snippet.next() {
+            writeln!(f, "{line} | {source}")?;
+        }
+        for carets in snippet {
+            write!(f, "{gutter} | {carets}")?;
+        }
+        match self.expected.as_slice() {
+            [] => Ok(()),
+            [expected] => write!(f, "\n{gutter} = expected {expected}"),
+            expected => write!(f, "\n{gutter} = expected one of {}", expected.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config, input};
+
+    #[test]
+    fn location() {
+        let source = "first\nsecond line\n\tthird";
+        let diagnostic = Diagnostic::new(source, 13..17, "message").with_file("file");
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.column, 8);
+        assert_eq!(diagnostic.snippet, "second line\n       ^^^^");
+        assert_eq!(
+            diagnostic.to_string(),
+            "message\n --> file:2:8\n  |\n2 | second line\n  |        ^^^^"
+        );
+
+        let diagnostic = Diagnostic::new(source, 19..20, "message");
+        assert_eq!((diagnostic.line, diagnostic.column), (3, 2));
+        assert_eq!(diagnostic.snippet, "\tthird\n\t^");
+    }
+
+    #[test]
+    fn input_parse_error() {
+        let source = "atom (0, 0) atom0\n@0 load atom0\n@1 move (1, 1 atom0\n";
+        let tokens = input::lexer::lex(source).expect("Failed to lex");
+        let error = input::parser::parse(&tokens).expect_err("Parsed invalid input");
+        let diagnostic =
+            Diagnostic::from_token_error(source, &input::lexer::token_spans(source), &error);
+        assert_eq!((diagnostic.line, diagnostic.column), (3, 1));
+        assert_eq!(diagnostic.snippet, "@1 move (1, 1 atom0\n^");
+        assert_eq!(diagnostic.expected, ["instruction or directive"]);
+    }
+
+    #[test]
+    fn config_lex_error() {
+        let source = "name: \"test\"\nvalue: ?\n";
+        let error = config::lexer::lex(source).expect_err("Lexed invalid input");
+        let diagnostic = Diagnostic::from_str_error(source, &error);
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 8));
+        assert_eq!(diagnostic.snippet, "value: ?\n       ^");
+        assert_eq!(diagnostic.expected, ["token"]);
+    }
+
+    #[test]
+    fn config_evaluation_error() {
+        let source = "let a = 1\nvalue: (2, a / 0)\n";
+        let tokens = config::lexer::lex(source).expect("Failed to lex");
+        let error = config::parser::parse(&tokens).expect_err("Parsed invalid input");
+        let diagnostic =
+            Diagnostic::from_token_error(source, &config::lexer::token_spans(source), &error);
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 8));
+        assert_eq!(diagnostic.message, "division by zero");
+    }
+}
@@ -0,0 +1,636 @@
+//! Composing files using `#include`-directives.
+//!
+//! [Sources::load] loads a file and all files it includes (recursively)
+//! using a [Resolver].
+//! Includes are resolved on the level of tokens:
+//! The tokens of an included file replace the `#include`-directive
+//! in the [token-stream][Sources::tokens].
+//! [Sources] keep track of which file each token originated from,
+//! which allows to create [Diagnostic]s pointing into the included files.
+
+use std::{
+    fmt::{self, Display},
+    marker::PhantomData,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{config, diagnostic::Diagnostic, input, ParseError};
+
+/// What an `#include`-directive refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Include {
+    /// A path (`#include "<path>"`), relative to the including file
+    Path(String),
+    /// The id of a config in the repository (`#include <id>`).
+    /// Only allowed in configs.
+    Id(String),
+}
+
+impl Display for Include {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "\"{path}\""),
+            Self::Id(id) => f.write_str(id),
+        }
+    }
+}
+
+/// A file which can be loaded by [Sources::load]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Uniquely identifies the file (e.g., its normalized path).
+    /// Used to detect include-cycles
+    /// and passed to the [Resolver] to resolve includes relative to this file.
+    pub id: String,
+    /// The name of the file to show in [Diagnostic]s
+    pub name: String,
+    /// The contents of the file
+    pub source: String,
+}
+
+impl SourceFile {
+    /// Creates a new [SourceFile] which uses its `name` as its [id][SourceFile::id]
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            id: name.clone(),
+            name,
+            source: source.into(),
+        }
+    }
+}
+
+/// Resolves [Include]s to [SourceFile]s
+pub trait Resolver {
+    /// Resolves the `include` in the file with the [id][SourceFile::id] `from`.
+    /// Returns a description of the problem if the `include` could not be resolved.
+    fn resolve(&mut self, from: &str, include: &Include) -> Result<SourceFile, String>;
+}
+
+impl<F: FnMut(&str, &Include) -> Result<SourceFile, String>> Resolver for F {
+    fn resolve(&mut self, from: &str, include: &Include) -> Result<SourceFile, String> {
+        self(from, include)
+    }
+}
+
+/// A [Resolver] which does not allow any includes
+pub fn no_includes(_from: &str, _include: &Include) -> Result<SourceFile, String> {
+    Err("includes are not supported here".to_string())
+}
+
+/// Resolves the `path` of an include relative to the directory of the including file `from`.
+/// `.` and `..` are resolved lexically,
+/// so that the same file is always referred to by the same path.
+pub fn relative_path(from: &str, path: &str) -> String {
+    let joined = Path::new(from).parent().unwrap_or(Path::new("")).join(path);
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
+
+/// Reads the file at the `path` relative to the including file `from` (see [relative_path])
+/// from the file-system.
+pub fn read_relative(from: &str, path: &str) -> Result<SourceFile, String> {
+    let path = relative_path(from, path);
+    let source = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    Ok(SourceFile::new(path, source))
+}
+
+/// A [Resolver] which reads included paths from the file-system (see [read_relative]).
+/// Does not allow including ids.
+pub fn file_system(from: &str, include: &Include) -> Result<SourceFile, String> {
+    match include {
+        Include::Path(path) => read_relative(from, path),
+        Include::Id(id) => Err(format!("cannot include id `{id}` without a repository")),
+    }
+}
+
+/// A format which supports `#include`-directives
+pub trait Format {
+    /// The tokens of this format
+    type Token<'a>;
+
+    /// Lexes the `source` (e.g., [input::lexer::lex])
+    fn lex(source: &str) -> Result<Vec<Self::Token<'_>>, ParseError<&str>>;
+
+    /// The byte-ranges of the tokens of the `source` (e.g., [input::lexer::token_spans])
+    fn token_spans(source: &str) -> Vec<Range<usize>>;
+
+    /// Checks whether the `tokens` start with an `#include`-directive.
+    /// Returns [None] if they do not,
+    /// the [Include] and the number of tokens of the directive if they do,
+    /// or a description of the expected argument if the directive is malformed.
+    fn include(tokens: &[Self::Token<'_>]) -> Option<Result<(Include, usize), &'static str>>;
+}
+
+/// The `.naviz`-format: Allows including paths
+pub struct InputFormat;
+
+impl Format for InputFormat {
+    type Token<'a> = input::lexer::Token<&'a str>;
+
+    fn lex(source: &str) -> Result<Vec<Self::Token<'_>>, ParseError<&str>> {
+        input::lexer::lex(source)
+    }
+
+    fn token_spans(source: &str) -> Vec<Range<usize>> {
+        input::lexer::token_spans(source)
+    }
+
+    fn include(tokens: &[Self::Token<'_>]) -> Option<Result<(Include, usize), &'static str>> {
+        use input::lexer::{Token, Value};
+        match tokens {
+            [Token::Directive("include"), Token::Value(Value::String(path)), ..] => {
+                Some(Ok((Include::Path(path.to_string()), 2)))
+            }
+            [Token::Directive("include"), ..] => Some(Err("a path")),
+            _ => None,
+        }
+    }
+}
+
+/// The config-format: Allows including paths and ids of configs in the repository
+pub struct ConfigFormat;
+
+impl Format for ConfigFormat {
+    type Token<'a> = config::lexer::Token<&'a str>;
+
+    fn lex(source: &str) -> Result<Vec<Self::Token<'_>>, ParseError<&str>> {
+        config::lexer::lex(source)
+    }
+
+    fn token_spans(source: &str) -> Vec<Range<usize>> {
+        config::lexer::token_spans(source)
+    }
+
+    fn include(tokens: &[Self::Token<'_>]) -> Option<Result<(Include, usize), &'static str>> {
+        use config::lexer::{Token, Value};
+        match tokens {
+            [Token::Directive("include"), Token::Value(Value::String(path)), ..] => {
+                Some(Ok((Include::Path(path.to_string()), 2)))
+            }
+            [Token::Directive("include"), Token::Identifier(id), ..] => {
+                Some(Ok((Include::Id(id.to_string()), 2)))
+            }
+            [Token::Directive("include"), ..] => Some(Err("a path or an id")),
+            _ => None,
+        }
+    }
+}
+
+/// An error while [loading][Sources::load] some [Sources]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    /// A file could not be lexed
+    Lex(Box<Diagnostic>),
+    /// An `#include`-directive is malformed
+    Invalid(Box<Diagnostic>),
+    /// An include could not be resolved
+    Resolve(Box<Diagnostic>),
+    /// A file (indirectly) includes itself
+    Cycle(Box<Diagnostic>),
+}
+
+impl IncludeError {
+    /// The [Diagnostic] locating this error
+    pub fn diagnostic(&self) -> &Diagnostic {
+        match self {
+            Self::Lex(diagnostic)
+            | Self::Invalid(diagnostic)
+            | Self::Resolve(diagnostic)
+            | Self::Cycle(diagnostic) => diagnostic,
+        }
+    }
+
+    /// Converts this error into its [Diagnostic]
+    pub fn into_diagnostic(self) -> Box<Diagnostic> {
+        match self {
+            Self::Lex(diagnostic)
+            | Self::Invalid(diagnostic)
+            | Self::Resolve(diagnostic)
+            | Self::Cycle(diagnostic) => diagnostic,
+        }
+    }
+}
+
+impl Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.diagnostic().fmt(f)
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// An include of a [LoadedFile]
+struct Included {
+    /// Index of the first token of the `#include`-directive in the including file
+    token: usize,
+    /// Number of tokens of the `#include`-directive
+    tokens: usize,
+    /// Byte-range of the `#include`-directive in the including file
+    span: Range<usize>,
+    /// Index of the included file in [Sources::files]
+    file: usize,
+}
+
+/// A [SourceFile] loaded into [Sources]
+struct LoadedFile {
+    /// The file
+    file: SourceFile,
+    /// The includes of the file, in order of occurrence
+    includes: Vec<Included>,
+}
+
+/// A file and all files it includes in some [Format].
+pub struct Sources<F> {
+    /// All loaded files; the first one is the root
+    files: Vec<LoadedFile>,
+    /// The index of the file and the byte-range in that file
+    /// for each token in the [token-stream][Sources::tokens]
+    locations: Vec<(usize, Range<usize>)>,
+    /// The format of the files
+    format: PhantomData<F>,
+}
+
+impl<F: Format> Sources<F> {
+    /// Loads the `root` file and all files it includes using the `resolver`.
+    /// Each file is identified by its [id][SourceFile::id]:
+    /// A file may be included multiple times, but may not (indirectly) include itself.
+    pub fn load(root: SourceFile, resolver: &mut impl Resolver) -> Result<Self, IncludeError> {
+        let mut sources = Self {
+            files: Vec::new(),
+            locations: Vec::new(),
+            format: PhantomData,
+        };
+        sources.load_file(root, resolver, &mut Vec::new())?;
+        let mut locations = Vec::new();
+        sources.splice(0, &mut Vec::new(), &mut locations);
+        sources.locations = locations;
+        Ok(sources)
+    }
+
+    /// Loads the `file` and (recursively) all files it includes.
+    /// `stack` contains the indices of the files currently being included.
+    /// Returns the index of the loaded file.
+    fn load_file(
+        &mut self,
+        file: SourceFile,
+        resolver: &mut impl Resolver,
+        stack: &mut Vec<usize>,
+    ) -> Result<usize, IncludeError> {
+        let located = |span: Range<usize>, message: String| {
+            Box::new(Diagnostic::new(&file.source, span, message).with_file(&file.name))
+        };
+
+        let tokens = F::lex(&file.source).map_err(|e| {
+            IncludeError::Lex(Box::new(
+                Diagnostic::from_str_error(&file.source, &e).with_file(&file.name),
+            ))
+        })?;
+        let spans = F::token_spans(&file.source);
+        let span = |token: usize| {
+            spans
+                .get(token)
+                .cloned()
+                .unwrap_or(file.source.len()..file.source.len())
+        };
+        let mut directives = Vec::new();
+        for token in 0..tokens.len() {
+            match F::include(&tokens[token..]) {
+                Some(Ok((include, tokens))) => {
+                    let span = span(token).start..span(token + tokens - 1).end;
+                    directives.push((token, tokens, span, include));
+                }
+                Some(Err(expected)) => {
+                    return Err(IncludeError::Invalid(located(
+                        span(token),
+                        format!("`#include` expects {expected}"),
+                    )));
+                }
+                None => {}
+            }
+        }
+        drop(tokens);
+
+        // Resolve all includes before storing the file
+        // to not have to access the file while other files are loaded
+        let mut resolved = Vec::with_capacity(directives.len());
+        for (token, tokens, span, include) in directives {
+            let included = resolver.resolve(&file.id, &include).map_err(|e| {
+                IncludeError::Resolve(located(
+                    span.clone(),
+                    format!("failed to include {include}: {e}"),
+                ))
+            })?;
+            if let Some(position) = stack
+                .iter()
+                .position(|index| self.files[*index].file.id == included.id)
+            {
+                let cycle: Vec<_> = stack[position..]
+                    .iter()
+                    .map(|index| self.files[*index].file.name.as_str())
+                    .chain([file.name.as_str(), included.name.as_str()])
+                    .collect();
+                return Err(IncludeError::Cycle(located(
+                    span,
+                    format!("cyclic include: {}", cycle.join(" -> ")),
+                )));
+            }
+            if included.id == file.id {
+                return Err(IncludeError::Cycle(located(
+                    span,
+                    format!("cyclic include: `{}` includes itself", file.name),
+                )));
+            }
+            resolved.push((token, tokens, span, included));
+        }
+
+        let index = self.files.len();
+        self.files.push(LoadedFile {
+            file,
+            includes: Vec::with_capacity(resolved.len()),
+        });
+        stack.push(index);
+        for (token, tokens, span, included) in resolved {
+            let file = match self.files.iter().position(|f| f.file.id == included.id) {
+                // Already loaded (and not part of a cycle): include again
+                Some(file) => file,
+                None => self.load_file(included, resolver, stack)?,
+            };
+            self.files[index].includes.push(Included {
+                token,
+                tokens,
+                span,
+                file,
+            });
+        }
+        stack.pop();
+
+        Ok(index)
+    }
+
+    /// The combined token-stream of all files,
+    /// where each `#include`-directive is replaced by the tokens of the included file.
+    pub fn tokens(&self) -> Vec<F::Token<'_>> {
+        let mut tokens = Vec::with_capacity(self.locations.len());
+        self.splice(0, &mut tokens, &mut Vec::new());
+        tokens
+    }
+
+    /// Pushes the tokens of the file with the passed `index` onto `tokens`,
+    /// splicing in the tokens of included files,
+    /// and pushes the locations of the tokens onto `locations`.
+    fn splice<'s>(
+        &'s self,
+        index: usize,
+        tokens: &mut Vec<F::Token<'s>>,
+        locations: &mut Vec<(usize, Range<usize>)>,
+    ) {
+        let LoadedFile { file, includes } = &self.files[index];
+        let spans = F::token_spans(&file.source);
+        // Lexing succeeded when loading the file
+        let lexed = F::lex(&file.source).unwrap_or_default();
+
+        let mut includes = includes.iter().peekable();
+        let mut lexed = lexed.into_iter().enumerate();
+        while let Some((token, lexed_token)) = lexed.next() {
+            if let Some(include) = includes.next_if(|include| include.token == token) {
+                self.splice(include.file, tokens, locations);
+                // Skip the remaining tokens of the directive
+                lexed.by_ref().take(include.tokens - 1).for_each(drop);
+                continue;
+            }
+            tokens.push(lexed_token);
+            locations.push((
+                index,
+                spans
+                    .get(token)
+                    .cloned()
+                    .unwrap_or(file.source.len()..file.source.len()),
+            ));
+        }
+    }
+
+    /// The root file
+    pub fn root(&self) -> &SourceFile {
+        &self.files[0].file
+    }
+
+    /// The file and byte-range in that file of the token with the passed index
+    /// in the [token-stream][Sources::tokens].
+    /// Tokens after the end of the stream are located at the end of the root file.
+    pub fn locate(&self, token: usize) -> (&SourceFile, Range<usize>) {
+        match self.locations.get(token) {
+            Some((file, span)) => (&self.files[*file].file, span.clone()),
+            None => {
+                let root = self.root();
+                (root, root.source.len()..root.source.len())
+            }
+        }
+    }
+
+    /// Creates a [Diagnostic] with the passed `message`
+    /// for the token with the passed index in the [token-stream][Sources::tokens]
+    pub fn diagnostic(&self, token: usize, message: impl Into<String>) -> Diagnostic {
+        let (file, span) = self.locate(token);
+        Diagnostic::new(&file.source, span, message).with_file(&file.name)
+    }
+
+    /// Creates a [Diagnostic] from the `error` of a parser
+    /// operating on the [token-stream][Sources::tokens]
+    /// (see [Diagnostic::from_token_error]).
+    pub fn token_error<T>(&self, error: &ParseError<&[T]>) -> Diagnostic {
+        let (file, span) = self.locate(error.offset());
+        Diagnostic::from_token_error_at(&file.source, span, error).with_file(&file.name)
+    }
+
+    /// The source of the root file,
+    /// where each `#include`-directive is replaced by the source of the included file.
+    /// Can be used to store the file without its includes.
+    pub fn flatten(&self) -> String {
+        let mut flattened = String::new();
+        self.flatten_file(0, &mut flattened);
+        flattened
+    }
+
+    /// Pushes the source of the file with the passed `index` onto `flattened`,
+    /// replacing `#include`-directives by the (flattened) included files.
+    fn flatten_file(&self, index: usize, flattened: &mut String) {
+        let LoadedFile { file, includes } = &self.files[index];
+        let mut position = 0;
+        for include in includes {
+            flattened.push_str(&file.source[position..include.span.start]);
+            self.flatten_file(include.file, flattened);
+            // Terminate trailing line-comments of the included file
+            flattened.push('\n');
+            position = include.span.end;
+        }
+        flattened.push_str(&file.source[position..]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// A [Resolver] resolving includes against the passed `(path, source)`-pairs
+    fn files<'a>(
+        files: &'a [(&str, &str)],
+    ) -> impl FnMut(&str, &Include) -> Result<SourceFile, String> + 'a {
+        let files: HashMap<_, _> = files.iter().copied().collect();
+        move |from, include| {
+            let path = match include {
+                Include::Path(path) => relative_path(from, path),
+                Include::Id(id) => format!("repository/{id}"),
+            };
+            files
+                .get(path.as_str())
+                .map(|source| SourceFile::new(path.clone(), *source))
+                .ok_or_else(|| "file not found".to_string())
+        }
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(relative_path("a/b.naviz", "c.naviz"), "a/c.naviz");
+        assert_eq!(relative_path("a/b.naviz", "./c/../d.naviz"), "a/d.naviz");
+        assert_eq!(relative_path("b.naviz", "../c.naviz"), "../c.naviz");
+        assert_eq!(relative_path("a/b.naviz", "/c.naviz"), "/c.naviz");
+    }
+
+    #[test]
+    fn input_includes() {
+        let mut resolver = files(&[
+            (
+                "dir/atoms.naviz",
+                "atom (0, 0) atom0\n#include \"../load.naviz\"",
+            ),
+            ("load.naviz", "@0 load atom0"),
+        ]);
+        let sources: Sources<InputFormat> = Sources::load(
+            SourceFile::new(
+                "main.naviz",
+                "#include \"dir/atoms.naviz\"\n@1 store atom0\n",
+            ),
+            &mut resolver,
+        )
+        .expect("Failed to load sources");
+
+        let instructions = |tokens: &[input::lexer::Token<&str>]| {
+            input::concrete::Instructions::new(
+                input::parser::parse(tokens).expect("Failed to parse"),
+            )
+            .expect("Failed to parse into concrete instructions")
+        };
+        let written = input::lexer::lex("atom (0, 0) atom0\n@0 load atom0\n@1 store atom0\n")
+            .expect("Failed to lex");
+        assert_eq!(instructions(&sources.tokens()), instructions(&written));
+        assert_eq!(
+            sources.flatten(),
+            "atom (0, 0) atom0\n@0 load atom0\n\n\n@1 store atom0\n"
+        );
+
+        // `load` is located in the included file
+        let diagnostic = sources.diagnostic(10, "message");
+        assert_eq!(diagnostic.file.as_deref(), Some("load.naviz"));
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 4));
+        // `store` is located in the including file
+        let diagnostic = sources.diagnostic(17, "message");
+        assert_eq!(diagnostic.file.as_deref(), Some("main.naviz"));
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 4));
+    }
+
+    #[test]
+    fn config_includes() {
+        let mut resolver = files(&[
+            (
+                "repository/base",
+                "name: \"base\"\n#include \"shared.namachine\"",
+            ),
+            ("repository/shared.namachine", "movement { speed: 1 }"),
+        ]);
+        let sources: Sources<ConfigFormat> = Sources::load(
+            SourceFile::new("machine.namachine", "#include base\nid: \"machine\""),
+            &mut resolver,
+        )
+        .expect("Failed to load sources");
+        let written = config::lexer::lex("name: \"base\"\nmovement { speed: 1 }\nid: \"machine\"")
+            .expect("Failed to lex");
+        assert_eq!(sources.tokens(), written);
+    }
+
+    #[test]
+    fn include_errors() {
+        let load = |root: &str, resolver: &[(&str, &str)]| {
+            Sources::<InputFormat>::load(SourceFile::new("main.naviz", root), &mut files(resolver))
+                .err()
+        };
+
+        let error = load(
+            "#include \"a.naviz\"",
+            &[
+                ("a.naviz", "#include \"b.naviz\""),
+                ("b.naviz", "\n#include \"a.naviz\""),
+            ],
+        )
+        .expect("Loaded cyclic include");
+        assert!(matches!(error, IncludeError::Cycle(_)));
+        assert_eq!(
+            error.diagnostic().message,
+            "cyclic include: a.naviz -> b.naviz -> a.naviz"
+        );
+        assert_eq!(error.diagnostic().file.as_deref(), Some("b.naviz"));
+        assert_eq!(error.diagnostic().line, 2);
+
+        assert!(matches!(
+            load("#include \"main.naviz\"", &[("main.naviz", "")]),
+            Some(IncludeError::Cycle(_))
+        ));
+
+        // Including a file twice is not a cycle
+        assert!(load(
+            "#include \"a.naviz\"\n#include \"b.naviz\"",
+            &[("a.naviz", "#include \"b.naviz\""), ("b.naviz", "")],
+        )
+        .is_none());
+
+        let error =
+            load("@0 load atom0\n#include \"missing.naviz\"", &[]).expect("Loaded missing include");
+        assert!(matches!(error, IncludeError::Resolve(_)));
+        assert_eq!(
+            error.diagnostic().message,
+            "failed to include \"missing.naviz\": file not found"
+        );
+        assert_eq!(
+            error.diagnostic().snippet,
+            "#include \"missing.naviz\"\n^^^^^^^^^^^^^^^^^^^^^^^^"
+        );
+
+        assert!(matches!(
+            load("#include atoms", &[]),
+            Some(IncludeError::Invalid(_))
+        ));
+        assert!(matches!(
+            load("#include \"a.naviz\"", &[("a.naviz", "?")]),
+            Some(IncludeError::Lex(ref diagnostic)) if diagnostic.file.as_deref() == Some("a.naviz")
+        ));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod concrete;
 pub mod lexer;
+pub mod location;
 pub mod parser;
+pub mod writer;
@@ -2,44 +2,62 @@
 //! Collects parsed instructions and directives into an [Instructions]-object.
 //! [TimedInstruction]s are collected into an [AbsoluteTimeline],
 //! which in turn contains [RelativeTimeline]s.
+//! The positions of the instructions are [Location]s,
+//! which can be [resolved][Instructions::resolve] using a [MachineConfig].
 
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, error::Error, fmt::Display, rc::Rc};
 
 use super::{
-    lexer::TimeSpec,
-    parser::{InstructionOrDirective, Value},
+    lexer::{self, Operator, TimeSpec},
+    location::{Location, LocationError, Reference},
+    parser::{
+        self,
+        constants::{evaluate, Constants, EvaluationError},
+        InstructionOrDirective, Time, Value,
+    },
+};
+use crate::{
+    common::color::Color,
+    config::{machine::MachineConfig, position::Position},
+    diagnostic::Diagnostic,
+    include::{InputFormat, Sources},
 };
-use crate::config::position::Position;
 use fraction::{Fraction, Zero};
 use itertools::{Either, Itertools};
+use regex::Regex;
+use winnow::{combinator::terminated, Parser};
 
 /// Timeline which has multiple relative timelines starting at fixed positions.
-pub type AbsoluteTimeline = Vec<(Fraction, RelativeTimeline)>;
+pub type AbsoluteTimeline<P = Location> = Vec<(Fraction, RelativeTimeline<P>)>;
 
 /// Timeline which shows relative times.
 /// Item format: `(from_start, offset, instruction)`.
 /// Each entry is relative to the previous entry.
-pub type RelativeTimeline = Vec<(bool, Fraction, InstructionGroup)>;
+pub type RelativeTimeline<P = Location> = Vec<(bool, Fraction, InstructionGroup<P>)>;
 
 /// A group of instructions.
 /// Single instructions can be represented as groups of size `1`
 #[derive(Debug, PartialEq, Clone)]
-pub struct InstructionGroup {
+pub struct InstructionGroup<P = Location> {
     /// Whether this is a variable group
     /// (i.e., the timing of the instructions is allowed to vary)
     pub variable: bool,
     /// The instructions of this group
-    pub instructions: Vec<TimedInstruction>,
+    /// and their durations overriding the default durations (`~<duration>`), if any
+    pub instructions: Vec<(TimedInstruction<P>, Option<Fraction>)>,
 }
 
 /// A single instruction which does not require a time.
 /// See documentation of file format.
+///
+/// Positions are of type `P`:
+/// [Location]s as written in the input or [resolved][SetupInstruction::resolve] [Position]s.
 #[derive(Debug, PartialEq, Clone)]
-pub enum SetupInstruction {
-    Atom { position: Position, id: String },
+pub enum SetupInstruction<P = Location> {
+    Atom { position: P, id: String },
 }
 
-impl SetupInstruction {
+impl<P> SetupInstruction<P> {
     /// Get the name of a [SetupInstruction]
     pub fn str(&self) -> &'static str {
         match self {
@@ -50,34 +68,73 @@ impl SetupInstruction {
 
 /// A single instruction which requires a time.
 /// See documentation of file format.
+///
+/// Positions are of type `P`:
+/// [Location]s as written in the input or [resolved][TimedInstruction::resolve] [Position]s.
 #[derive(Debug, PartialEq, Clone)]
-pub enum TimedInstruction {
+pub enum TimedInstruction<P = Location> {
     Load {
-        position: Option<Position>,
+        position: Option<P>,
         id: String,
     },
     Store {
-        position: Option<Position>,
+        position: Option<P>,
         id: String,
     },
     Move {
-        position: Position,
+        position: P,
         id: String,
     },
     Rz {
         value: Fraction,
-        targets: Vec<String>,
+        targets: Vec<Target>,
     },
     Ry {
         value: Fraction,
-        targets: Vec<String>,
+        targets: Vec<Target>,
     },
     Cz {
-        targets: Vec<String>,
+        targets: Vec<Target>,
+    },
+    /// A cz-operation on clusters of `qubits` atoms
+    Mcz {
+        qubits: usize,
+        targets: Vec<Target>,
+    },
+    Gate {
+        name: String,
+        parameters: Vec<Fraction>,
+        targets: Vec<Target>,
+    },
+    /// Creates a new atom (e.g., reloaded from a reservoir)
+    Spawn {
+        position: P,
+        id: String,
+    },
+    /// Removes an atom (e.g., atom loss)
+    Lose {
+        id: String,
+    },
+    Measure {
+        targets: Vec<Target>,
+    },
+    /// Shows a text for `duration` (at the `position`, if any)
+    Caption {
+        text: String,
+        duration: Fraction,
+        position: Option<P>,
+    },
+    /// Highlights the targeted atoms in `color` for `duration`
+    /// (and draws an outline of width `outline` around them, if any)
+    Highlight {
+        color: Color,
+        duration: Fraction,
+        outline: Option<Fraction>,
+        targets: Vec<Target>,
     },
 }
 
-impl TimedInstruction {
+impl<P> TimedInstruction<P> {
     /// Get the name of a [TimedInstruction]
     pub fn str(&self) -> &'static str {
         match self {
@@ -87,6 +144,64 @@ impl TimedInstruction {
             Self::Rz { .. } => "rz",
             Self::Ry { .. } => "ry",
             Self::Cz { .. } => "cz",
+            Self::Mcz { .. } => "mcz",
+            Self::Gate { .. } => "gate",
+            Self::Spawn { .. } => "spawn",
+            Self::Lose { .. } => "lose",
+            Self::Measure { .. } => "measure",
+            Self::Caption { .. } => "caption",
+            Self::Highlight { .. } => "highlight",
+        }
+    }
+
+    /// The [Target]s of this [TimedInstruction], if it acts on targets
+    fn targets_mut(&mut self) -> Option<&mut Vec<Target>> {
+        match self {
+            Self::Rz { targets, .. }
+            | Self::Ry { targets, .. }
+            | Self::Cz { targets }
+            | Self::Mcz { targets, .. }
+            | Self::Gate { targets, .. }
+            | Self::Measure { targets }
+            | Self::Highlight { targets, .. } => Some(targets),
+            Self::Load { .. }
+            | Self::Store { .. }
+            | Self::Move { .. }
+            | Self::Spawn { .. }
+            | Self::Lose { .. }
+            | Self::Caption { .. } => None,
+        }
+    }
+}
+
+/// A single target of an operation.
+/// See documentation of file format.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// An atom or a zone by its id
+    Id(String),
+    /// All atoms whose id matches the regex
+    Regex(Regex),
+}
+
+impl Target {
+    /// Whether this [Target] selects the atom with the passed `id`
+    /// (i.e., it is the id or a regex matching the id)
+    pub fn matches(&self, id: &str) -> bool {
+        match self {
+            Self::Id(target) => target == id,
+            Self::Regex(regex) => regex.is_match(id),
+        }
+    }
+}
+
+/// [Regex]es are equal if they were compiled from the same source string
+impl PartialEq for Target {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Id(a), Self::Id(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
         }
     }
 }
@@ -96,14 +211,28 @@ impl TimedInstruction {
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct Directives {
     pub targets: Vec<String>,
+    /// The named groups of targets (`#group <name> <target>`) in order of their declaration.
+    /// Groups are already replaced by their members in the instructions.
+    pub groups: Vec<(String, Vec<Target>)>,
 }
 
 /// The parsed instructions, split into [Directives], [SetupInstruction]s, and [TimedInstruction]s.
-#[derive(Default, Debug, PartialEq, Clone)]
-pub struct Instructions {
+/// Positions are [Location]s until [resolved][Instructions::resolve].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Instructions<P = Location> {
     pub directives: Directives,
-    pub setup: Vec<SetupInstruction>,
-    pub instructions: AbsoluteTimeline,
+    pub setup: Vec<SetupInstruction<P>>,
+    pub instructions: AbsoluteTimeline<P>,
+}
+
+impl<P> Default for Instructions<P> {
+    fn default() -> Self {
+        Self {
+            directives: Default::default(),
+            setup: Default::default(),
+            instructions: Default::default(),
+        }
+    }
 }
 
 /// Error during the parsing of instructions in [Instructions::new].
@@ -146,119 +275,919 @@ pub enum ParseInstructionsError {
         /// Name of instructions or directives
         name: Vec<&'static str>,
     },
+    /// A [SetupInstruction] or the call of a definition was given a duration
+    SuperfluousDuration {
+        /// Name of the instruction or definition
+        name: String,
+    },
+    /// The duration of a [TimedInstruction] is not a non-negative number
+    InvalidDuration {
+        /// Name of the instruction
+        name: &'static str,
+    },
+    /// A group with the same name already exists
+    DuplicateGroup {
+        /// Name of the group
+        name: String,
+    },
+    /// A definition with the same name already exists
+    DuplicateDefinition {
+        /// Name of the definition
+        name: String,
+    },
+    /// A definition was called with a wrong number of arguments
+    WrongNumberOfDefinitionArguments {
+        /// Number of parameters of the definition
+        expected: usize,
+        /// Actually got this many arguments
+        actual: usize,
+    },
+    /// A definition was called while it was being expanded
+    RecursiveDefinition,
+    /// The call of a definition and the first instruction of the definition both have a time
+    DuplicateTime,
+    /// The time of an instruction is not a number
+    InvalidTime,
+    /// An error occurred while expanding the call of a definition
+    InDefinition {
+        /// Name of the definition
+        name: String,
+        /// Index of the first token of the call
+        call: usize,
+        /// Index of the first token of the definition
+        definition: usize,
+        /// The error in the expanded definition
+        error: Box<ParseInstructionsError>,
+    },
+    /// An expression or binding could not be evaluated
+    Evaluation(EvaluationError),
+    /// An interpolation (`{<expression>}`) in an identifier
+    /// could not be evaluated to an integer
+    InvalidInterpolation {
+        /// The identifier containing the interpolation
+        identifier: String,
+    },
+    /// A zone was referenced with an unknown anchor (`<zone>.<anchor>`)
+    UnknownAnchor {
+        /// The reference containing the anchor
+        reference: String,
+    },
+    /// An error occurred in an iteration of a repeat
+    InRepeat {
+        /// Index of the first token of the repeat
+        token: usize,
+        /// Name of the index-variable
+        variable: String,
+        /// Value of the index-variable in the iteration
+        index: Fraction,
+        /// The error in the iteration
+        error: Box<ParseInstructionsError>,
+    },
+}
+
+impl Display for ParseInstructionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Formats the names as a list of code-spans
+        let names = |names: &[&str]| names.iter().map(|n| format!("`{n}`")).join(", ");
+        match self {
+            Self::UnknownInstruction { name } => write!(f, "unknown instruction `{name}`"),
+            Self::UnknownDirective { name } => write!(f, "unknown directive `{name}`"),
+            Self::WrongNumberOfArguments {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "`{name}` expects {} argument(s), but got {actual}",
+                expected.iter().join(" or ")
+            ),
+            Self::WrongTypeOfArgument { name, expected } => write!(
+                f,
+                "wrong type of argument for `{name}`: expected {}",
+                expected
+                    .iter()
+                    .map(|types| format!("`{}`", types.join(" ")))
+                    .join(" or ")
+            ),
+            Self::MissingTime { name } => write!(f, "missing time for {}", names(name)),
+            Self::SuperfluousTime { name } => {
+                write!(f, "{} cannot have a time", names(name))
+            }
+            Self::SuperfluousDuration { name } => write!(f, "`{name}` cannot have a duration"),
+            Self::InvalidDuration { name } => {
+                write!(f, "duration of `{name}` is not a non-negative number")
+            }
+            Self::DuplicateGroup { name } => write!(f, "group `{name}` already exists"),
+            Self::DuplicateDefinition { name } => {
+                write!(f, "definition `{name}` already exists")
+            }
+            Self::WrongNumberOfDefinitionArguments { expected, actual } => write!(
+                f,
+                "definition expects {expected} argument(s), but got {actual}"
+            ),
+            Self::RecursiveDefinition => f.write_str("definition calls itself"),
+            Self::DuplicateTime => f.write_str(
+                "call of definition and first instruction of definition both have a time",
+            ),
+            Self::InvalidTime => f.write_str("time is not a number"),
+            Self::InDefinition { name, error, .. } => {
+                write!(f, "in the call of `{name}`: {error}")
+            }
+            Self::Evaluation(error) => error.fmt(f),
+            Self::InvalidInterpolation { identifier } => write!(
+                f,
+                "interpolation in `{identifier}` does not evaluate to an integer"
+            ),
+            Self::UnknownAnchor { reference } => write!(f, "unknown anchor in `{reference}`"),
+            Self::InRepeat {
+                variable,
+                index,
+                error,
+                ..
+            } => write!(f, "in the iteration `{variable} = {index}`: {error}"),
+        }
+    }
+}
+
+impl Error for ParseInstructionsError {}
+
+impl From<EvaluationError> for ParseInstructionsError {
+    fn from(value: EvaluationError) -> Self {
+        Self::Evaluation(value)
+    }
+}
+
+impl ParseInstructionsError {
+    /// The error without the surrounding [InDefinition][ParseInstructionsError::InDefinition]s
+    /// and [InRepeat][ParseInstructionsError::InRepeat]s
+    pub fn innermost(&self) -> &Self {
+        match self {
+            Self::InDefinition { error, .. } | Self::InRepeat { error, .. } => error.innermost(),
+            error => error,
+        }
+    }
+
+    /// Creates [Diagnostic]s pointing to the calls, definitions, and repeats
+    /// this error occurred in (see [ParseInstructionsError::InDefinition]
+    /// and [ParseInstructionsError::InRepeat]),
+    /// starting with the outermost one.
+    /// The tokens are located in the `sources` the instructions were parsed from.
+    pub fn diagnostics(&self, sources: &Sources<InputFormat>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut error = self;
+        loop {
+            match error {
+                Self::InDefinition {
+                    name,
+                    call,
+                    definition,
+                    error: inner,
+                } => {
+                    diagnostics.push(sources.diagnostic(*call, format!("in the call of `{name}`")));
+                    diagnostics
+                        .push(sources.diagnostic(*definition, format!("`{name}` is defined here")));
+                    error = inner;
+                }
+                Self::InRepeat {
+                    token,
+                    variable,
+                    index,
+                    error: inner,
+                } => {
+                    diagnostics.push(sources.diagnostic(
+                        *token,
+                        format!("in the iteration `{variable} = {index}` of this repeat"),
+                    ));
+                    error = inner;
+                }
+                _ => return diagnostics,
+            }
+        }
+    }
 }
 
 impl Instructions {
     /// Try to parse [Instructions] from a [Vec] of [InstructionOrDirective]s.
     pub fn new(input: Vec<InstructionOrDirective>) -> Result<Self, ParseInstructionsError> {
-        let mut instructions = Instructions::default();
-
-        let mut prev = None;
+        let mut builder = Builder::default();
 
         for i in input {
-            match i {
-                InstructionOrDirective::Directive { name, args } => match name.as_str() {
-                    "target" => {
-                        let id = id(args, "#target")?;
-                        instructions.directives.targets.push(id);
+            builder.add(i)?;
+        }
+
+        let mut instructions = builder.instructions;
+        instructions.instructions.sort_unstable_by_key(|e| e.0);
+
+        Ok(instructions)
+    }
+
+    /// Resolves the [Location]s of all instructions to [Position]s
+    /// using the traps and zones of the `machine`.
+    pub fn resolve(
+        &self,
+        machine: &MachineConfig,
+    ) -> Result<Instructions<Position>, LocationError> {
+        Ok(Instructions {
+            directives: self.directives.clone(),
+            setup: self
+                .setup
+                .iter()
+                .map(|instruction| instruction.resolve(machine))
+                .collect::<Result<_, _>>()?,
+            instructions: self
+                .instructions
+                .iter()
+                .map(|(time, timeline)| {
+                    let timeline = timeline
+                        .iter()
+                        .map(|(from_start, offset, group)| {
+                            Ok((*from_start, *offset, group.resolve(machine)?))
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Ok((*time, timeline))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl InstructionGroup {
+    /// Resolves the [Location]s of all instructions to [Position]s
+    /// using the traps and zones of the `machine`.
+    pub fn resolve(
+        &self,
+        machine: &MachineConfig,
+    ) -> Result<InstructionGroup<Position>, LocationError> {
+        Ok(InstructionGroup {
+            variable: self.variable,
+            instructions: self
+                .instructions
+                .iter()
+                .map(|(instruction, duration)| Ok((instruction.resolve(machine)?, *duration)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl SetupInstruction {
+    /// Resolves the [Location] of this instruction to a [Position]
+    /// using the traps and zones of the `machine`.
+    pub fn resolve(
+        &self,
+        machine: &MachineConfig,
+    ) -> Result<SetupInstruction<Position>, LocationError> {
+        Ok(match self {
+            Self::Atom { position, id } => SetupInstruction::Atom {
+                position: position.resolve(machine)?,
+                id: id.clone(),
+            },
+        })
+    }
+}
+
+impl TimedInstruction {
+    /// Resolves the [Location] of this instruction (if any) to a [Position]
+    /// using the traps and zones of the `machine`.
+    pub fn resolve(
+        &self,
+        machine: &MachineConfig,
+    ) -> Result<TimedInstruction<Position>, LocationError> {
+        let resolve = |position: &Option<Location>| {
+            position
+                .as_ref()
+                .map(|position| position.resolve(machine))
+                .transpose()
+        };
+        Ok(match self.clone() {
+            Self::Load { position, id } => TimedInstruction::Load {
+                position: resolve(&position)?,
+                id,
+            },
+            Self::Store { position, id } => TimedInstruction::Store {
+                position: resolve(&position)?,
+                id,
+            },
+            Self::Move { position, id } => TimedInstruction::Move {
+                position: position.resolve(machine)?,
+                id,
+            },
+            Self::Rz { value, targets } => TimedInstruction::Rz { value, targets },
+            Self::Ry { value, targets } => TimedInstruction::Ry { value, targets },
+            Self::Cz { targets } => TimedInstruction::Cz { targets },
+            Self::Mcz { qubits, targets } => TimedInstruction::Mcz { qubits, targets },
+            Self::Gate {
+                name,
+                parameters,
+                targets,
+            } => TimedInstruction::Gate {
+                name,
+                parameters,
+                targets,
+            },
+            Self::Spawn { position, id } => TimedInstruction::Spawn {
+                position: position.resolve(machine)?,
+                id,
+            },
+            Self::Lose { id } => TimedInstruction::Lose { id },
+            Self::Measure { targets } => TimedInstruction::Measure { targets },
+            Self::Caption {
+                text,
+                duration,
+                position,
+            } => TimedInstruction::Caption {
+                text,
+                duration,
+                position: resolve(&position)?,
+            },
+            Self::Highlight {
+                color,
+                duration,
+                outline,
+                targets,
+            } => TimedInstruction::Highlight {
+                color,
+                duration,
+                outline,
+                targets,
+            },
+        })
+    }
+}
+
+/// A [Definition][InstructionOrDirective::Definition] collected by the [Builder]
+struct Definition {
+    /// Index of the first token of the definition
+    token: usize,
+    /// The names of the parameters
+    parameters: Vec<String>,
+    /// The instructions the definition expands to
+    body: Vec<InstructionOrDirective>,
+}
+
+/// Collects [InstructionOrDirective]s into [Instructions]
+#[derive(Default)]
+struct Builder {
+    /// The collected instructions
+    instructions: Instructions,
+    /// Previous insertion-point (see [insert_at_time])
+    prev: Option<usize>,
+    /// The definitions by name
+    definitions: HashMap<String, Rc<Definition>>,
+    /// The names of the definitions which are currently being expanded
+    expanding: Vec<String>,
+    /// The values of the index-variables of the repeats which are currently being expanded
+    variables: HashMap<String, Fraction>,
+    /// The constants defined by bindings
+    constants: Constants,
+}
+
+impl Builder {
+    /// Adds a single [InstructionOrDirective].
+    /// Calls of definitions and repeats are expanded,
+    /// and expressions in the arguments are [evaluated][Builder::evaluate].
+    fn add(&mut self, input: InstructionOrDirective) -> Result<(), ParseInstructionsError> {
+        match input {
+            InstructionOrDirective::Directive { name, args } => match name.as_str() {
+                "target" => {
+                    let id = id(self.evaluate_all(args)?, "#target")?;
+                    self.instructions.directives.targets.push(id);
+                }
+                "group" => {
+                    let (name, members) = id_target(self.evaluate_all(args)?, "#group")?;
+                    let groups = &mut self.instructions.directives.groups;
+                    if groups.iter().any(|(group, _)| *group == name) {
+                        return Err(ParseInstructionsError::DuplicateGroup { name });
                     }
-                    _ => return Err(ParseInstructionsError::UnknownDirective { name }),
-                },
+                    let members = expand_groups(groups, members);
+                    groups.push((name, members));
+                }
+                _ => return Err(ParseInstructionsError::UnknownDirective { name }),
+            },
 
-                InstructionOrDirective::Instruction { time, name, args } => {
-                    match parse_instruction(name.into(), args)? {
-                        Instruction::SetupInstruction(setup) => {
-                            if time.is_some() {
-                                Err(ParseInstructionsError::SuperfluousTime {
-                                    name: vec![setup.str()],
-                                })?
-                            }
-                            instructions.setup.push(setup);
+            InstructionOrDirective::Definition {
+                token,
+                name,
+                parameters,
+                body,
+            } => {
+                if self.definitions.contains_key(&name) {
+                    return Err(ParseInstructionsError::DuplicateDefinition { name });
+                }
+                let definition = Definition {
+                    token,
+                    parameters,
+                    body,
+                };
+                self.definitions.insert(name, Rc::new(definition));
+            }
+
+            InstructionOrDirective::Binding {
+                binding,
+                name,
+                value,
+            } => {
+                let value = self.evaluate(value)?;
+                self.constants.define(binding, name, value)?;
+            }
+
+            InstructionOrDirective::Repeat {
+                token,
+                variable,
+                range,
+                body,
+            } => {
+                let (mut index, to) = self.range(range)?;
+                while index < to {
+                    let previous = self.variables.insert(variable.clone(), index);
+                    let result = body.iter().try_for_each(|item| self.add(item.clone()));
+                    match previous {
+                        Some(previous) => self.variables.insert(variable.clone(), previous),
+                        None => self.variables.remove(&variable),
+                    };
+                    result.map_err(|error| ParseInstructionsError::InRepeat {
+                        token,
+                        variable: variable.clone(),
+                        index,
+                        error: Box::new(error),
+                    })?;
+                    index += Fraction::from(1);
+                }
+            }
+
+            InstructionOrDirective::Instruction {
+                token,
+                time,
+                name,
+                args,
+                duration,
+            } => {
+                let (time, name, args) = self.split_time(time, name, args);
+                let time = self.time(time)?;
+                let args = self.evaluate_all(args)?;
+                if let Some(definition) = self.definitions.get(&name).cloned() {
+                    if duration.is_some() {
+                        return Err(ParseInstructionsError::SuperfluousDuration { name });
+                    }
+                    return self.expand(token, time, name, args, &definition);
+                }
+                match self.instruction(name.into(), args, duration)? {
+                    Instruction::SetupInstruction(setup) => {
+                        if time.is_some() {
+                            Err(ParseInstructionsError::SuperfluousTime {
+                                name: vec![setup.str()],
+                            })?
                         }
-                        Instruction::TimedInstruction(instruction) => insert_at_time(
-                            time,
-                            false,
-                            vec![instruction],
-                            &mut prev,
-                            &mut instructions.instructions,
-                        )?,
+                        self.instructions.setup.push(setup);
                     }
+                    Instruction::TimedInstruction(instruction, duration) => insert_at_time(
+                        time,
+                        false,
+                        vec![(instruction, duration)],
+                        &mut self.prev,
+                        &mut self.instructions.instructions,
+                    )?,
                 }
+            }
 
-                InstructionOrDirective::GroupedTime {
+            InstructionOrDirective::GroupedTime {
+                time,
+                variable,
+                group,
+            } => {
+                let time = self.time(time)?;
+                let (setup, timed): (Vec<_>, Vec<_>) = group
+                    .into_iter()
+                    .map(|(name, args, duration)| {
+                        self.instruction(name.into(), self.evaluate_all(args)?, duration)
+                    })
+                    .process_results(|i| {
+                        i.partition_map(|i| match i {
+                            Instruction::SetupInstruction(setup) => Either::Left(setup),
+                            Instruction::TimedInstruction(instruction, duration) => {
+                                Either::Right((instruction, duration))
+                            }
+                        })
+                    })?;
+                if !setup.is_empty() && time.is_some() {
+                    Err(ParseInstructionsError::SuperfluousTime {
+                        name: setup.iter().map(SetupInstruction::str).collect(),
+                    })?
+                }
+                setup
+                    .into_iter()
+                    .for_each(|s| self.instructions.setup.push(s));
+                insert_at_time(
                     time,
                     variable,
-                    group,
-                } => {
-                    let (setup, timed): (Vec<_>, Vec<_>) = group
-                        .into_iter()
-                        .map(|(name, args)| parse_instruction(name.into(), args))
-                        .process_results(|i| {
-                            i.partition_map(|i| match i {
-                                Instruction::SetupInstruction(setup) => Either::Left(setup),
-                                Instruction::TimedInstruction(instruction) => {
-                                    Either::Right(instruction)
-                                }
-                            })
-                        })?;
-                    if !setup.is_empty() && time.is_some() {
-                        Err(ParseInstructionsError::SuperfluousTime {
-                            name: setup.iter().map(SetupInstruction::str).collect(),
-                        })?
-                    }
-                    setup.into_iter().for_each(|s| instructions.setup.push(s));
-                    insert_at_time(
-                        time,
-                        variable,
-                        timed,
-                        &mut prev,
-                        &mut instructions.instructions,
-                    )?;
-                }
+                    timed,
+                    &mut self.prev,
+                    &mut self.instructions.instructions,
+                )?;
+            }
 
-                InstructionOrDirective::GroupedInstruction {
+            InstructionOrDirective::GroupedInstruction {
+                time,
+                variable,
+                name,
+                group,
+            } => {
+                let time = self.time(time)?;
+                let (setup, timed): (Vec<_>, Vec<_>) = group
+                    .into_iter()
+                    .map(|(args, duration)| {
+                        self.instruction(name.as_str().into(), self.evaluate_all(args)?, duration)
+                    })
+                    .process_results(|i| {
+                        i.partition_map(|i| match i {
+                            Instruction::SetupInstruction(setup) => Either::Left(setup),
+                            Instruction::TimedInstruction(instruction, duration) => {
+                                Either::Right((instruction, duration))
+                            }
+                        })
+                    })?;
+                if !setup.is_empty() && time.is_some() {
+                    Err(ParseInstructionsError::SuperfluousTime {
+                        name: setup.iter().map(SetupInstruction::str).collect(),
+                    })?
+                }
+                setup
+                    .into_iter()
+                    .for_each(|s| self.instructions.setup.push(s));
+                insert_at_time(
                     time,
                     variable,
-                    name,
-                    group,
-                } => {
-                    let (setup, timed): (Vec<_>, Vec<_>) = group
-                        .into_iter()
-                        .map(|args| parse_instruction(name.as_str().into(), args))
-                        .process_results(|i| {
-                            i.partition_map(|i| match i {
-                                Instruction::SetupInstruction(setup) => Either::Left(setup),
-                                Instruction::TimedInstruction(instruction) => {
-                                    Either::Right(instruction)
-                                }
-                            })
-                        })?;
-                    if !setup.is_empty() && time.is_some() {
-                        Err(ParseInstructionsError::SuperfluousTime {
-                            name: setup.iter().map(SetupInstruction::str).collect(),
-                        })?
-                    }
-                    setup.into_iter().for_each(|s| instructions.setup.push(s));
-                    insert_at_time(
-                        time,
-                        variable,
-                        timed,
-                        &mut prev,
-                        &mut instructions.instructions,
-                    )?;
+                    timed,
+                    &mut self.prev,
+                    &mut self.instructions.instructions,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands the call of the `definition` with the passed `name`
+    /// at token-index `token` with the passed `time` and `args`.
+    ///
+    /// The body of the definition is added as if it was written in place of the call,
+    /// with the parameters replaced by the `args`.
+    /// The `time` of the call is used as the time of the first instruction of the body.
+    fn expand(
+        &mut self,
+        token: usize,
+        time: Option<(TimeSpec, Fraction)>,
+        name: String,
+        args: Vec<Value>,
+        definition: &Definition,
+    ) -> Result<(), ParseInstructionsError> {
+        let result = if self.expanding.contains(&name) {
+            Err(ParseInstructionsError::RecursiveDefinition)
+        } else if args.len() != definition.parameters.len() {
+            Err(ParseInstructionsError::WrongNumberOfDefinitionArguments {
+                expected: definition.parameters.len(),
+                actual: args.len(),
+            })
+        } else {
+            let parameters: HashMap<_, _> = definition
+                .parameters
+                .iter()
+                .map(String::as_str)
+                .zip(args)
+                .collect();
+            let mut time = time;
+            self.expanding.push(name.clone());
+            let result = definition.body.iter().try_for_each(|item| {
+                let mut item = substitute(item, &parameters);
+                if let Some(time) = time.take() {
+                    set_time(&mut item, time)?;
+                }
+                self.add(item)
+            });
+            self.expanding.pop();
+            result
+        };
+
+        result.map_err(|error| ParseInstructionsError::InDefinition {
+            name,
+            call: token,
+            definition: definition.token,
+            error: Box::new(error),
+        })
+    }
+
+    /// Evaluates the bounds of a repeat (`[<from>] <to>`).
+    /// `from` defaults to `0`.
+    fn range(&self, range: Vec<Value>) -> Result<(Fraction, Fraction), ParseInstructionsError> {
+        let count = range.len();
+        if !(1..=2).contains(&count) {
+            return Err(ParseInstructionsError::WrongNumberOfArguments {
+                name: "#repeat",
+                expected: &[1, 2],
+                actual: count,
+            });
+        }
+        let bounds = self
+            .evaluate_all(range)?
+            .into_iter()
+            .map(|value| match value {
+                Value::Number(n) if n.denom() == Some(&1) => Some(n),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(ParseInstructionsError::WrongTypeOfArgument {
+                name: "#repeat",
+                expected: &[&["integer"], &["integer", "integer"]],
+            })?;
+        match bounds[..] {
+            [to] => Ok((Fraction::zero(), to)),
+            [from, to] => Ok((from, to)),
+            _ => unreachable!(), // Checked above
+        }
+    }
+
+    /// Parses a single [Instruction] from the instruction name,
+    /// its already evaluated arguments, and its `duration` (see [parse_instruction]).
+    /// The duration is [evaluated][Builder::evaluate]
+    /// and has to be a non-negative number given to a [TimedInstruction].
+    fn instruction(
+        &self,
+        name: Cow<str>,
+        args: Vec<Value>,
+        duration: Option<Value>,
+    ) -> Result<Instruction, ParseInstructionsError> {
+        let mut instruction = parse_instruction(name, args)?;
+        if let Instruction::TimedInstruction(instruction, _) = &mut instruction {
+            if let Some(targets) = instruction.targets_mut() {
+                *targets = expand_groups(
+                    &self.instructions.directives.groups,
+                    std::mem::take(targets),
+                );
+            }
+        }
+        let Some(duration) = duration else {
+            return Ok(instruction);
+        };
+        match instruction {
+            Instruction::SetupInstruction(setup) => {
+                Err(ParseInstructionsError::SuperfluousDuration {
+                    name: setup.str().to_string(),
+                })
+            }
+            Instruction::TimedInstruction(instruction, _) => match self.evaluate(duration)? {
+                Value::Number(duration) if duration >= Fraction::zero() => {
+                    Ok(Instruction::TimedInstruction(instruction, Some(duration)))
                 }
+                _ => Err(ParseInstructionsError::InvalidDuration {
+                    name: instruction.str(),
+                }),
+            },
+        }
+    }
+
+    /// Splits a time which is a single identifier (e.g., `@t rz 1 atom0`) off the instruction.
+    /// As such a time cannot be told apart from the name of the instruction when parsing
+    /// (e.g., `@+ load atom0`), it is parsed as the `name` (see [parser::time]).
+    /// If the `name` is a variable or constant, it is used as the time instead,
+    /// and the first of the `args` is used as the name.
+    fn split_time(
+        &self,
+        time: Option<Time>,
+        name: String,
+        mut args: Vec<Value>,
+    ) -> (Option<Time>, String, Vec<Value>) {
+        let bound = self.variables.contains_key(&name) || self.constants.get(&name).is_some();
+        if let (true, Some((spec, None)), Some(Value::Identifier(_))) = (bound, &time, args.first())
+        {
+            if let Value::Identifier(instruction) = args.remove(0) {
+                return (
+                    Some((spec.clone(), Some(Value::Identifier(name)))),
+                    instruction,
+                    args,
+                );
             }
         }
+        (time, name, args)
+    }
 
-        instructions.instructions.sort_unstable_by_key(|e| e.0);
+    /// [Evaluates][Builder::evaluate] the expression of the `time`
+    /// (which is `0` if omitted, e.g., `@+`).
+    /// Returns [InvalidTime][ParseInstructionsError::InvalidTime] if it is not a number.
+    fn time(
+        &self,
+        time: Option<Time>,
+    ) -> Result<Option<(TimeSpec, Fraction)>, ParseInstructionsError> {
+        time.map(|(spec, value)| match value {
+            None => Ok((spec, Fraction::zero())),
+            Some(value) => match self.evaluate(value)? {
+                Value::Number(time) => Ok((spec, time)),
+                _ => Err(ParseInstructionsError::InvalidTime),
+            },
+        })
+        .transpose()
+    }
 
-        Ok(instructions)
+    /// [Evaluates][Builder::evaluate] all `values`
+    fn evaluate_all(&self, values: Vec<Value>) -> Result<Vec<Value>, ParseInstructionsError> {
+        values
+            .into_iter()
+            .map(|value| self.evaluate(value))
+            .collect()
+    }
+
+    /// [Evaluates][evaluate] the `value` using the current [variables][Builder::variables]
+    /// and [constants][Builder::constants]:
+    /// - [Identifier][Value::Identifier]s which are variables or constants
+    ///   are replaced by their value;
+    ///   all other identifiers are [interpolated][Builder::interpolate]
+    /// - [Operation][Value::Operation]s are calculated
+    /// - [Tuple][Value::Tuple]s containing only a single number are replaced by the number
+    ///   (i.e., they are used as parentheses)
+    fn evaluate(&self, value: Value) -> Result<Value, ParseInstructionsError> {
+        let identifier = |id: String| -> Result<_, ParseInstructionsError> {
+            match self.variables.get(&id).copied().or(self.constants.get(&id)) {
+                Some(value) => Ok(Value::Number(value)),
+                None => Ok(Value::Identifier(self.interpolate(id)?)),
+            }
+        };
+        evaluate(value, &identifier)
+    }
+
+    /// Replaces the interpolations (`{<expression>}`) in the `identifier`
+    /// by the integer they [evaluate][Builder::evaluate] to.
+    fn interpolate(&self, identifier: String) -> Result<String, ParseInstructionsError> {
+        if !identifier.contains('{') {
+            return Ok(identifier);
+        }
+
+        let error = || ParseInstructionsError::InvalidInterpolation {
+            identifier: identifier.clone(),
+        };
+        let mut interpolated = String::new();
+        let mut rest = identifier.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest.find('}').ok_or_else(error)?;
+            interpolated.push_str(&rest[..start]);
+            let tokens = lexer::lex(&rest[start + 1..end]).map_err(|_| error())?;
+            let expression = terminated(parser::expression, parser::token::separator)
+                .parse(&tokens)
+                .map_err(|_| error())?;
+            match self.evaluate(expression)? {
+                Value::Number(n) if n.denom() == Some(&1) => interpolated.push_str(&n.to_string()),
+                _ => return Err(error()),
+            }
+            rest = &rest[end + 1..];
+        }
+        interpolated.push_str(rest);
+        Ok(interpolated)
+    }
+}
+
+/// Sets the `time` of the first instruction of the `item`
+/// (recursing into [Repeat][InstructionOrDirective::Repeat]s).
+/// Returns [DuplicateTime][ParseInstructionsError::DuplicateTime]
+/// if the instruction already has a time.
+fn set_time(
+    item: &mut InstructionOrDirective,
+    time: (TimeSpec, Fraction),
+) -> Result<(), ParseInstructionsError> {
+    match item {
+        InstructionOrDirective::Instruction { time: t, .. }
+        | InstructionOrDirective::GroupedTime { time: t, .. }
+        | InstructionOrDirective::GroupedInstruction { time: t, .. } => {
+            match t.replace((time.0.clone(), Some(Value::Number(time.1)))) {
+                Some(_) => Err(ParseInstructionsError::DuplicateTime),
+                None => Ok(()),
+            }
+        }
+        InstructionOrDirective::Repeat { body, .. } => match body.first_mut() {
+            Some(first) => set_time(first, time),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Replaces the parameters in the times and arguments of the `item`
+/// by the values specified in `parameters`.
+fn substitute(
+    item: &InstructionOrDirective,
+    parameters: &HashMap<&str, Value>,
+) -> InstructionOrDirective {
+    let time = |time: &Option<Time>| {
+        time.as_ref().map(|(spec, value)| {
+            (
+                spec.clone(),
+                value.as_ref().map(|v| substitute_value(v, parameters)),
+            )
+        })
+    };
+    let args = |args: &[Value]| {
+        args.iter()
+            .map(|arg| substitute_value(arg, parameters))
+            .collect()
+    };
+    match item {
+        InstructionOrDirective::Instruction {
+            token,
+            time: t,
+            name,
+            args: a,
+            duration,
+        } => InstructionOrDirective::Instruction {
+            token: *token,
+            time: time(t),
+            name: name.clone(),
+            args: args(a),
+            duration: duration.as_ref().map(|d| substitute_value(d, parameters)),
+        },
+        InstructionOrDirective::GroupedTime {
+            time: t,
+            variable,
+            group,
+        } => InstructionOrDirective::GroupedTime {
+            time: time(t),
+            variable: *variable,
+            group: group
+                .iter()
+                .map(|(name, a, duration)| {
+                    (
+                        name.clone(),
+                        args(a),
+                        duration.as_ref().map(|d| substitute_value(d, parameters)),
+                    )
+                })
+                .collect(),
+        },
+        InstructionOrDirective::GroupedInstruction {
+            time: t,
+            variable,
+            name,
+            group,
+        } => InstructionOrDirective::GroupedInstruction {
+            time: time(t),
+            variable: *variable,
+            name: name.clone(),
+            group: group
+                .iter()
+                .map(|(a, duration)| {
+                    (
+                        args(a),
+                        duration.as_ref().map(|d| substitute_value(d, parameters)),
+                    )
+                })
+                .collect(),
+        },
+        InstructionOrDirective::Repeat {
+            token,
+            variable,
+            range,
+            body,
+        } => InstructionOrDirective::Repeat {
+            token: *token,
+            variable: variable.clone(),
+            range: args(range),
+            body: body
+                .iter()
+                .map(|item| substitute(item, parameters))
+                .collect(),
+        },
+        item => item.clone(),
+    }
+}
+
+/// Replaces the parameters in the `value` (recursing into sets and tuples)
+/// by the values specified in `parameters`.
+fn substitute_value(value: &Value, parameters: &HashMap<&str, Value>) -> Value {
+    match value {
+        Value::Identifier(id) => parameters
+            .get(id.as_str())
+            .cloned()
+            .unwrap_or_else(|| value.clone()),
+        Value::Set(values) => Value::Set(
+            values
+                .iter()
+                .map(|v| substitute_value(v, parameters))
+                .collect(),
+        ),
+        Value::Tuple(values) => Value::Tuple(
+            values
+                .iter()
+                .map(|v| substitute_value(v, parameters))
+                .collect(),
+        ),
+        Value::Operation(a, operator, b) => Value::Operation(
+            Box::new(substitute_value(a, parameters)),
+            *operator,
+            Box::new(substitute_value(b, parameters)),
+        ),
+        value => value.clone(),
     }
 }
 
-/// An instruction: Either a [TimedInstruction] or a [SetupInstruction]
+/// An instruction: Either a [TimedInstruction] (with an optional duration)
+/// or a [SetupInstruction]
 enum Instruction {
-    TimedInstruction(TimedInstruction),
+    TimedInstruction(TimedInstruction, Option<Fraction>),
     SetupInstruction(SetupInstruction),
 }
 
@@ -270,7 +1199,7 @@ impl From<SetupInstruction> for Instruction {
 
 impl From<TimedInstruction> for Instruction {
     fn from(value: TimedInstruction) -> Self {
-        Self::TimedInstruction(value)
+        Self::TimedInstruction(value, None)
     }
 }
 
@@ -309,6 +1238,55 @@ fn parse_instruction(
             let targets = target(args, "cz")?;
             TimedInstruction::Cz { targets }.into()
         }
+        "ccz" => {
+            let targets = target(args, "ccz")?;
+            TimedInstruction::Mcz { qubits: 3, targets }.into()
+        }
+        "mcz" => {
+            let (qubits, targets) = qubits_target(args, "mcz")?;
+            TimedInstruction::Mcz { qubits, targets }.into()
+        }
+        "gate" => {
+            let (name, parameters, targets) = id_numbers_target(args, "gate")?;
+            TimedInstruction::Gate {
+                name,
+                parameters,
+                targets,
+            }
+            .into()
+        }
+        "spawn" => {
+            let (position, id) = position_id(args, "spawn")?;
+            TimedInstruction::Spawn { position, id }.into()
+        }
+        "lose" => {
+            let id = id(args, "lose")?;
+            TimedInstruction::Lose { id }.into()
+        }
+        "measure" => {
+            let targets = target(args, "measure")?;
+            TimedInstruction::Measure { targets }.into()
+        }
+        "caption" => {
+            let (text, duration, position) = string_number_maybe_position(args, "caption")?;
+            TimedInstruction::Caption {
+                text,
+                duration,
+                position,
+            }
+            .into()
+        }
+        "highlight" => {
+            let (color, duration, outline, targets) =
+                color_number_maybe_number_target(args, "highlight")?;
+            TimedInstruction::Highlight {
+                color,
+                duration,
+                outline,
+                targets,
+            }
+            .into()
+        }
         _ => Err(ParseInstructionsError::UnknownInstruction {
             name: name.into_owned(),
         })?,
@@ -330,7 +1308,7 @@ fn parse_instruction(
 fn insert_at_time(
     time: Option<(TimeSpec, Fraction)>,
     variable: bool,
-    instructions: Vec<TimedInstruction>,
+    instructions: Vec<(TimedInstruction, Option<Fraction>)>,
     prev: &mut Option<usize>,
     target: &mut AbsoluteTimeline,
 ) -> Result<(), ParseInstructionsError> {
@@ -338,7 +1316,10 @@ fn insert_at_time(
         return Ok(());
     }
     let (spec, mut time) = time.ok_or_else(|| ParseInstructionsError::MissingTime {
-        name: instructions.iter().map(TimedInstruction::str).collect(),
+        name: instructions
+            .iter()
+            .map(|(instruction, _)| instruction.str())
+            .collect(),
     })?;
     match spec {
         TimeSpec::Absolute => {
@@ -391,34 +1372,31 @@ fn insert_at_time(
     Ok(())
 }
 
-/// Tries to parse the arguments into a position and an id.
+/// Tries to parse the arguments into a location and an id.
 /// Returns a [ParseInstructionsError] if there is a wrong number of arguments
 /// or they have wrong types.
 fn position_id(
     args: Vec<Value>,
     name: &'static str,
-) -> Result<(Position, String), ParseInstructionsError> {
+) -> Result<(Location, String), ParseInstructionsError> {
     let error = || ParseInstructionsError::WrongTypeOfArgument {
         name,
         expected: &[&["position", "id"]],
     };
 
     match n_args(args, name, &[2])? {
-        [Value::Tuple(t), Value::Identifier(id)] => match maybe_get_n(t).map_err(|_| error())? {
-            [Value::Number(x), Value::Number(y)] => Ok(((x, y), id)),
-            _ => Err(error()),
-        },
+        [location, Value::Identifier(id)] => Ok((value_to_location(location, error)?, id)),
         _ => Err(error()),
     }
 }
 
-/// Tries to parse the arguments into a position and an id or into just an id.
+/// Tries to parse the arguments into a location and an id or into just an id.
 /// Returns a [ParseInstructionsError] if there is a wrong number of arguments
 /// or they have wrong types.
 fn maybe_position_id(
     args: Vec<Value>,
     name: &'static str,
-) -> Result<(Option<Position>, String), ParseInstructionsError> {
+) -> Result<(Option<Location>, String), ParseInstructionsError> {
     let error = || ParseInstructionsError::WrongTypeOfArgument {
         name,
         expected: &[&["position", "id"], &["id"]],
@@ -426,11 +1404,8 @@ fn maybe_position_id(
 
     match maybe_get_n(args) {
         Ok(args) => match args {
-            [Value::Tuple(t), Value::Identifier(id)] => {
-                match maybe_get_n(t).map_err(|_| error())? {
-                    [Value::Number(x), Value::Number(y)] => Ok((Some((x, y)), id)),
-                    _ => Err(error()),
-                }
+            [location, Value::Identifier(id)] => {
+                Ok((Some(value_to_location(location, error)?), id))
             }
             _ => Err(error()),
         },
@@ -447,7 +1422,7 @@ fn maybe_position_id(
 fn number_target(
     args: Vec<Value>,
     name: &'static str,
-) -> Result<(Fraction, Vec<String>), ParseInstructionsError> {
+) -> Result<(Fraction, Vec<Target>), ParseInstructionsError> {
     let error = || ParseInstructionsError::WrongTypeOfArgument {
         name,
         expected: &[&["number", "id"]],
@@ -459,53 +1434,241 @@ fn number_target(
     }
 }
 
-/// Tries to parse the arguments into just an id.
+/// Tries to parse the arguments into a number of qubits (at least two) and a target.
 /// Returns a [ParseInstructionsError] if there is a wrong number of arguments
 /// or they have wrong types.
-fn id(args: Vec<Value>, name: &'static str) -> Result<String, ParseInstructionsError> {
+fn qubits_target(
+    args: Vec<Value>,
+    name: &'static str,
+) -> Result<(usize, Vec<Target>), ParseInstructionsError> {
     let error = || ParseInstructionsError::WrongTypeOfArgument {
         name,
-        expected: &[&["id"]],
+        expected: &[&["integer", "target"]],
     };
 
-    match n_args(args, name, &[1])? {
-        [Value::Identifier(id)] => Ok(id),
+    match n_args(args, name, &[2])? {
+        [Value::Number(n), target] => {
+            let qubits = match (n.is_sign_positive(), n.denom(), n.numer()) {
+                (true, Some(1), Some(&qubits)) if qubits >= 2 => qubits as usize,
+                _ => return Err(error()),
+            };
+            Ok((qubits, value_to_target(target, error)?))
+        }
         _ => Err(error()),
     }
 }
 
-/// Tries to parse the arguments into any target.
-/// Returns a [ParseInstructionsError] if there is a wrong number of arguments
+/// Tries to parse the arguments into an id, any amount of numbers, and a target.
+/// Returns a [ParseInstructionsError] if there are less than two arguments
 /// or they have wrong types.
-fn target(args: Vec<Value>, name: &'static str) -> Result<Vec<String>, ParseInstructionsError> {
+fn id_numbers_target(
+    mut args: Vec<Value>,
+    name: &'static str,
+) -> Result<(String, Vec<Fraction>, Vec<Target>), ParseInstructionsError> {
     let error = || ParseInstructionsError::WrongTypeOfArgument {
         name,
-        expected: &[&["target"]],
+        expected: &[&["id", "number...", "target"]],
     };
 
-    let [target] = n_args(args, name, &[1])?;
-    value_to_target(target, error)
-}
-
-/// Tries to convert a [Value] to a target (list of IDs).
+    if args.len() < 2 {
+        return Err(ParseInstructionsError::WrongNumberOfArguments {
+            name,
+            expected: &[2],
+            actual: args.len(),
+        });
+    }
+    let target = value_to_target(args.pop().ok_or_else(error)?, error)?;
+    let mut args = args.into_iter();
+    let Some(Value::Identifier(id)) = args.next() else {
+        return Err(error());
+    };
+    let numbers = args
+        .map(|v| match v {
+            Value::Number(n) => Ok(n),
+            _ => Err(error()),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((id, numbers, target))
+}
+
+/// Tries to parse the arguments into just an id.
+/// Returns a [ParseInstructionsError] if there is a wrong number of arguments
+/// or they have wrong types.
+fn id(args: Vec<Value>, name: &'static str) -> Result<String, ParseInstructionsError> {
+    let error = || ParseInstructionsError::WrongTypeOfArgument {
+        name,
+        expected: &[&["id"]],
+    };
+
+    match n_args(args, name, &[1])? {
+        [Value::Identifier(id)] => Ok(id),
+        _ => Err(error()),
+    }
+}
+
+/// Tries to parse the arguments into a string, a non-negative number, and an optional position.
+/// Returns a [ParseInstructionsError] if there is a wrong number of arguments
+/// or they have wrong types.
+fn string_number_maybe_position(
+    args: Vec<Value>,
+    name: &'static str,
+) -> Result<(String, Fraction, Option<Location>), ParseInstructionsError> {
+    let error = || ParseInstructionsError::WrongTypeOfArgument {
+        name,
+        expected: &[&["string", "number", "position"], &["string", "number"]],
+    };
+
+    match maybe_get_n(args) {
+        Ok([Value::String(text), Value::Number(n), location]) if !n.is_sign_negative() => {
+            Ok((text, n, Some(value_to_location(location, error)?)))
+        }
+        Ok(_) => Err(error()),
+        Err(args) => match n_args(args, name, &[2, 3])? {
+            [Value::String(text), Value::Number(n)] if !n.is_sign_negative() => Ok((text, n, None)),
+            _ => Err(error()),
+        },
+    }
+}
+
+/// Tries to parse the arguments into a color, a number, an optional positive number, and a target.
+/// Returns a [ParseInstructionsError] if there is a wrong number of arguments
+/// or they have wrong types.
+fn color_number_maybe_number_target(
+    args: Vec<Value>,
+    name: &'static str,
+) -> Result<(Color, Fraction, Option<Fraction>, Vec<Target>), ParseInstructionsError> {
+    let error = || ParseInstructionsError::WrongTypeOfArgument {
+        name,
+        expected: &[
+            &["color", "number", "number", "target"],
+            &["color", "number", "target"],
+        ],
+    };
+
+    match maybe_get_n(args) {
+        Ok([Value::Color(color), Value::Number(n), Value::Number(outline), target])
+            if !n.is_sign_negative() && outline > Fraction::zero() =>
+        {
+            Ok((color, n, Some(outline), value_to_target(target, error)?))
+        }
+        Ok(_) => Err(error()),
+        Err(args) => match n_args(args, name, &[3, 4])? {
+            [Value::Color(color), Value::Number(n), target] if !n.is_sign_negative() => {
+                Ok((color, n, None, value_to_target(target, error)?))
+            }
+            _ => Err(error()),
+        },
+    }
+}
+
+/// Tries to parse the arguments into an id and a target.
+/// Returns a [ParseInstructionsError] if there is a wrong number of arguments
+/// or they have wrong types.
+fn id_target(
+    args: Vec<Value>,
+    name: &'static str,
+) -> Result<(String, Vec<Target>), ParseInstructionsError> {
+    let error = || ParseInstructionsError::WrongTypeOfArgument {
+        name,
+        expected: &[&["id", "target"]],
+    };
+
+    match n_args(args, name, &[2])? {
+        [Value::Identifier(id), target] => Ok((id, value_to_target(target, error)?)),
+        _ => Err(error()),
+    }
+}
+
+/// Replaces the [Target]s which name one of the `groups` by the members of the group
+fn expand_groups(groups: &[(String, Vec<Target>)], targets: Vec<Target>) -> Vec<Target> {
+    targets
+        .into_iter()
+        .flat_map(|target| {
+            let members = match &target {
+                Target::Id(id) => groups.iter().find(|(group, _)| group == id),
+                Target::Regex(_) => None,
+            };
+            match members {
+                Some((_, members)) => Either::Left(members.clone().into_iter()),
+                None => Either::Right(std::iter::once(target)),
+            }
+        })
+        .collect()
+}
+
+/// Tries to parse the arguments into any target.
+/// Returns a [ParseInstructionsError] if there is a wrong number of arguments
+/// or they have wrong types.
+fn target(args: Vec<Value>, name: &'static str) -> Result<Vec<Target>, ParseInstructionsError> {
+    let error = || ParseInstructionsError::WrongTypeOfArgument {
+        name,
+        expected: &[&["target"]],
+    };
+
+    let [target] = n_args(args, name, &[1])?;
+    value_to_target(target, error)
+}
+
+/// Tries to convert a [Value] to a target (list of [Target]s).
 /// Will return the `error` if the [Value] contains anything
-/// apart from [Set][Value::Set]s and [Identifier][Value::Identifier]s.
+/// apart from [Set][Value::Set]s, [Identifier][Value::Identifier]s, and [Regex][Value::Regex]es.
 fn value_to_target(
     target: Value,
     error: impl Fn() -> ParseInstructionsError,
-) -> Result<Vec<String>, ParseInstructionsError> {
+) -> Result<Vec<Target>, ParseInstructionsError> {
     target
         .flatten_sets()
-        .map(|v| {
-            if let Value::Identifier(id) = v {
-                Ok(id)
-            } else {
-                Err(error())
-            }
+        .map(|v| match v {
+            Value::Identifier(id) => Ok(Target::Id(id)),
+            Value::Regex(regex) => Ok(Target::Regex(regex)),
+            _ => Err(error()),
         })
         .collect()
 }
 
+/// Tries to convert a [Value] to a [Location]:
+/// - A tuple of two numbers is a [Position][Location::Position]
+/// - An identifier is a [Reference] to a trap (`<trap>`) or a zone (`<zone>.<anchor>`)
+/// - Adding a tuple to or subtracting a tuple from a location offsets the location
+///
+/// Will return the `error` if the [Value] is none of these.
+fn value_to_location(
+    location: Value,
+    error: impl Fn() -> ParseInstructionsError + Copy,
+) -> Result<Location, ParseInstructionsError> {
+    let position = |value| match value {
+        Value::Tuple(t) => match maybe_get_n(t).map_err(|_| error())? {
+            [Value::Number(x), Value::Number(y)] => Ok((x, y)),
+            _ => Err(error()),
+        },
+        _ => Err(error()),
+    };
+
+    match location {
+        Value::Tuple(_) => Ok(Location::Position(position(location)?)),
+        Value::Identifier(reference) => Ok(Location::Reference {
+            reference: Reference::new(reference.clone())
+                .ok_or(ParseInstructionsError::UnknownAnchor { reference })?,
+            offset: (Fraction::zero(), Fraction::zero()),
+        }),
+        Value::Operation(a, operator, b) => {
+            let (location, offset) = match (*a, operator, *b) {
+                (offset @ Value::Tuple(_), Operator::Add, location) => {
+                    (location, position(offset)?)
+                }
+                (location, Operator::Add, offset) => (location, position(offset)?),
+                (location, Operator::Subtract, offset) => {
+                    let (x, y) = position(offset)?;
+                    (location, (-x, -y))
+                }
+                _ => return Err(error()),
+            };
+            Ok(value_to_location(location, error)?.offset(offset))
+        }
+        _ => Err(error()),
+    }
+}
+
 /// Returns a slice of length `N` if the passed vector has length `N`,
 /// or an error containing the original vector.
 ///
@@ -531,7 +1694,16 @@ pub fn n_args<T, const N: usize>(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::input::{lexer, parser};
+    use crate::{
+        include::{no_includes, SourceFile},
+        input::{lexer, parser},
+    };
+
+    /// The [Sources] of the passed `source` without any includes
+    fn sources(source: &str) -> Sources<InputFormat> {
+        Sources::load(SourceFile::new("test.naviz", source), &mut no_includes)
+            .expect("Failed to load sources")
+    }
 
     #[test]
     pub fn example() {
@@ -543,19 +1715,29 @@ mod test {
         let expected = Instructions {
             directives: Directives {
                 targets: vec!["example".to_string()],
+                groups: Vec::new(),
             },
 
             setup: vec![
                 SetupInstruction::Atom {
-                    position: (Fraction::new(0u64, 1u64), Fraction::new(0u64, 1u64)),
+                    position: Location::Position((
+                        Fraction::new(0u64, 1u64),
+                        Fraction::new(0u64, 1u64),
+                    )),
                     id: "atom0".to_string(),
                 },
                 SetupInstruction::Atom {
-                    position: (Fraction::new(16u64, 1u64), Fraction::new(0u64, 1u64)),
+                    position: Location::Position((
+                        Fraction::new(16u64, 1u64),
+                        Fraction::new(0u64, 1u64),
+                    )),
                     id: "atom1".to_string(),
                 },
                 SetupInstruction::Atom {
-                    position: (Fraction::new(32u64, 1u64), Fraction::new(0u64, 1u64)),
+                    position: Location::Position((
+                        Fraction::new(32u64, 1u64),
+                        Fraction::new(0u64, 1u64),
+                    )),
                     id: "atom2".to_string(),
                 },
             ],
@@ -568,17 +1750,23 @@ mod test {
                         InstructionGroup {
                             variable: false,
                             instructions: vec![
-                                TimedInstruction::Load {
-                                    position: None,
-                                    id: "atom0".to_string(),
-                                },
-                                TimedInstruction::Load {
-                                    position: Some((
-                                        Fraction::new(16u64, 1u64),
-                                        Fraction::new(2u64, 1u64),
-                                    )),
-                                    id: "atom1".to_string(),
-                                },
+                                (
+                                    TimedInstruction::Load {
+                                        position: None,
+                                        id: "atom0".to_string(),
+                                    },
+                                    None,
+                                ),
+                                (
+                                    TimedInstruction::Load {
+                                        position: Some(Location::Position((
+                                            Fraction::new(16u64, 1u64),
+                                            Fraction::new(2u64, 1u64),
+                                        ))),
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                ),
                             ],
                         },
                     ),
@@ -587,10 +1775,16 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Move {
-                                position: (Fraction::new(8u64, 1u64), Fraction::new(8u64, 1u64)),
-                                id: "atom0".to_string(),
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Move {
+                                    position: Location::Position((
+                                        Fraction::new(8u64, 1u64),
+                                        Fraction::new(8u64, 1u64),
+                                    )),
+                                    id: "atom0".to_string(),
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -598,10 +1792,16 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Move {
-                                position: (Fraction::new(16u64, 1u64), Fraction::new(16u64, 1u64)),
-                                id: "atom1".to_string(),
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Move {
+                                    position: Location::Position((
+                                        Fraction::new(16u64, 1u64),
+                                        Fraction::new(16u64, 1u64),
+                                    )),
+                                    id: "atom1".to_string(),
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -609,10 +1809,13 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Store {
-                                position: None,
-                                id: "atom0".to_string(),
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Store {
+                                    position: None,
+                                    id: "atom0".to_string(),
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -620,10 +1823,13 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Store {
-                                position: None,
-                                id: "atom1".to_string(),
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Store {
+                                    position: None,
+                                    id: "atom1".to_string(),
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -631,10 +1837,13 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Rz {
-                                value: Fraction::new(3141u64, 1000u64),
-                                targets: vec!["atom0".to_string()],
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Rz {
+                                    value: Fraction::new(3141u64, 1000u64),
+                                    targets: vec![Target::Id("atom0".to_string())],
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -642,10 +1851,13 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Ry {
-                                value: Fraction::new(3141u64, 1000u64),
-                                targets: vec!["atom1".to_string()],
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Ry {
+                                    value: Fraction::new(3141u64, 1000u64),
+                                    targets: vec![Target::Id("atom1".to_string())],
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -653,9 +1865,12 @@ mod test {
                         Fraction::new(0u64, 1u64),
                         InstructionGroup {
                             variable: false,
-                            instructions: vec![TimedInstruction::Cz {
-                                targets: vec!["zone0".to_string()],
-                            }],
+                            instructions: vec![(
+                                TimedInstruction::Cz {
+                                    targets: vec![Target::Id("zone0".to_string())],
+                                },
+                                None,
+                            )],
                         },
                     ),
                     (
@@ -664,13 +1879,19 @@ mod test {
                         InstructionGroup {
                             variable: true,
                             instructions: vec![
-                                TimedInstruction::Cz {
-                                    targets: vec!["zone1".to_string()],
-                                },
-                                TimedInstruction::Ry {
-                                    value: Fraction::new(3141u64, 1000u64),
-                                    targets: vec!["atom0".to_string()],
-                                },
+                                (
+                                    TimedInstruction::Cz {
+                                        targets: vec![Target::Id("zone1".to_string())],
+                                    },
+                                    None,
+                                ),
+                                (
+                                    TimedInstruction::Ry {
+                                        value: Fraction::new(3141u64, 1000u64),
+                                        targets: vec![Target::Id("atom0".to_string())],
+                                    },
+                                    None,
+                                ),
                             ],
                         },
                     ),
@@ -698,6 +1919,7 @@ mod test {
                 args: vec![Value::Identifier("machine_b".to_string())],
             },
             InstructionOrDirective::Instruction {
+                token: 0,
                 time: None,
                 name: "atom".to_string(),
                 args: vec![
@@ -707,78 +1929,99 @@ mod test {
                     ]),
                     Value::Identifier("atom1".to_string()),
                 ],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 0,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: false,
                         positive: true,
                     },
-                    Fraction::new(0u64, 1u64),
+                    Some(Value::Number(Fraction::new(0u64, 1u64))),
                 )),
                 name: "load".to_string(),
                 args: vec![Value::Identifier("atom1".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 0,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: true,
                         positive: true,
                     },
-                    Fraction::new(2u64, 1u64),
+                    Some(Value::Number(Fraction::new(2u64, 1u64))),
                 )),
                 name: "store".to_string(),
                 args: vec![Value::Identifier("atom1".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 0,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: false,
                         positive: true,
                     },
-                    Fraction::new(3u64, 1u64),
+                    Some(Value::Number(Fraction::new(3u64, 1u64))),
                 )),
                 name: "load".to_string(),
                 args: vec![Value::Identifier("atom1".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
-                time: Some((TimeSpec::Absolute, Fraction::new(20u64, 1u64))),
+                token: 0,
+                time: Some((
+                    TimeSpec::Absolute,
+                    Some(Value::Number(Fraction::new(20u64, 1u64))),
+                )),
                 name: "store".to_string(),
                 args: vec![Value::Identifier("atom1".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 0,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: true,
                         positive: true,
                     },
-                    Fraction::new(2u64, 1u64),
+                    Some(Value::Number(Fraction::new(2u64, 1u64))),
                 )),
                 name: "load".to_string(),
                 args: vec![Value::Identifier("atom1".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 0,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: false,
                         positive: true,
                     },
-                    Fraction::new(0u64, 1u64),
+                    Some(Value::Number(Fraction::new(0u64, 1u64))),
                 )),
                 name: "store".to_string(),
                 args: vec![Value::Identifier("atom1".to_string())],
+                duration: None,
             },
             InstructionOrDirective::GroupedTime {
-                time: Some((TimeSpec::Absolute, Fraction::new(20u64, 1u64))),
+                time: Some((
+                    TimeSpec::Absolute,
+                    Some(Value::Number(Fraction::new(20u64, 1u64))),
+                )),
                 variable: false,
                 group: vec![
                     (
                         "load".to_string(),
                         vec![Value::Identifier("atom0".to_string())],
+                        None,
                     ),
                     (
                         "load".to_string(),
                         vec![Value::Identifier("atom1".to_string())],
+                        None,
                     ),
                 ],
             },
@@ -788,13 +2031,13 @@ mod test {
                         from_start: false,
                         positive: true,
                     },
-                    Fraction::new(0u64, 1u64),
+                    Some(Value::Number(Fraction::new(0u64, 1u64))),
                 )),
                 variable: true,
                 name: "store".to_string(),
                 group: vec![
-                    vec![Value::Identifier("atom0".to_string())],
-                    vec![Value::Identifier("atom1".to_string())],
+                    (vec![Value::Identifier("atom0".to_string())], None),
+                    (vec![Value::Identifier("atom1".to_string())], None),
                 ],
             },
         ];
@@ -802,9 +2045,13 @@ mod test {
         let expected = Instructions {
             directives: Directives {
                 targets: vec!["machine_a".to_string(), "machine_b".to_string()],
+                groups: Vec::new(),
             },
             setup: vec![SetupInstruction::Atom {
-                position: (Fraction::new(0u64, 1u64), Fraction::new(0u64, 1u64)),
+                position: Location::Position((
+                    Fraction::new(0u64, 1u64),
+                    Fraction::new(0u64, 1u64),
+                )),
                 id: "atom1".to_string(),
             }],
             instructions: vec![
@@ -816,10 +2063,13 @@ mod test {
                             Fraction::zero(),
                             InstructionGroup {
                                 variable: false,
-                                instructions: vec![TimedInstruction::Load {
-                                    position: None,
-                                    id: "atom1".to_string(),
-                                }],
+                                instructions: vec![(
+                                    TimedInstruction::Load {
+                                        position: None,
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                )],
                             },
                         ),
                         (
@@ -827,10 +2077,13 @@ mod test {
                             Fraction::new(2u64, 1u64),
                             InstructionGroup {
                                 variable: false,
-                                instructions: vec![TimedInstruction::Store {
-                                    position: None,
-                                    id: "atom1".to_string(),
-                                }],
+                                instructions: vec![(
+                                    TimedInstruction::Store {
+                                        position: None,
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                )],
                             },
                         ),
                         (
@@ -838,10 +2091,13 @@ mod test {
                             Fraction::new(3u64, 1u64),
                             InstructionGroup {
                                 variable: false,
-                                instructions: vec![TimedInstruction::Load {
-                                    position: None,
-                                    id: "atom1".to_string(),
-                                }],
+                                instructions: vec![(
+                                    TimedInstruction::Load {
+                                        position: None,
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                )],
                             },
                         ),
                     ],
@@ -854,10 +2110,13 @@ mod test {
                             Fraction::zero(),
                             InstructionGroup {
                                 variable: false,
-                                instructions: vec![TimedInstruction::Store {
-                                    position: None,
-                                    id: "atom1".to_string(),
-                                }],
+                                instructions: vec![(
+                                    TimedInstruction::Store {
+                                        position: None,
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                )],
                             },
                         ),
                         (
@@ -865,10 +2124,13 @@ mod test {
                             Fraction::new(2u64, 1u64),
                             InstructionGroup {
                                 variable: false,
-                                instructions: vec![TimedInstruction::Load {
-                                    position: None,
-                                    id: "atom1".to_string(),
-                                }],
+                                instructions: vec![(
+                                    TimedInstruction::Load {
+                                        position: None,
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                )],
                             },
                         ),
                         (
@@ -876,10 +2138,13 @@ mod test {
                             Fraction::new(0u64, 1u64),
                             InstructionGroup {
                                 variable: false,
-                                instructions: vec![TimedInstruction::Store {
-                                    position: None,
-                                    id: "atom1".to_string(),
-                                }],
+                                instructions: vec![(
+                                    TimedInstruction::Store {
+                                        position: None,
+                                        id: "atom1".to_string(),
+                                    },
+                                    None,
+                                )],
                             },
                         ),
                     ],
@@ -893,14 +2158,20 @@ mod test {
                             InstructionGroup {
                                 variable: false,
                                 instructions: vec![
-                                    TimedInstruction::Load {
-                                        position: None,
-                                        id: "atom0".to_string(),
-                                    },
-                                    TimedInstruction::Load {
-                                        position: None,
-                                        id: "atom1".to_string(),
-                                    },
+                                    (
+                                        TimedInstruction::Load {
+                                            position: None,
+                                            id: "atom0".to_string(),
+                                        },
+                                        None,
+                                    ),
+                                    (
+                                        TimedInstruction::Load {
+                                            position: None,
+                                            id: "atom1".to_string(),
+                                        },
+                                        None,
+                                    ),
                                 ],
                             },
                         ),
@@ -910,14 +2181,20 @@ mod test {
                             InstructionGroup {
                                 variable: true,
                                 instructions: vec![
-                                    TimedInstruction::Store {
-                                        position: None,
-                                        id: "atom0".to_string(),
-                                    },
-                                    TimedInstruction::Store {
-                                        position: None,
-                                        id: "atom1".to_string(),
-                                    },
+                                    (
+                                        TimedInstruction::Store {
+                                            position: None,
+                                            id: "atom0".to_string(),
+                                        },
+                                        None,
+                                    ),
+                                    (
+                                        TimedInstruction::Store {
+                                            position: None,
+                                            id: "atom1".to_string(),
+                                        },
+                                        None,
+                                    ),
                                 ],
                             },
                         ),
@@ -930,4 +2207,676 @@ mod test {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    pub fn gate() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let instructions = parse("@0 gate u3 0.5 1 2 {atom0, zone0}")
+            .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Gate {
+                    name: "u3".to_string(),
+                    parameters: vec![
+                        Fraction::new(1u64, 2u64),
+                        Fraction::new(1u64, 1u64),
+                        Fraction::new(2u64, 1u64),
+                    ],
+                    targets: vec![
+                        Target::Id("atom0".to_string()),
+                        Target::Id("zone0".to_string())
+                    ],
+                },
+                None
+            )]
+        );
+
+        assert!(matches!(
+            parse("@0 gate h"),
+            Err(ParseInstructionsError::WrongNumberOfArguments { .. })
+        ));
+        assert!(matches!(
+            parse("@0 gate h (0, 0) atom0"),
+            Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+        ));
+    }
+
+    #[test]
+    pub fn mcz() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let instructions = parse("@0 ccz zone0\n@1 mcz 4 {atom0, zone0}")
+            .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Mcz {
+                    qubits: 3,
+                    targets: vec![Target::Id("zone0".to_string())],
+                },
+                None
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[1].1[0].2.instructions,
+            [(
+                TimedInstruction::Mcz {
+                    qubits: 4,
+                    targets: vec![
+                        Target::Id("atom0".to_string()),
+                        Target::Id("zone0".to_string())
+                    ],
+                },
+                None
+            )]
+        );
+
+        for invalid in ["@0 mcz 1 zone0", "@0 mcz 2.5 zone0", "@0 mcz zone0 4"] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+            ));
+        }
+    }
+
+    #[test]
+    pub fn spawn_lose_measure() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let instructions = parse("@0 spawn (1, 2) atom1\n@1 lose atom0\n@2 measure {atom1, zone0}")
+            .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Spawn {
+                    position: Location::Position((Fraction::from(1), Fraction::from(2))),
+                    id: "atom1".to_string(),
+                },
+                None
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[1].1[0].2.instructions,
+            [(
+                TimedInstruction::Lose {
+                    id: "atom0".to_string(),
+                },
+                None
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[2].1[0].2.instructions,
+            [(
+                TimedInstruction::Measure {
+                    targets: vec![
+                        Target::Id("atom1".to_string()),
+                        Target::Id("zone0".to_string())
+                    ],
+                },
+                None
+            )]
+        );
+
+        assert!(matches!(
+            parse("@0 spawn atom0"),
+            Err(ParseInstructionsError::WrongNumberOfArguments { .. })
+        ));
+        assert!(matches!(
+            parse("@0 lose {atom0, atom1}"),
+            Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+        ));
+    }
+
+    #[test]
+    pub fn caption() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let instructions = parse("@0 caption \"Stage 1\" 2\n@1 caption \"here\" 0.5 (1, 2)")
+            .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Caption {
+                    text: "Stage 1".to_string(),
+                    duration: Fraction::from(2),
+                    position: None,
+                },
+                None
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[1].1[0].2.instructions,
+            [(
+                TimedInstruction::Caption {
+                    text: "here".to_string(),
+                    duration: Fraction::new(1u64, 2u64),
+                    position: Some(Location::Position((Fraction::from(1), Fraction::from(2)))),
+                },
+                None
+            )]
+        );
+
+        for invalid in ["@0 caption \"text\"", "@0 caption \"text\" 1 (0, 0) atom0"] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ParseInstructionsError::WrongNumberOfArguments { .. })
+            ));
+        }
+        for invalid in ["@0 caption text 1", "@0 caption \"text\" -1"] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+            ));
+        }
+    }
+
+    #[test]
+    pub fn highlight() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let instructions =
+            parse("@0 highlight #ff0000 2 {atom0, atom1}\n@1 highlight #00ff0080 1 0.5 ^atom.*$")
+                .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Highlight {
+                    color: Color {
+                        r: 255,
+                        g: 0,
+                        b: 0,
+                        a: 255
+                    },
+                    duration: Fraction::from(2),
+                    outline: None,
+                    targets: vec![
+                        Target::Id("atom0".to_string()),
+                        Target::Id("atom1".to_string())
+                    ],
+                },
+                None
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[1].1[0].2.instructions,
+            [(
+                TimedInstruction::Highlight {
+                    color: Color {
+                        r: 0,
+                        g: 255,
+                        b: 0,
+                        a: 128
+                    },
+                    duration: Fraction::from(1),
+                    outline: Some(Fraction::new(1u64, 2u64)),
+                    targets: vec![Target::Regex(Regex::new("^atom.*$").unwrap())],
+                },
+                None
+            )]
+        );
+
+        for invalid in [
+            "@0 highlight #ff0000 atom0",
+            "@0 highlight #ff0000 1 1 1 atom0",
+        ] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ParseInstructionsError::WrongNumberOfArguments { .. })
+            ));
+        }
+        for invalid in [
+            "@0 highlight red 1 atom0",
+            "@0 highlight #ff0000 -1 atom0",
+            "@0 highlight #ff0000 1 0 atom0",
+            "@0 highlight #ff0000 1 (0, 0)",
+        ] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+            ));
+        }
+    }
+
+    #[test]
+    pub fn groups() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+        let id = |id: &str| Target::Id(id.to_string());
+        let regex = |regex: &str| Target::Regex(Regex::new(regex).unwrap());
+
+        let instructions = parse(
+            "#group data {q0, q1}\n#group ancillas ^anc_.*$\n#group all {data, ancillas, q5}\n\
+             @0 rz 0.5 data\n@1 ry 1 {all, zone0}",
+        )
+        .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.directives.groups,
+            [
+                ("data".to_string(), vec![id("q0"), id("q1")]),
+                ("ancillas".to_string(), vec![regex("^anc_.*$")]),
+                (
+                    "all".to_string(),
+                    vec![id("q0"), id("q1"), regex("^anc_.*$"), id("q5")]
+                ),
+            ]
+        );
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Rz {
+                    value: Fraction::new(1u64, 2u64),
+                    targets: vec![id("q0"), id("q1")],
+                },
+                None
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[1].1[0].2.instructions,
+            [(
+                TimedInstruction::Ry {
+                    value: Fraction::from(1),
+                    targets: vec![id("q0"), id("q1"), regex("^anc_.*$"), id("q5"), id("zone0")],
+                },
+                None
+            )]
+        );
+
+        assert!(matches!(
+            parse("#group data q0\n#group data q1"),
+            Err(ParseInstructionsError::DuplicateGroup { .. })
+        ));
+        assert!(matches!(
+            parse("#group data"),
+            Err(ParseInstructionsError::WrongNumberOfArguments { .. })
+        ));
+        assert!(matches!(
+            parse("#group ^data$ q0"),
+            Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+        ));
+    }
+
+    #[test]
+    pub fn duration() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let instructions =
+            parse("let t = 1.5\n@0 move (1, 2) atom0 ~3\n@1 [\nrz 1 atom0 ~2 * t\ncz atom0\n]")
+                .expect("Failed to parse into concrete instructions");
+        assert_eq!(
+            instructions.instructions[0].1[0].2.instructions,
+            [(
+                TimedInstruction::Move {
+                    position: Location::Position((Fraction::from(1), Fraction::from(2))),
+                    id: "atom0".to_string(),
+                },
+                Some(Fraction::from(3)),
+            )]
+        );
+        assert_eq!(
+            instructions.instructions[1].1[0].2.instructions,
+            [
+                (
+                    TimedInstruction::Rz {
+                        value: Fraction::from(1),
+                        targets: vec![Target::Id("atom0".to_string())],
+                    },
+                    Some(Fraction::from(3)),
+                ),
+                (
+                    TimedInstruction::Cz {
+                        targets: vec![Target::Id("atom0".to_string())],
+                    },
+                    None,
+                ),
+            ]
+        );
+
+        for invalid in ["@0 rz 1 atom0 ~atom1", "@0 rz 1 atom0 ~-1"] {
+            assert!(matches!(
+                parse(invalid),
+                Err(ParseInstructionsError::InvalidDuration { name: "rz" })
+            ));
+        }
+        assert!(matches!(
+            parse("atom (0, 0) atom0 ~1"),
+            Err(ParseInstructionsError::SuperfluousDuration { .. })
+        ));
+        assert!(matches!(
+            parse("#define a x [\n@+ rz 1 x\n]\n@0 a atom0 ~1"),
+            Err(ParseInstructionsError::SuperfluousDuration { .. })
+        ));
+    }
+
+    #[test]
+    pub fn definition() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let expanded = parse(
+            "#define swap a b pos [\n\
+             \tload a\n\
+             \t@+ move pos a\n\
+             \t@+ rz 0.5 {a, b}\n\
+             ]\n\
+             @0 swap atom0 atom1 (1, 2)\n\
+             @+ cz {atom0, atom1}",
+        )
+        .expect("Failed to parse into concrete instructions");
+        let written = parse(
+            "@0 load atom0\n\
+             @+ move (1, 2) atom0\n\
+             @+ rz 0.5 {atom0, atom1}\n\
+             @+ cz {atom0, atom1}",
+        )
+        .expect("Failed to parse into concrete instructions");
+        assert_eq!(expanded, written);
+
+        assert!(matches!(
+            parse("#define a [\n@0 load x\n]\n#define a [\n@0 load x\n]"),
+            Err(ParseInstructionsError::DuplicateDefinition { .. })
+        ));
+
+        let source = "#define l x [\n@+ load x\n]\n#define two [\nl atom0\n@1 l atom1\n]\n@0 two";
+        let error = parse(source).expect_err("Parsed invalid definition");
+        assert!(matches!(
+            error.innermost(),
+            ParseInstructionsError::DuplicateTime
+        ));
+        let diagnostics = error.diagnostics(&sources(source));
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.line))
+                .collect::<Vec<_>>(),
+            [
+                ("in the call of `two`", 8),
+                ("`two` is defined here", 4),
+                ("in the call of `l`", 5),
+                ("`l` is defined here", 1),
+            ]
+        );
+
+        let error = parse("#define r [\n@0 r\n]\nr").expect_err("Parsed recursive definition");
+        assert!(matches!(
+            error.innermost(),
+            ParseInstructionsError::RecursiveDefinition
+        ));
+        let error = parse("#define l x [\n@0 load x\n]\nl").expect_err("Parsed invalid call");
+        assert!(matches!(
+            error.innermost(),
+            ParseInstructionsError::WrongNumberOfDefinitionArguments {
+                expected: 1,
+                actual: 0
+            }
+        ));
+    }
+
+    #[test]
+    pub fn repeat() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let expanded = parse(
+            "#repeat i 2 [\n\
+             \tatom (i * 14, 0) atom{i}\n\
+             ]\n\
+             @0 load atom0\n\
+             #repeat i 1 3 [\n\
+             \t@+ move ((i + 1) * 14 / 2, 40 - i) atom{i - 1}\n\
+             \t#repeat j i [\n\
+             \t\t@= rz j atom{j}\n\
+             \t]\n\
+             ]",
+        )
+        .expect("Failed to parse into concrete instructions");
+        let written = parse(
+            "atom (0, 0) atom0\n\
+             atom (14, 0) atom1\n\
+             @0 load atom0\n\
+             @+ move (14, 39) atom0\n\
+             @= rz 0 atom0\n\
+             @+ move (21, 38) atom1\n\
+             @= rz 0 atom0\n\
+             @= rz 1 atom1",
+        )
+        .expect("Failed to parse into concrete instructions");
+        assert_eq!(expanded, written);
+
+        let source = "#repeat i 3 [\n@0 rz 1 / (i - 1) atom0\n]";
+        let error = parse(source).expect_err("Divided by zero");
+        assert!(matches!(
+            error,
+            ParseInstructionsError::InRepeat { ref index, .. } if *index == Fraction::from(1)
+        ));
+        assert!(matches!(
+            error.innermost(),
+            ParseInstructionsError::Evaluation(EvaluationError::DivisionByZero)
+        ));
+        let diagnostics = error.diagnostics(&sources(source));
+        assert_eq!(
+            diagnostics[0].message,
+            "in the iteration `i = 1` of this repeat"
+        );
+
+        assert!(matches!(
+            parse("#repeat i 0.5 [\n@0 load atom0\n]"),
+            Err(ParseInstructionsError::WrongTypeOfArgument { .. })
+        ));
+        assert!(matches!(
+            parse("@0 load atom{i}"),
+            Err(ParseInstructionsError::InvalidInterpolation { .. })
+        ));
+
+        // A set directly following an instruction is not an interpolation
+        assert_eq!(
+            parse("@0 cz{a, b}")
+                .expect("Failed to parse into concrete instructions")
+                .instructions[0]
+                .1[0]
+                .2
+                .instructions,
+            [(
+                TimedInstruction::Cz {
+                    targets: vec![Target::Id("a".to_string()), Target::Id("b".to_string())],
+                },
+                None
+            )]
+        );
+        assert!(matches!(
+            parse("@0 rz atom0 + 1 atom0"),
+            Err(ParseInstructionsError::Evaluation(
+                EvaluationError::NonNumericOperand
+            ))
+        ));
+    }
+
+    #[test]
+    pub fn bindings() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let evaluated = parse(
+            "const pitch = 14\n\
+             let y = pitch * 3 - 2\n\
+             #repeat i 2 [\n\
+             \tatom (i * pitch, y) atom{i}\n\
+             ]\n\
+             let y = y / 2\n\
+             @0 move (-pitch, y) atom0",
+        )
+        .expect("Failed to parse into concrete instructions");
+        let written = parse(
+            "atom (0, 40) atom0\n\
+             atom (14, 40) atom1\n\
+             @0 move (-14, 20) atom0",
+        )
+        .expect("Failed to parse into concrete instructions");
+        assert_eq!(evaluated, written);
+
+        assert!(matches!(
+            parse("const a = 1\nconst a = 2"),
+            Err(ParseInstructionsError::Evaluation(
+                EvaluationError::ConstantRedefinition { .. }
+            ))
+        ));
+        assert!(matches!(
+            parse("let a = atom0"),
+            Err(ParseInstructionsError::Evaluation(
+                EvaluationError::NonNumericConstant { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    pub fn times() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+
+        let evaluated = parse(
+            "const t = 2
+             @t rz 1 atom0
+             @(t * 2) [
+             	rz 1 atom0
+             ]
+             @+t / 4 rz 1 atom0
+             @=-(t - 1) ry [
+             	1 atom0
+             ]
+             @=t ry 1 atom0
+             #repeat i 1 3 [
+             	@i * 10 rz i atom0
+             ]
+             @+ load atom0",
+        )
+        .expect("Failed to parse into concrete instructions");
+        let written = parse(
+            "@2 rz 1 atom0
+             @4 [
+             	rz 1 atom0
+             ]
+             @+0.5 rz 1 atom0
+             @=-1 ry [
+             	1 atom0
+             ]
+             @=2 ry 1 atom0
+             @10 rz 1 atom0
+             @20 rz 2 atom0
+             @+ load atom0",
+        )
+        .expect("Failed to parse into concrete instructions");
+        assert_eq!(evaluated, written);
+
+        // A time which is no constant is the name of the instruction
+        assert!(matches!(
+            parse("@t rz 1 atom0"),
+            Err(ParseInstructionsError::UnknownInstruction { .. })
+        ));
+        assert!(matches!(
+            parse("@(atom0) rz 1 atom0"),
+            Err(ParseInstructionsError::InvalidTime)
+        ));
+    }
+
+    #[test]
+    pub fn locations() {
+        let parse = |input: &str| {
+            let lexed = lexer::lex(input).expect("Failed to lex");
+            let parsed = parser::parse(&lexed).expect("Failed to parse");
+            Instructions::new(parsed)
+        };
+        let machine: MachineConfig = {
+            let input = "
+                name: \"Test\"
+                movement { max_speed: 1 }
+                time { load: 1 store: 1 ry: 1 rz: 1 cz: 1 unit: \"us\" }
+                distance { interaction: 2 unit: \"um\" }
+                zone zone0 { from: (0, 0) to: (10, 20) }
+                trap trap0 { position: (3, 4) }
+            ";
+            let lexed = crate::config::lexer::lex(input).expect("Failed to lex");
+            let parsed = crate::config::parser::parse(&lexed).expect("Failed to parse");
+            let config: crate::config::generic::Config = parsed.into();
+            config.try_into().expect("Failed to convert")
+        };
+
+        let instructions = parse(
+            "let dx = 2\n\
+             atom trap0 atom0\n\
+             @0 move zone0.center + (dx, 0) atom0\n\
+             @+ load (1, 1) + trap0 - (0, 2) atom0\n\
+             @+ store zone0.to atom0",
+        )
+        .expect("Failed to parse into concrete instructions");
+        let resolved = instructions
+            .resolve(&machine)
+            .expect("Failed to resolve positions");
+        let written = parse(
+            "atom (3, 4) atom0\n\
+             @0 move (7, 10) atom0\n\
+             @+ load (4, 3) atom0\n\
+             @+ store (10, 20) atom0",
+        )
+        .expect("Failed to parse into concrete instructions")
+        .resolve(&machine)
+        .expect("Failed to resolve positions");
+        assert_eq!(resolved, written);
+
+        assert_eq!(
+            parse("@0 move trap1 atom0")
+                .expect("Failed to parse into concrete instructions")
+                .resolve(&machine),
+            Err(LocationError::UnknownTrap("trap1".to_string()))
+        );
+        assert_eq!(
+            parse("atom zone1.from atom0")
+                .expect("Failed to parse into concrete instructions")
+                .resolve(&machine),
+            Err(LocationError::UnknownZone("zone1".to_string()))
+        );
+        assert!(matches!(
+            parse("atom zone0.corner atom0"),
+            Err(ParseInstructionsError::UnknownAnchor { .. })
+        ));
+    }
 }
@@ -1,12 +1,15 @@
 //! Lexer for the `.naviz` format.
 //! Use [lex] to lex into a stream of [Token]s.
 
+use std::ops::Range;
+
 use crate::common;
 use token::token;
 use winnow::{
     ascii::{multispace0, space0},
-    combinator::{preceded, repeat, terminated},
-    stream::{AsChar, Compare, FindSlice, SliceLen, Stream, StreamIsPartial},
+    combinator::{eof, preceded, repeat, terminated},
+    error::{StrContext, StrContextValue},
+    stream::{AsChar, Compare, FindSlice, LocatingSlice, SliceLen, Stream, StreamIsPartial},
     Parser,
 };
 
@@ -22,7 +25,8 @@ pub enum TimeSpec {
 /// A token of the input format
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Token<T> {
-    /// An identifier
+    /// An identifier.
+    /// May contain interpolations (`{<expression>}`), e.g., `atom{i}`.
     Identifier(T),
     /// A value; see [Value]
     Value(Value<T>),
@@ -47,10 +51,16 @@ pub enum Token<T> {
     ElementSeparator,
     /// The symbol to denote the starting-time
     TimeSymbol(TimeSpec),
+    /// The symbol to denote the duration of an instruction (`~<duration>`)
+    DurationSymbol,
     /// A directive (`#<directive`)
     Directive(T),
     /// The separator between instructions
     Separator,
+    /// An arithmetic operator
+    Operator(Operator),
+    /// The assignment-symbol of a binding (`let <name> = <value>`)
+    Assign,
 }
 
 impl<T> From<GenericToken<T>> for Token<T> {
@@ -64,6 +74,8 @@ impl<T> From<GenericToken<T>> for Token<T> {
             GenericToken::SetOpen => Self::SetOpen,
             GenericToken::SetClose => Self::SetClose,
             GenericToken::ElementSeparator => Self::ElementSeparator,
+            GenericToken::Operator(o) => Self::Operator(o),
+            GenericToken::Assign => Self::Assign,
         }
     }
 }
@@ -79,6 +91,8 @@ impl<T> From<Token<T>> for Option<GenericToken<T>> {
             Token::SetOpen => Some(GenericToken::SetOpen),
             Token::SetClose => Some(GenericToken::SetClose),
             Token::ElementSeparator => Some(GenericToken::ElementSeparator),
+            Token::Operator(o) => Some(GenericToken::Operator(o)),
+            Token::Assign => Some(GenericToken::Assign),
             _ => None,
         }
     }
@@ -103,30 +117,75 @@ where
     <I as Stream>::Token: AsChar + Clone,
     I::Slice: SliceLen,
 {
-    preceded(multispace0, repeat(0.., terminated(token, space0)))
-        .parse(input)
-        .map(|mut tokens: Vec<_>| {
-            // Ensure separator at end of token-stream
-            match tokens.last() {
-                Some(Token::Separator) => { /* Already exists */ }
-                _ => tokens.push(Token::Separator),
-            }
-            tokens
-        })
+    terminated(
+        preceded(multispace0, repeat(0.., terminated(token, space0))),
+        eof.context(StrContext::Expected(StrContextValue::Description("token"))),
+    )
+    .parse(input)
+    .map(|mut tokens: Vec<_>| {
+        // Ensure separator at end of token-stream
+        match tokens.last() {
+            Some(Token::Separator) => { /* Already exists */ }
+            _ => tokens.push(Token::Separator),
+        }
+        tokens
+    })
+}
+
+/// Lexes the `input` like [lex], but returns the byte-ranges of the lexed [Token]s.
+/// Does not contain the separator [lex] may add at the end.
+/// Can be used to locate errors of the [parser][crate::input::parser].
+pub fn token_spans(input: &str) -> Vec<Range<usize>> {
+    preceded(multispace0, repeat(0.., terminated(token.span(), space0)))
+        .parse_next(&mut LocatingSlice::new(input))
+        .unwrap_or_default()
 }
 
 pub mod token {
     use super::*;
     use winnow::{
         ascii::{line_ending, multispace0},
-        combinator::{alt, opt, terminated},
+        combinator::{alt, opt, repeat, terminated},
         stream::{AsChar, Compare, FindSlice, SliceLen, Stream, StreamIsPartial},
-        token::take_till,
+        token::{take_till, take_while},
         ModalResult, Parser,
     };
 
     pub use common::lexer::token::*;
 
+    /// Tries to parse a [Token::Identifier].
+    /// Valid identifier characters are `[0-9a-zA-Z_]`.
+    /// Identifiers may contain interpolations (`{<expression>}`, e.g., `atom{i}`)
+    /// and dots (e.g., `zone0.center`),
+    /// but have to start with an identifier character.
+    /// Interpolations may not contain `,`,
+    /// such that a set directly following an identifier (e.g., `cz{a, b}`) stays a set.
+    /// Does not allow empty identifiers.
+    pub fn identifier<
+        I: Stream + StreamIsPartial + Compare<&'static str> + FindSlice<&'static str>,
+    >(
+        input: &mut I,
+    ) -> ModalResult<Token<<I as Stream>::Slice>>
+    where
+        <I as Stream>::Token: AsChar + Clone,
+    {
+        let characters = || take_while(1.., ('0'..='9', 'a'..='z', 'A'..='Z', '_'));
+        (
+            characters(),
+            repeat::<_, _, (), _, _>(
+                0..,
+                alt((
+                    characters().void(),
+                    ".".void(),
+                    ("{", take_till(0.., [',', '}']), "}").void(),
+                )),
+            ),
+        )
+            .take()
+            .map(Token::Identifier)
+            .parse_next(input)
+    }
+
     /// Tries to parse a [Token::GroupOpen].
     pub fn group_open<Tok, I: Stream + StreamIsPartial + Compare<&'static str>>(
         input: &mut I,
@@ -154,6 +213,14 @@ pub mod token {
             .parse_next(input)
     }
 
+    /// Tries to parse a single [Token::DurationSymbol].
+    /// Must be tried after [group_open], as `~[` opens a variable group.
+    pub fn duration_symbol<I: Stream + StreamIsPartial + Compare<&'static str>>(
+        input: &mut I,
+    ) -> ModalResult<Token<<I as Stream>::Slice>> {
+        "~".map(|_| Token::DurationSymbol).parse_next(input)
+    }
+
     /// Tries to parse a single [Token::TimeSymbol].
     pub fn time_symbol<I: Stream + StreamIsPartial + Compare<&'static str>>(
         input: &mut I,
@@ -238,7 +305,10 @@ pub mod token {
             set_open,
             set_close,
             element_separator,
+            operator,
+            assign,
             time_symbol,
+            duration_symbol,
             directive,
             separator,
         ))
@@ -362,4 +432,85 @@ mod test {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn expressions() {
+        let input = "move ((i + 1) * 14, -2 - i/2) atom{i}_{2*j}\n";
+
+        let expected = vec![
+            Token::Identifier("move"),
+            Token::TupleOpen,
+            Token::TupleOpen,
+            Token::Identifier("i"),
+            Token::Operator(Operator::Add),
+            Token::Value(Value::Number("1")),
+            Token::TupleClose,
+            Token::Operator(Operator::Multiply),
+            Token::Value(Value::Number("14")),
+            Token::ElementSeparator,
+            Token::Value(Value::Number("-2")),
+            Token::Operator(Operator::Subtract),
+            Token::Identifier("i"),
+            Token::Operator(Operator::Divide),
+            Token::Value(Value::Number("2")),
+            Token::TupleClose,
+            Token::Identifier("atom{i}_{2*j}"),
+            Token::Separator,
+        ];
+
+        let actual = lex(input).expect("Failed to lex");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn duration() {
+        let input = "@0 move (1, 2) atom0 ~3\n@1 rz ~[\n";
+
+        let expected = vec![
+            Token::TimeSymbol(TimeSpec::Absolute),
+            Token::Value(Value::Number("0")),
+            Token::Identifier("move"),
+            Token::TupleOpen,
+            Token::Value(Value::Number("1")),
+            Token::ElementSeparator,
+            Token::Value(Value::Number("2")),
+            Token::TupleClose,
+            Token::Identifier("atom0"),
+            Token::DurationSymbol,
+            Token::Value(Value::Number("3")),
+            Token::Separator,
+            Token::TimeSymbol(TimeSpec::Absolute),
+            Token::Value(Value::Number("1")),
+            Token::Identifier("rz"),
+            Token::GroupOpen { variable: true },
+            Token::Separator,
+        ];
+
+        let actual = lex(input).expect("Failed to lex");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn references() {
+        let input = "move zone{i}.center + (2, 0) atom1\n";
+
+        let expected = vec![
+            Token::Identifier("move"),
+            Token::Identifier("zone{i}.center"),
+            Token::Operator(Operator::Add),
+            Token::TupleOpen,
+            Token::Value(Value::Number("2")),
+            Token::ElementSeparator,
+            Token::Value(Value::Number("0")),
+            Token::TupleClose,
+            Token::Identifier("atom1"),
+            Token::Separator,
+        ];
+
+        let actual = lex(input).expect("Failed to lex");
+
+        assert_eq!(actual, expected);
+    }
 }
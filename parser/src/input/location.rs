@@ -0,0 +1,173 @@
+//! [Location]s of instructions:
+//! Either absolute [Position]s or references to traps and zones of the machine,
+//! which are [resolved][Location::resolve] using the [MachineConfig].
+
+use std::{error::Error, fmt::Display};
+
+use fraction::Fraction;
+
+use crate::config::{
+    machine::{MachineConfig, ZoneConfig},
+    position::Position,
+};
+
+/// A location as written in the instructions.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Location {
+    /// An absolute position: `(<x>, <y>)`
+    Position(Position),
+    /// A position relative to a trap or zone of the machine:
+    /// `<reference>` or `<reference> + (<x>, <y>)`
+    Reference {
+        /// The referenced trap or zone
+        reference: Reference,
+        /// The offset from the referenced position
+        offset: Position,
+    },
+}
+
+/// A reference to a trap or zone of the machine
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Reference {
+    /// The position of a trap: `<trap>`
+    Trap(String),
+    /// A point of a zone: `<zone>.<anchor>`
+    Zone {
+        /// The id of the zone
+        id: String,
+        /// The point of the zone
+        anchor: ZoneAnchor,
+    },
+}
+
+/// A point of a zone
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ZoneAnchor {
+    /// The `from`-corner: `<zone>.from`
+    From,
+    /// The `to`-corner: `<zone>.to`
+    To,
+    /// The center: `<zone>.center`
+    Center,
+}
+
+/// A [Reference] which does not exist in the [MachineConfig]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocationError {
+    /// The machine has no trap with this id
+    UnknownTrap(String),
+    /// The machine has no zone with this id
+    UnknownZone(String),
+}
+
+impl Display for LocationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownTrap(id) => write!(f, "unknown trap `{id}`"),
+            Self::UnknownZone(id) => write!(f, "unknown zone `{id}`"),
+        }
+    }
+}
+
+impl Error for LocationError {}
+
+impl From<Position> for Location {
+    fn from(value: Position) -> Self {
+        Self::Position(value)
+    }
+}
+
+impl Location {
+    /// Moves this [Location] by the passed `offset`
+    pub fn offset(self, (x, y): Position) -> Self {
+        match self {
+            Self::Position((px, py)) => Self::Position((px + x, py + y)),
+            Self::Reference {
+                reference,
+                offset: (ox, oy),
+            } => Self::Reference {
+                reference,
+                offset: (ox + x, oy + y),
+            },
+        }
+    }
+
+    /// Resolves this [Location] to a [Position] using the traps and zones of the `machine`.
+    pub fn resolve(&self, machine: &MachineConfig) -> Result<Position, LocationError> {
+        match self {
+            Self::Position(position) => Ok(*position),
+            Self::Reference { reference, offset } => {
+                let (x, y) = reference.resolve(machine)?;
+                Ok((x + offset.0, y + offset.1))
+            }
+        }
+    }
+}
+
+impl Reference {
+    /// Parses a [Reference] from an identifier:
+    /// `<zone>.<anchor>` references a zone, any other identifier references a trap.
+    /// Returns [None] if the anchor is unknown.
+    pub fn new(identifier: String) -> Option<Self> {
+        match identifier.split_once('.') {
+            None => Some(Self::Trap(identifier)),
+            Some((id, anchor)) => Some(Self::Zone {
+                id: id.to_string(),
+                anchor: ZoneAnchor::new(anchor)?,
+            }),
+        }
+    }
+
+    /// Resolves this [Reference] to a [Position] using the traps and zones of the `machine`.
+    pub fn resolve(&self, machine: &MachineConfig) -> Result<Position, LocationError> {
+        match self {
+            Self::Trap(id) => machine
+                .trap
+                .get(id)
+                .map(|trap| trap.position)
+                .ok_or_else(|| LocationError::UnknownTrap(id.clone())),
+            Self::Zone { id, anchor } => machine
+                .zone
+                .get(id)
+                .map(|zone| anchor.position(zone))
+                .ok_or_else(|| LocationError::UnknownZone(id.clone())),
+        }
+    }
+}
+
+impl ZoneAnchor {
+    /// Parses a [ZoneAnchor] from its name,
+    /// or returns [None] if there is no anchor with this name.
+    pub fn new(name: &str) -> Option<Self> {
+        match name {
+            "from" => Some(Self::From),
+            "to" => Some(Self::To),
+            "center" => Some(Self::Center),
+            _ => None,
+        }
+    }
+
+    /// Gets the name of this [ZoneAnchor]
+    pub fn str(&self) -> &'static str {
+        match self {
+            Self::From => "from",
+            Self::To => "to",
+            Self::Center => "center",
+        }
+    }
+
+    /// Gets the position of this [ZoneAnchor] in the passed `zone`
+    pub fn position(&self, zone: &ZoneConfig) -> Position {
+        match self {
+            Self::From => zone.from,
+            Self::To => zone.to,
+            Self::Center => {
+                let two = Fraction::from(2);
+                (
+                    (zone.from.0 + zone.to.0) / two,
+                    (zone.from.1 + zone.to.1) / two,
+                )
+            }
+        }
+    }
+}
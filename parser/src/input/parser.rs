@@ -2,48 +2,91 @@
 //! Takes tokens lexed by the [lexer][super::lexer].
 
 use super::lexer::{TimeSpec, Token};
-use crate::common::{self, parser::try_into_value::TryIntoValue};
-use fraction::{Fraction, Zero};
+use crate::common::{
+    self,
+    parser::{constants::Binding, try_into_value::TryIntoValue},
+};
+use fraction::Fraction;
 use std::fmt::Debug;
 use token::{
-    comment, group_close, group_open, identifier, ignore_comments, number, separator, time_symbol,
+    comment, duration_symbol, group_close, group_open, identifier, ignore_comments, separator,
+    time_symbol,
 };
 use winnow::{
-    combinator::{alt, opt, preceded, repeat, terminated},
+    combinator::{alt, eof, opt, preceded, repeat, terminated},
+    error::{StrContext, StrContextValue},
     ModalResult, Parser,
 };
 
 // Re-export the common parser
 pub use common::parser::*;
 
+/// The time of an instruction:
+/// The [TimeSpec] and the expression following it (if any; `0` otherwise)
+pub type Time = (TimeSpec, Option<Value>);
+
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Debug, Clone)]
 pub enum InstructionOrDirective {
     /// A single instruction
     Instruction {
-        time: Option<(TimeSpec, Fraction)>,
+        /// Index of the first token of this instruction in the token-stream
+        token: usize,
+        time: Option<Time>,
         name: String,
         args: Vec<Value>,
+        /// The duration overriding the default duration (`~<duration>`)
+        duration: Option<Value>,
     },
     /// A single time with multiple instructions
     GroupedTime {
-        time: Option<(TimeSpec, Fraction)>,
+        time: Option<Time>,
         /// The durations are allowed to vary
         variable: bool,
-        /// The grouped part: instruction and arguments
-        group: Vec<(String, Vec<Value>)>,
+        /// The grouped part: instruction, arguments, and duration
+        group: Vec<(String, Vec<Value>, Option<Value>)>,
     },
     /// A single time and instructions with multiple argument-instances
     GroupedInstruction {
-        time: Option<(TimeSpec, Fraction)>,
+        time: Option<Time>,
         /// The durations are allowed to vary
         variable: bool,
         name: String,
-        /// The grouped part: argument-instances
-        group: Vec<Vec<Value>>,
+        /// The grouped part: argument-instances and durations
+        group: Vec<(Vec<Value>, Option<Value>)>,
     },
     /// A single directive
     Directive { name: String, args: Vec<Value> },
+    /// A definition which can be called like an instruction:
+    /// `#define <name> <parameters...> [ <body> ]`
+    Definition {
+        /// Index of the first token of this definition in the token-stream
+        token: usize,
+        name: String,
+        /// The names of the parameters
+        parameters: Vec<String>,
+        /// The instructions the definition expands to
+        body: Vec<InstructionOrDirective>,
+    },
+    /// A binding of a constant:
+    /// `let <name> = <value>` or `const <name> = <value>`
+    Binding {
+        binding: Binding,
+        name: String,
+        value: Value,
+    },
+    /// A block which is repeated for a range of values of a variable:
+    /// `#repeat <variable> [<from>] <to> [ <body> ]`
+    Repeat {
+        /// Index of the first token of this block in the token-stream
+        token: usize,
+        /// The name of the index-variable
+        variable: String,
+        /// The bounds of the range (`[<from>] <to>`)
+        range: Vec<Value>,
+        /// The repeated instructions
+        body: Vec<InstructionOrDirective>,
+    },
 }
 
 /// Parse a full stream of [Token]s into a [Vec] of [InstructionOrDirective]s.
@@ -53,40 +96,175 @@ pub fn parse<S: TryIntoValue + Clone + Debug + PartialEq>(
     Vec<InstructionOrDirective>,
     winnow::error::ParseError<&[Token<S>], winnow::error::ContextError>,
 > {
-    instruction_or_directives.parse(input)
+    terminated(
+        instruction_or_directives(input.len()),
+        eof.context(StrContext::Expected(StrContextValue::Description(
+            "instruction or directive",
+        ))),
+    )
+    .parse(input)
 }
 
-/// Parse all [Instruction][InstructionOrDirective::Instruction]s,
+/// Create a parser for all [Instruction][InstructionOrDirective::Instruction]s,
 /// [GroupedTime][InstructionOrDirective::GroupedTime]s,,
 /// [GroupedInstruction][InstructionOrDirective::GroupedInstruction]s,,
+/// [Definition][InstructionOrDirective::Definition]s,
+/// [Repeat][InstructionOrDirective::Repeat]s,
+/// [Binding][InstructionOrDirective::Binding]s,
 /// and [Directive][InstructionOrDirective::Directive]s
-pub fn instruction_or_directives<S: TryIntoValue + Clone + Debug + PartialEq>(
-    input: &mut &[Token<S>],
-) -> ModalResult<Vec<InstructionOrDirective>> {
-    preceded(
-        ignore_comments_and_separators,
-        repeat(
-            0..,
+/// in a stream of `len` [Token]s (used to calculate the token-indices).
+pub fn instruction_or_directives<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    len: usize,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<Vec<InstructionOrDirective>> {
+    move |input| {
+        preceded(
+            ignore_comments_and_separators,
+            repeat(
+                0..,
+                terminated(
+                    alt((
+                        instruction(len),
+                        definition(len),
+                        repetition(len),
+                        constant,
+                        directive,
+                        grouped_time,
+                        grouped_instruction,
+                    )),
+                    ignore_comments_and_separators,
+                ),
+            ),
+        )
+        .parse_next(input)
+    }
+}
+
+/// Create a parser for an [Instruction][InstructionOrDirective::Instruction]
+/// in a stream of `len` [Token]s (used to calculate the token-index).
+pub fn instruction<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    len: usize,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<InstructionOrDirective> {
+    move |input| {
+        let token = len - input.len();
+        (
+            terminated(opt(time), ignore_comments),
+            terminated(identifier, ignore_comments),
+            arguments,
+            separator,
+        )
+            .map(
+                |(time, name, (args, duration), _)| InstructionOrDirective::Instruction {
+                    token,
+                    time,
+                    name,
+                    args,
+                    duration,
+                },
+            )
+            .parse_next(input)
+    }
+}
+
+/// Create a parser for the body of a [Definition][InstructionOrDirective::Definition]
+/// or [Repeat][InstructionOrDirective::Repeat] (including the brackets)
+/// in a stream of `len` [Token]s (used to calculate the token-indices).
+/// The body may contain instructions, groups, and repeats, but no directives or definitions.
+pub fn body<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    len: usize,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<Vec<InstructionOrDirective>> {
+    move |input| {
+        let items = preceded(
+            ignore_comments_and_separators,
+            repeat(
+                0..,
+                terminated(
+                    alt((
+                        instruction(len),
+                        repetition(len),
+                        grouped_time,
+                        grouped_instruction,
+                    )),
+                    ignore_comments_and_separators,
+                ),
+            ),
+        );
+        terminated(
+            (group_open.verify(|variable| !variable), items),
+            group_close,
+        )
+        .map(|(_, items)| items)
+        .parse_next(input)
+    }
+}
+
+/// Create a parser for a [Definition][InstructionOrDirective::Definition]
+/// in a stream of `len` [Token]s (used to calculate the token-indices).
+pub fn definition<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    len: usize,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<InstructionOrDirective> {
+    move |input| {
+        let token = len - input.len();
+        (
             terminated(
-                alt((instruction, directive, grouped_time, grouped_instruction)),
-                ignore_comments_and_separators,
+                token::directive.verify(|name: &String| name == "define"),
+                ignore_comments,
             ),
-        ),
-    )
-    .parse_next(input)
+            terminated(identifier, ignore_comments),
+            repeat(0.., terminated(identifier, ignore_comments)),
+            body(len),
+            separator,
+        )
+            .map(
+                |(_, name, parameters, body, _)| InstructionOrDirective::Definition {
+                    token,
+                    name,
+                    parameters,
+                    body,
+                },
+            )
+            .parse_next(input)
+    }
+}
+
+/// Create a parser for a [Repeat][InstructionOrDirective::Repeat]
+/// in a stream of `len` [Token]s (used to calculate the token-indices).
+pub fn repetition<'a, S: TryIntoValue + Clone + Debug + PartialEq>(
+    len: usize,
+) -> impl FnMut(&mut &'a [Token<S>]) -> ModalResult<InstructionOrDirective> {
+    move |input| {
+        let token = len - input.len();
+        (
+            terminated(
+                token::directive.verify(|name: &String| name == "repeat"),
+                ignore_comments,
+            ),
+            terminated(identifier, ignore_comments),
+            repeat(0.., terminated(any_value, ignore_comments)),
+            body(len),
+            separator,
+        )
+            .map(
+                |(_, variable, range, body, _)| InstructionOrDirective::Repeat {
+                    token,
+                    variable,
+                    range,
+                    body,
+                },
+            )
+            .parse_next(input)
+    }
 }
 
-/// Try to parse an [Instruction][InstructionOrDirective::Instruction] from a stream of [Token]s.
-pub fn instruction<S: TryIntoValue + Clone + Debug + PartialEq>(
+/// Try to parse a [Binding][InstructionOrDirective::Binding] from a stream of [Token]s.
+pub fn constant<S: TryIntoValue + Clone + Debug + PartialEq>(
     input: &mut &[Token<S>],
 ) -> ModalResult<InstructionOrDirective> {
-    (
-        terminated(opt(time), ignore_comments),
-        terminated(identifier, ignore_comments),
-        repeat(0.., terminated(any_value, ignore_comments)),
-        separator,
-    )
-        .map(|(time, name, args, _)| InstructionOrDirective::Instruction { time, name, args })
+    terminated(binding, separator)
+        .map(|(binding, name, value)| InstructionOrDirective::Binding {
+            binding,
+            name,
+            value,
+        })
         .parse_next(input)
 }
 
@@ -103,28 +281,34 @@ pub fn directive<S: TryIntoValue + Clone + Debug + PartialEq>(
         .parse_next(input)
 }
 
-/// Try to parse a time ([TimeSpec] and accompanying number) from a stream of [Token]s.
-pub fn time<S: TryIntoValue + Clone + Debug>(
-    input: &mut &[Token<S>],
-) -> ModalResult<(TimeSpec, Fraction)> {
+/// Try to parse a time ([TimeSpec] and accompanying expression) from a stream of [Token]s.
+/// A single identifier is not parsed as the expression,
+/// as it may also be the name of the instruction (e.g., `@+ load atom0`).
+pub fn time<S: TryIntoValue + Clone + Debug>(input: &mut &[Token<S>]) -> ModalResult<Time> {
     (
         time_symbol,
-        opt(number).map(|n| n.unwrap_or_else(Fraction::zero)),
+        opt(any_value.verify(|value| !matches!(value, Value::Identifier(_)))),
     )
         .parse_next(input)
 }
 
+/// Try to parse a time ([TimeSpec] and accompanying expression) from a stream of [Token]s
+/// where the time is followed by a group or instruction-name (i.e., it cannot be mistaken for one).
+pub fn anchored_time<S: TryIntoValue + Clone + Debug>(
+    input: &mut &[Token<S>],
+) -> ModalResult<Time> {
+    (time_symbol, opt(any_value)).parse_next(input)
+}
+
 /// Try to parse a [GroupedTime][InstructionOrDirective::GroupedTime] from a stream of [Token]s.
 pub fn grouped_time<S: TryIntoValue + Clone + Debug + PartialEq>(
     input: &mut &[Token<S>],
 ) -> ModalResult<InstructionOrDirective> {
     let grouped_instruction = terminated(
-        (
-            terminated(identifier, ignore_comments),
-            repeat(0.., terminated(any_value, ignore_comments)),
-        ),
+        (terminated(identifier, ignore_comments), arguments),
         separator,
-    );
+    )
+    .map(|(name, (args, duration))| (name, args, duration));
 
     let grouped_instructions = preceded(
         ignore_comments_and_separators,
@@ -135,7 +319,7 @@ pub fn grouped_time<S: TryIntoValue + Clone + Debug + PartialEq>(
     );
 
     (
-        opt(time),
+        opt(anchored_time),
         terminated((group_open, grouped_instructions), group_close),
     )
         .map(
@@ -152,26 +336,31 @@ pub fn grouped_time<S: TryIntoValue + Clone + Debug + PartialEq>(
 pub fn grouped_instruction<S: TryIntoValue + Clone + Debug + PartialEq>(
     input: &mut &[Token<S>],
 ) -> ModalResult<InstructionOrDirective> {
-    let grouped_value = terminated(
-        repeat(0.., terminated(any_value, ignore_comments)),
-        separator,
-    );
+    let grouped_value = terminated(arguments, separator);
 
     let grouped_values = preceded(
         ignore_comments_and_separators,
         repeat(
             1..,
-            terminated::<_, Vec<_>, _, _, _, _>(grouped_value, ignore_comments_and_separators),
+            terminated(grouped_value, ignore_comments_and_separators),
         ),
     );
 
+    // The time may only be a single identifier if it is followed by the instruction-name
+    let time_and_name = alt((
+        (
+            anchored_time.verify(|(_, value)| value.is_some()).map(Some),
+            terminated(identifier, ignore_comments),
+        ),
+        (opt(time), terminated(identifier, ignore_comments)),
+    ));
+
     (
-        opt(time),
-        terminated(identifier, ignore_comments),
+        time_and_name,
         terminated((group_open, grouped_values), group_close),
     )
         .map(
-            |(time, name, (variable, group))| InstructionOrDirective::GroupedInstruction {
+            |((time, name), (variable, group))| InstructionOrDirective::GroupedInstruction {
                 time,
                 variable,
                 name,
@@ -181,6 +370,21 @@ pub fn grouped_instruction<S: TryIntoValue + Clone + Debug + PartialEq>(
         .parse_next(input)
 }
 
+/// Try to parse the arguments of an instruction from a stream of [Token]s,
+/// optionally followed by a duration (`~<duration>`).
+pub fn arguments<S: TryIntoValue + Clone + Debug + PartialEq>(
+    input: &mut &[Token<S>],
+) -> ModalResult<(Vec<Value>, Option<Value>)> {
+    (
+        repeat(0.., terminated(any_value, ignore_comments)),
+        opt(preceded(
+            terminated(duration_symbol, ignore_comments),
+            terminated(any_value, ignore_comments),
+        )),
+    )
+        .parse_next(input)
+}
+
 /// Ignores all [Comment][Token::Comment]s and [Separator][Token::Separator]s
 pub fn ignore_comments_and_separators<S: Clone + Debug + PartialEq + TryIntoValue>(
     input: &mut &[Token<S>],
@@ -206,6 +410,13 @@ pub mod token {
             .parse_next(input)
     }
 
+    /// Try to parse a single [Token::DurationSymbol].
+    pub fn duration_symbol<S: Clone + Debug + PartialEq>(
+        input: &mut &[Token<S>],
+    ) -> ModalResult<()> {
+        one_of([Token::DurationSymbol]).void().parse_next(input)
+    }
+
     /// Try to parse a single [Token::GroupOpen].
     pub fn group_open<S: Clone + Debug + PartialEq>(input: &mut &[Token<S>]) -> ModalResult<bool> {
         one_of(|t| matches!(t, Token::GroupOpen { .. }))
@@ -288,7 +499,7 @@ impl<T: PartialEq, const LEN: usize> winnow::stream::ContainsToken<Token<T>> for
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::common::lexer;
+    use crate::common::lexer::{self, Operator};
     use fraction::{ConstZero, Fraction};
     use regex::Regex;
 
@@ -338,6 +549,8 @@ mod test {
             Token::Separator,
             Token::Identifier("group_instruction_b"),
             Token::Value(lexer::Value::Number("2")),
+            Token::DurationSymbol,
+            Token::Value(lexer::Value::Number("3")),
             Token::Separator,
             Token::GroupClose,
             Token::Separator,
@@ -362,6 +575,7 @@ mod test {
                 args: vec![Value::String("string".to_string())],
             },
             InstructionOrDirective::Instruction {
+                token: 6,
                 time: None,
                 name: "instruction".to_string(),
                 args: vec![
@@ -369,33 +583,43 @@ mod test {
                     Value::String("argument".to_string()),
                     Value::Regex(Regex::new("argument").unwrap()),
                 ],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
-                time: Some((TimeSpec::Absolute, Fraction::new(0u64, 1u64))),
+                token: 11,
+                time: Some((
+                    TimeSpec::Absolute,
+                    Some(Value::Number(Fraction::new(0u64, 1u64))),
+                )),
                 name: "timed_instruction".to_string(),
                 args: vec![Value::Identifier("arg".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 16,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: false,
                         positive: false,
                     },
-                    Fraction::new(1u64, 1u64),
+                    Some(Value::Number(Fraction::new(1u64, 1u64))),
                 )),
                 name: "negative_timed_instruction".to_string(),
                 args: vec![Value::Identifier("arg".to_string())],
+                duration: None,
             },
             InstructionOrDirective::Instruction {
+                token: 21,
                 time: Some((
                     TimeSpec::Relative {
                         from_start: true,
                         positive: true,
                     },
-                    Fraction::new(2u64, 1u64),
+                    Some(Value::Number(Fraction::new(2u64, 1u64))),
                 )),
                 name: "positive_start_timed_instruction".to_string(),
                 args: vec![Value::Identifier("arg".to_string())],
+                duration: None,
             },
             InstructionOrDirective::GroupedTime {
                 time: Some((
@@ -403,17 +627,19 @@ mod test {
                         from_start: false,
                         positive: true,
                     },
-                    Fraction::new(0u64, 1u64),
+                    None,
                 )),
                 variable: false,
                 group: vec![
                     (
                         "group_instruction_a".to_string(),
                         vec![Value::Number(Fraction::new(1u64, 1u64))],
+                        None,
                     ),
                     (
                         "group_instruction_b".to_string(),
                         vec![Value::Number(Fraction::new(2u64, 1u64))],
+                        Some(Value::Number(Fraction::new(3u64, 1u64))),
                     ),
                 ],
             },
@@ -422,8 +648,8 @@ mod test {
                 variable: true,
                 name: "group_instruction".to_string(),
                 group: vec![
-                    vec![Value::Number(Fraction::new(1u64, 1u64))],
-                    vec![Value::Number(Fraction::new(2u64, 1u64))],
+                    (vec![Value::Number(Fraction::new(1u64, 1u64))], None),
+                    (vec![Value::Number(Fraction::new(2u64, 1u64))], None),
                 ],
             },
         ];
@@ -513,7 +739,8 @@ mod test {
         ];
 
         let expected = vec![InstructionOrDirective::Instruction {
-            time: Some((TimeSpec::Absolute, Fraction::ZERO)),
+            token: 0,
+            time: Some((TimeSpec::Absolute, Some(Value::Number(Fraction::ZERO)))),
             name: "timed_instruction".to_string(),
             args: vec![Value::Set(vec![
                 Value::Identifier("t1".to_string()),
@@ -521,6 +748,7 @@ mod test {
                 Value::Set(vec![Value::Identifier("t3".to_string())]),
                 Value::Set(vec![Value::Set(vec![Value::Identifier("t4".to_string())])]),
             ])],
+            duration: None,
         }];
 
         let actual = parse(&input).expect("Failed to parse");
@@ -571,4 +799,49 @@ mod test {
 
         parse(&input).expect_err("Invalid input was parsed without error");
     }
+
+    #[test]
+    fn expression_precedence() {
+        // `1 - i * 2 - 3` is parsed as `(1 - (i * 2)) - 3`
+        let input = vec![
+            Token::Identifier("rz"),
+            Token::Value(lexer::Value::Number("1")),
+            Token::Operator(Operator::Subtract),
+            Token::Identifier("i"),
+            Token::Operator(Operator::Multiply),
+            Token::Value(lexer::Value::Number("2")),
+            Token::Operator(Operator::Subtract),
+            Token::Value(lexer::Value::Number("3")),
+            Token::Identifier("atom0"),
+            Token::Separator,
+        ];
+
+        let number = |n: u64| Box::new(Value::Number(Fraction::from(n)));
+        let expected = vec![InstructionOrDirective::Instruction {
+            token: 0,
+            time: None,
+            name: "rz".to_string(),
+            args: vec![
+                Value::Operation(
+                    Box::new(Value::Operation(
+                        number(1),
+                        Operator::Subtract,
+                        Box::new(Value::Operation(
+                            Box::new(Value::Identifier("i".to_string())),
+                            Operator::Multiply,
+                            number(2),
+                        )),
+                    )),
+                    Operator::Subtract,
+                    number(3),
+                ),
+                Value::Identifier("atom0".to_string()),
+            ],
+            duration: None,
+        }];
+
+        let actual = parse(&input).expect("Failed to parse");
+
+        assert_eq!(actual, expected);
+    }
 }
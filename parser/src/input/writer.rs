@@ -0,0 +1,379 @@
+//! Writes [Instructions] back to the `.naviz`-format.
+//!
+//! [Instructions], [SetupInstruction]s, and [TimedInstruction]s implement [Display],
+//! which emits valid `.naviz`-text.
+//! Relative times and groups are reconstructed from the [AbsoluteTimeline],
+//! such that lexing, parsing, and [converting][Instructions::new] the output
+//! results in equal [Instructions].
+
+use std::fmt::{self, Display};
+
+use fraction::{Decimal, Fraction, Zero};
+use itertools::Itertools;
+
+use super::{
+    concrete::{
+        InstructionGroup, Instructions, RelativeTimeline, SetupInstruction, Target,
+        TimedInstruction,
+    },
+    location::{Location, Reference},
+};
+use crate::{common::color::Color, config::position::Position};
+
+/// Displays a [Fraction] as a decimal number,
+/// or as a division (`<numerator> / <denominator>`)
+/// if its decimal expansion does not terminate (e.g., `1 / 3`)
+struct Number<'a>(&'a Fraction);
+
+impl Display for Number<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.0.numer(), self.0.denom()) {
+            (Some(numerator), Some(&denominator)) if !terminates(denominator) => {
+                let sign = if self.0.is_sign_negative() { "-" } else { "" };
+                write!(f, "{sign}{numerator} / {denominator}")
+            }
+            _ => write!(f, "{}", Decimal::from_fraction(*self.0)),
+        }
+    }
+}
+
+/// Whether a fraction with the passed (reduced) `denominator`
+/// has a terminating decimal expansion,
+/// i.e., whether the `denominator` has no prime factors other than `2` and `5`
+fn terminates(mut denominator: u64) -> bool {
+    for factor in [2, 5] {
+        while denominator != 0 && denominator % factor == 0 {
+            denominator /= factor;
+        }
+    }
+    denominator == 1
+}
+
+/// Displays a [Color] as a hex-color (`#rrggbbaa`)
+struct Hex<'a>(&'a Color);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Color { r, g, b, a } = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+/// Displays a [Position] as a tuple
+struct Tuple<'a>(&'a Position);
+
+impl Display for Tuple<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", Number(&self.0 .0), Number(&self.0 .1))
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trap(id) => f.write_str(id),
+            Self::Zone { id, anchor } => write!(f, "{id}.{}", anchor.str()),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Position(position) => write!(f, "{}", Tuple(position)),
+            Self::Reference { reference, offset } => {
+                write!(f, "{reference}")?;
+                if offset.0.is_zero() && offset.1.is_zero() {
+                    Ok(())
+                } else {
+                    write!(f, " + {}", Tuple(offset))
+                }
+            }
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => f.write_str(id),
+            Self::Regex(regex) => f.write_str(regex.as_str()),
+        }
+    }
+}
+
+/// Displays targets as a single target or as a set
+struct Targets<'a>(&'a [Target]);
+
+impl Display for Targets<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            [target] => write!(f, "{target}"),
+            targets => write!(f, "{{{}}}", targets.iter().join(", ")),
+        }
+    }
+}
+
+/// Displays the time of an entry in a [RelativeTimeline]: `(from_start, offset)`
+struct RelativeTime<'a>(bool, &'a Fraction);
+
+impl Display for RelativeTime<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(from_start, offset) = *self;
+        f.write_str(if from_start { "@=" } else { "@" })?;
+        match (offset.is_zero(), offset.is_sign_negative()) {
+            (true, _) if from_start => Ok(()),
+            (true, _) => f.write_str("+"),
+            (false, false) => write!(f, "+{}", Number(offset)),
+            (false, true) => write!(f, "-{}", Number(&-*offset)),
+        }
+    }
+}
+
+impl Display for SetupInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Atom { position, id } => write!(f, "atom {position} {id}"),
+        }
+    }
+}
+
+impl Display for TimedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.str())?;
+        match self {
+            Self::Load { position, id } | Self::Store { position, id } => match position {
+                Some(position) => write!(f, " {position} {id}"),
+                None => write!(f, " {id}"),
+            },
+            Self::Move { position, id } | Self::Spawn { position, id } => {
+                write!(f, " {position} {id}")
+            }
+            Self::Lose { id } => write!(f, " {id}"),
+            Self::Rz { value, targets } | Self::Ry { value, targets } => {
+                write!(f, " {} {}", Number(value), Targets(targets))
+            }
+            Self::Cz { targets } | Self::Measure { targets } => write!(f, " {}", Targets(targets)),
+            Self::Mcz { qubits, targets } => write!(f, " {qubits} {}", Targets(targets)),
+            Self::Gate {
+                name,
+                parameters,
+                targets,
+            } => {
+                write!(f, " {name}")?;
+                for parameter in parameters {
+                    write!(f, " {}", Number(parameter))?;
+                }
+                write!(f, " {}", Targets(targets))
+            }
+            Self::Caption {
+                text,
+                duration,
+                position,
+            } => {
+                write!(f, " \"{text}\" {}", Number(duration))?;
+                match position {
+                    Some(position) => write!(f, " {position}"),
+                    None => Ok(()),
+                }
+            }
+            Self::Highlight {
+                color,
+                duration,
+                outline,
+                targets,
+            } => {
+                write!(f, " {} {}", Hex(color), Number(duration))?;
+                if let Some(outline) = outline {
+                    write!(f, " {}", Number(outline))?;
+                }
+                write!(f, " {}", Targets(targets))
+            }
+        }
+    }
+}
+
+/// Displays a [TimedInstruction] followed by its duration (`~<duration>`), if any
+struct WithDuration<'a>(&'a TimedInstruction, &'a Option<Fraction>);
+
+impl Display for WithDuration<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        match self.1 {
+            Some(duration) => write!(f, " ~{}", Number(duration)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for InstructionGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instructions.as_slice() {
+            [(instruction, duration)] if !self.variable => {
+                write!(f, "{}", WithDuration(instruction, duration))
+            }
+            instructions => {
+                f.write_str(if self.variable { "~[\n" } else { "[\n" })?;
+                for (instruction, duration) in instructions {
+                    writeln!(f, "\t{}", WithDuration(instruction, duration))?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+/// Whether the [RelativeTimeline] was started by an absolute time.
+/// Otherwise, it was started by a relative time before any absolute time.
+fn starts_absolute(timeline: &RelativeTimeline) -> bool {
+    matches!(timeline.first(), Some((true, offset, _)) if offset.is_zero())
+}
+
+impl Display for Instructions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for target in &self.directives.targets {
+            writeln!(f, "#target {target}")?;
+        }
+        for (name, members) in &self.directives.groups {
+            writeln!(f, "#group {name} {{{}}}", members.iter().join(", "))?;
+        }
+
+        let has_directives =
+            !self.directives.targets.is_empty() || !self.directives.groups.is_empty();
+        if has_directives && !self.setup.is_empty() {
+            writeln!(f)?;
+        }
+        for instruction in &self.setup {
+            writeln!(f, "{instruction}")?;
+        }
+
+        // A timeline started by a relative time has to come before all absolute times
+        let (relative, absolute): (Vec<_>, Vec<_>) = self
+            .instructions
+            .iter()
+            .partition(|(_, timeline)| !starts_absolute(timeline));
+        for (time, timeline) in relative.into_iter().chain(absolute) {
+            writeln!(f)?;
+            for (idx, (from_start, offset, group)) in timeline.iter().enumerate() {
+                if idx == 0 && starts_absolute(timeline) {
+                    writeln!(f, "@{} {group}", Number(time))?;
+                } else {
+                    writeln!(f, "{} {group}", RelativeTime(*from_start, offset))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::{lexer, parser};
+
+    fn parse(input: &str) -> Instructions {
+        let lexed = lexer::lex(input).expect("Failed to lex");
+        let parsed = parser::parse(&lexed).expect("Failed to parse");
+        Instructions::new(parsed).expect("Failed to convert")
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = r#"
+            @=+0.5 rz 1 atom0
+            @- cz {atom0, atom1}
+            #target machine0
+            #target machine1
+            atom (0, 0) atom0
+            atom (-1.5, 2.25) atom1
+            @1 load atom0
+            @+ [
+                move (1, 2) atom0
+                ry -0.125 zone0
+            ]
+            @+ move trap0 atom0
+            @+ move zone0.center + (-1, 0.5) atom0
+            @=-0.5 store (0, 1) atom0
+            @= load (1, 1) atom1
+            @3.5 ~[
+                ccz {atom0, atom1}
+            ]
+            @+2 mcz 4 zone0
+            @1 gate u3 0.5 0 1 atom0
+            @1 gate h {atom0, atom1}
+            @2 spawn (1, 2) atom2
+            @2 lose atom1
+            @3 measure {atom0, atom2}
+            #group data {atom0, ^atom[12]$}
+            @6 rz 0.5 data
+            @6 ry 1 {^atom.*$, zone0}
+            @4 move (2, 2) atom2 ~1.5
+            @6 caption "Stage 2: entangling layer" 2.5
+            @= caption "moving" 1 zone0.center + (0, 1)
+            @= highlight #ff000080 2 {atom0, atom1}
+            @= highlight #00ff00ff 1.5 0.25 atom0
+            @5 ~[
+                rz 0.5 atom0 ~0.25
+                ry 0.5 atom2
+            ]
+            @2 rz [
+                0.5 atom0
+                0.25 atom1
+            ]
+        "#;
+        let instructions = parse(input);
+        let written = instructions.to_string();
+        assert_eq!(parse(&written), instructions);
+    }
+
+    #[test]
+    fn round_trip_thirds() {
+        let input = r#"
+            atom (1 / 3, -2 / 3) atom0
+            @0 rz 1 / 3 atom0
+            @1 gate u3 0.5 -1 / 3 2 / 7 atom0
+            @2 move zone0.center + (0, 1 / 3) atom0 ~1 / 3
+        "#;
+        let instructions = parse(input);
+        assert!(matches!(
+            &instructions.instructions[1].1[0].2.instructions[0].0,
+            TimedInstruction::Gate { parameters, .. }
+                if parameters == &[
+                    Fraction::new(1u64, 2u64),
+                    -Fraction::new(1u64, 3u64),
+                    Fraction::new(2u64, 7u64),
+                ]
+        ));
+        let written = instructions.to_string();
+        assert!(written.contains("rz 1 / 3 atom0"), "Written as: {written}");
+        assert_eq!(parse(&written), instructions);
+    }
+
+    #[test]
+    fn output() {
+        let input = r#"
+            #target machine0
+            atom (0, 0.5) atom0
+            @0 load atom0
+            @=+1 [
+                move (1, 2) atom0
+                cz {atom0, zone0}
+            ]
+            @-0.5 store atom0
+        "#;
+        assert_eq!(
+            parse(input).to_string(),
+            "#target machine0\n\
+             \n\
+             atom (0, 0.5) atom0\n\
+             \n\
+             @0 load atom0\n\
+             @=+1 [\n\
+             \tmove (1, 2) atom0\n\
+             \tcz {atom0, zone0}\n\
+             ]\n\
+             @-0.5 store atom0\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub mod common;
 pub mod config;
+pub mod diagnostic;
+pub mod include;
 pub mod input;
 
 /// Error returned when parsing/lexing.
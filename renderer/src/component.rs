@@ -21,6 +21,7 @@ use crate::{
 };
 
 pub mod atoms;
+pub mod captions;
 pub mod drawable;
 pub mod legend;
 pub mod machine;
@@ -140,39 +140,61 @@ impl Updatable for Atoms {
 }
 
 #[derive(Clone, Debug)]
-struct AtomSpec<'a, TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>> {
+pub(crate) struct AtomSpec<'a, TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>>
+{
     /// Circles representing the atoms
-    atom_circles: Vec<CircleSpec>,
+    pub(crate) atom_circles: Vec<CircleSpec>,
     /// Lines representing the atom shuttles
-    shuttles: Vec<LineSpec>,
+    pub(crate) shuttles: Vec<LineSpec>,
     /// Labels drawn over the atoms at their positions
-    labels: TextSpec<'a, TextIterator>,
+    pub(crate) labels: TextSpec<'a, TextIterator>,
 }
 
 /// Gets the specs for [Atoms] from the passed [State] and [Config].
-fn get_specs<'a>(
+pub(crate) fn get_specs<'a>(
     config: &'a Config,
     state: &'a State,
     viewport_projection: ViewportProjection,
 ) -> AtomSpec<'a, impl IntoIterator<Item = (&'a str, (f32, f32), Alignment)>> {
-    let atoms = &state.atoms;
+    // Atoms which are not visible (e.g., not yet spawned or already lost) are not drawn
+    let atoms: Vec<_> = state
+        .atoms
+        .iter()
+        .filter(|atom| atom.opacity > 0.)
+        .collect();
     let AtomsConfig { shuttle, label } = &config.atoms;
 
     // The circles for the atoms
     let atom_circles: Vec<_> = atoms
         .iter()
-        .map(
+        .flat_map(
             |AtomState {
                  position,
                  size,
                  color,
                  shuttle: _,
                  label: _,
-             }| CircleSpec {
-                center: (*position).into(),
-                radius: *size,
-                color: *color,
-                radius_inner: 0.,
+                 opacity,
+                 outline,
+             }| {
+                let fade = |mut color: [u8; 4]| {
+                    color[3] = (color[3] as f32 * opacity.min(1.)) as u8;
+                    color
+                };
+                let atom = CircleSpec {
+                    center: (*position).into(),
+                    radius: *size,
+                    color: fade(*color),
+                    radius_inner: 0.,
+                };
+                // The outline is a ring around the atom
+                let outline = outline.map(|(width, color)| CircleSpec {
+                    center: (*position).into(),
+                    radius: *size + width,
+                    color: fade(color),
+                    radius_inner: *size,
+                });
+                std::iter::once(atom).chain(outline)
             },
         )
         .collect();
@@ -188,6 +210,8 @@ fn get_specs<'a>(
                  color: _,
                  shuttle: _,
                  label: _,
+                 opacity: _,
+                 outline: _,
              }| {
                 [
                     LineSpec {
@@ -221,6 +245,8 @@ fn get_specs<'a>(
                  color: _,
                  shuttle: _,
                  label,
+                 opacity: _,
+                 outline: _,
              }| {
                 (
                     label.as_str(),
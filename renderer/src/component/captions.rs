@@ -0,0 +1,127 @@
+use naviz_state::{
+    config::Config,
+    state::{CaptionState, State},
+};
+use wgpu::{Device, Queue, RenderPass};
+
+use crate::{
+    buffer_updater::BufferUpdater, component::drawable::Drawable, viewport::ViewportProjection,
+};
+
+use super::{
+    primitive::text::{Alignment, HAlignment, Text, TextSpec, VAlignment},
+    updatable::Updatable,
+    ComponentInit,
+};
+
+/// A component to display the captions over the content
+pub struct Captions {
+    text: Text,
+    viewport_projection: ViewportProjection,
+}
+
+impl Captions {
+    pub fn new(
+        ComponentInit {
+            device,
+            queue,
+            format,
+            globals: _,
+            shader_composer: _,
+            config,
+            state,
+            viewport_projection,
+            screen_resolution,
+        }: ComponentInit,
+    ) -> Self {
+        Self {
+            text: Text::new(
+                device,
+                queue,
+                format,
+                get_specs(config, state, viewport_projection),
+                screen_resolution,
+            ),
+            viewport_projection,
+        }
+    }
+
+    /// Updates the viewport resolution of these [Captions]
+    pub fn update_viewport(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        screen_resolution: (u32, u32),
+    ) {
+        self.text
+            .update_viewport((device, queue), screen_resolution);
+    }
+}
+
+impl Drawable for Captions {
+    /// Draws these [Captions].
+    ///
+    /// May overwrite bind groups.
+    /// If `REBIND` is `true`, will call the passed `rebind`-function to rebind groups.
+    #[inline]
+    fn draw<const REBIND: bool>(
+        &self,
+        render_pass: &mut RenderPass<'_>,
+        rebind: impl Fn(&mut RenderPass),
+    ) {
+        self.text.draw::<REBIND>(render_pass, rebind);
+    }
+}
+
+impl Updatable for Captions {
+    fn update(
+        &mut self,
+        _updater: &mut impl BufferUpdater,
+        device: &Device,
+        queue: &Queue,
+        config: &Config,
+        state: &State,
+    ) {
+        self.text.update(
+            (device, queue),
+            get_specs(config, state, self.viewport_projection),
+        );
+    }
+
+    fn update_full(
+        &mut self,
+        updater: &mut impl BufferUpdater,
+        device: &Device,
+        queue: &Queue,
+        config: &Config,
+        state: &State,
+        viewport_projection: ViewportProjection,
+    ) {
+        self.viewport_projection = viewport_projection;
+        self.update(updater, device, queue, config, state);
+    }
+}
+
+/// Gets the specs for [Captions] from the passed [State] and [Config].
+pub(crate) fn get_specs<'a>(
+    config: &'a Config,
+    state: &'a State,
+    viewport_projection: ViewportProjection,
+) -> TextSpec<'a, impl IntoIterator<Item = (&'a str, (f32, f32), Alignment)>> {
+    TextSpec {
+        viewport_projection,
+        font_size: config.caption.font.size,
+        font_family: &config.caption.font.family,
+        texts: state
+            .captions
+            .iter()
+            .map(|CaptionState { text, position }| {
+                (
+                    text.as_str(),
+                    *position,
+                    Alignment(HAlignment::Center, VAlignment::Center),
+                )
+            }),
+        color: config.caption.font.color,
+    }
+}
@@ -109,15 +109,18 @@ impl Updatable for Legend {
 }
 
 #[derive(Clone, Debug)]
-struct LegendSpec<'a, TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>> {
+pub(crate) struct LegendSpec<
+    'a,
+    TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>,
+> {
     /// The legend text to draw
-    text: TextSpec<'a, TextIterator>,
+    pub(crate) text: TextSpec<'a, TextIterator>,
     /// The circles representing the colors to the left of the text
-    colors: Vec<CircleSpec>,
+    pub(crate) colors: Vec<CircleSpec>,
 }
 
 /// Gets the specs for [Legend] from the passed [State] and [Config].
-fn get_specs(
+pub(crate) fn get_specs(
     config: &Config,
     viewport_projection: ViewportProjection,
 ) -> LegendSpec<'_, impl IntoIterator<Item = (&'_ str, (f32, f32), Alignment)>> {
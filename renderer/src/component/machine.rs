@@ -151,19 +151,22 @@ fn range_f32(start: f32, end: f32, step: f32) -> impl Iterator<Item = f32> {
 }
 
 #[derive(Clone, Debug)]
-struct MachineSpec<'a, TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>> {
+pub(crate) struct MachineSpec<
+    'a,
+    TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>,
+> {
     /// The coordinate grid
-    lines: Vec<LineSpec>,
+    pub(crate) lines: Vec<LineSpec>,
     /// Circles to draw to represent the traps
-    traps: Vec<CircleSpec>,
+    pub(crate) traps: Vec<CircleSpec>,
     /// Axis labels (including the numbers)
-    labels: TextSpec<'a, TextIterator>,
+    pub(crate) labels: TextSpec<'a, TextIterator>,
     /// Rectangles to draw for the zones
-    zones: Vec<RectangleSpec>,
+    pub(crate) zones: Vec<RectangleSpec>,
 }
 
 /// Gets the specs for [Machine] from the passed [State] and [Config].
-fn get_specs<'a>(
+pub(crate) fn get_specs<'a>(
     config: &'a Config,
     viewport_projection: ViewportProjection,
     text_buffer: &'a mut Vec<(String, (f32, f32), Alignment)>,
@@ -60,7 +60,9 @@ impl Rectangles {
 }
 
 /// Converts a slice of [RectangleSpec]s to a [Vec] of [LineSpec]s
-fn rectangles_to_lines(rectangles: impl IntoIterator<Item = RectangleSpec>) -> Vec<LineSpec> {
+pub(crate) fn rectangles_to_lines(
+    rectangles: impl IntoIterator<Item = RectangleSpec>,
+) -> Vec<LineSpec> {
     rectangles
         .into_iter()
         .flat_map(
@@ -47,15 +47,15 @@ pub struct TextSpec<'a, TextIterator: IntoIterator<Item = (&'a str, (f32, f32),
 ///
 /// Create using [BakeCache::create],
 /// fully bake using [Text::bake].
-struct BakeCache {
+pub(crate) struct BakeCache {
     /// The text-buffers
-    text_buffers: Vec<(Buffer, (f32, f32), Alignment)>,
+    pub(crate) text_buffers: Vec<(Buffer, (f32, f32), Alignment)>,
     /// The text color
-    color: Color,
+    pub(crate) color: Color,
     /// The viewport projection to render in
-    viewport_projection: ViewportProjection,
+    pub(crate) viewport_projection: ViewportProjection,
     /// The last screen resolution
-    screen_resolution: (u32, u32),
+    pub(crate) screen_resolution: (u32, u32),
 }
 
 /// A component that renders text
@@ -76,12 +76,7 @@ impl Text {
         spec: TextSpec<'a, TextIterator>,
         screen_resolution: (u32, u32),
     ) -> Self {
-        let mut font_system = FontSystem::new();
-        // Load a default font
-        // Used when system-fonts cannot be loaded (e.g., on web)
-        font_system
-            .db_mut()
-            .load_font_data(include_bytes!(env!("DEFAULT_FONT_PATH")).to_vec());
+        let mut font_system = create_font_system();
 
         let swash_cache = SwashCache::new();
         let cache = Cache::new(device);
@@ -193,7 +188,10 @@ impl Text {
 
 impl BakeCache {
     /// Creates a new [BakeCache] from the passed [TextSpec]
-    fn create<'a, TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>>(
+    pub(crate) fn create<
+        'a,
+        TextIterator: IntoIterator<Item = (&'a str, (f32, f32), Alignment)>,
+    >(
         TextSpec {
             viewport_projection,
             font_size,
@@ -225,8 +223,19 @@ impl BakeCache {
     }
 }
 
+/// Creates a new [FontSystem] with the default font loaded.
+pub(crate) fn create_font_system() -> FontSystem {
+    let mut font_system = FontSystem::new();
+    // Load a default font
+    // Used when system-fonts cannot be loaded (e.g., on web)
+    font_system
+        .db_mut()
+        .load_font_data(include_bytes!(env!("DEFAULT_FONT_PATH")).to_vec());
+    font_system
+}
+
 /// Creates a [glyphon::Buffer] of the passed `text`.
-fn to_text_buffer(
+pub(crate) fn to_text_buffer(
     text: &str,
     font_system: &mut FontSystem,
     font_size: f32,
@@ -246,7 +255,7 @@ fn to_text_buffer(
 
 /// Creates a [TextArea] of the passed [glyphon::Buffer].
 /// Will handle alignment.
-fn to_text_area(
+pub(crate) fn to_text_area(
     text_buffer: &Buffer,
     (x, y): (f32, f32),
     alignment: Alignment,
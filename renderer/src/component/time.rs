@@ -100,7 +100,7 @@ impl Updatable for Time {
 }
 
 /// Gets the specs for [Time] from the passed [State] and [Config].
-fn get_specs<'a>(
+pub(crate) fn get_specs<'a>(
     config: &'a Config,
     state: &'a State,
     viewport_projection: ViewportProjection,
@@ -4,4 +4,6 @@ pub mod globals;
 pub mod layout;
 pub mod renderer;
 pub mod shaders;
+pub mod software;
+pub mod svg;
 pub mod viewport;
@@ -5,6 +5,7 @@ use crate::{
     buffer_updater::BufferUpdater,
     component::{
         atoms::Atoms,
+        captions::Captions,
         drawable::{Drawable, Hidable},
         legend::Legend,
         machine::Machine,
@@ -24,6 +25,7 @@ pub struct Renderer {
 
     machine: Machine,
     atoms: Atoms,
+    captions: Captions,
     legend: Hidable<Legend>,
     time: Hidable<Time>,
     screen_resolution: (u32, u32),
@@ -76,6 +78,17 @@ impl Renderer {
                 viewport_projection: content,
                 screen_resolution,
             }),
+            captions: Captions::new(ComponentInit {
+                device,
+                queue,
+                format,
+                globals: &globals,
+                shader_composer: &mut composer,
+                config,
+                state,
+                viewport_projection: content,
+                screen_resolution,
+            }),
             legend: Hidable::new(Legend::new(ComponentInit {
                 device,
                 queue,
@@ -124,6 +137,7 @@ impl Renderer {
     ) {
         self.machine.update(updater, device, queue, config, state);
         self.atoms.update(updater, device, queue, config, state);
+        self.captions.update(updater, device, queue, config, state);
         self.legend.update(updater, device, queue, config, state);
         self.time.update(updater, device, queue, config, state);
     }
@@ -148,6 +162,8 @@ impl Renderer {
             .update_full(updater, device, queue, config, state, content);
         self.atoms
             .update_full(updater, device, queue, config, state, content);
+        self.captions
+            .update_full(updater, device, queue, config, state, content);
         self.legend.update_full(
             updater,
             device,
@@ -181,6 +197,8 @@ impl Renderer {
         self.machine
             .update_viewport(device, queue, screen_resolution);
         self.atoms.update_viewport(device, queue, screen_resolution);
+        self.captions
+            .update_viewport(device, queue, screen_resolution);
         self.time.update_viewport(device, queue, screen_resolution);
     }
 
@@ -190,6 +208,7 @@ impl Renderer {
 
         self.machine.draw::<true>(render_pass, self.rebind_fn());
         self.atoms.draw::<true>(render_pass, self.rebind_fn());
+        self.captions.draw::<true>(render_pass, self.rebind_fn());
         self.legend.draw::<false>(render_pass, self.rebind_fn()); // No rebind: time does not need globals
         self.time.draw::<false>(render_pass, self.rebind_fn());
     }
@@ -210,7 +229,11 @@ impl Renderer {
 /// Gets the [Layout] to use based on the passed [Config].
 /// Will detect which [Layout] to use based on which parts should be displayed in the [Config].
 /// If `force_content_only` is `true`, will always use [Layout::new_content_only].
-fn get_layout(config: &Config, screen_resolution: (u32, u32), force_content_only: bool) -> Layout {
+pub fn get_layout(
+    config: &Config,
+    screen_resolution: (u32, u32),
+    force_content_only: bool,
+) -> Layout {
     const CONTENT_PADDING_Y: f32 = 36.;
     const LEGEND_HEIGHT: f32 = 1024.;
 
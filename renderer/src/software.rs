@@ -0,0 +1,372 @@
+//! A renderer which renders the visualization on the CPU.
+//!
+//! The [SoftwareRenderer] draws the same components as the [Renderer][crate::renderer::Renderer],
+//! but does not need a GPU (or any [wgpu]-adapter).
+//! This allows rendering on headless machines (e.g., CI-runners or compute-clusters).
+
+use glyphon::{FontSystem, SwashCache};
+use naviz_state::{config::Config, state::State, Color};
+
+use crate::{
+    component::{
+        atoms::{self, AtomSpec},
+        captions,
+        legend::{self, LegendSpec},
+        machine::{self, MachineSpec},
+        primitive::{
+            circles::CircleSpec,
+            lines::LineSpec,
+            rectangles::rectangles_to_lines,
+            text::{create_font_system, to_text_area, Alignment, BakeCache, TextSpec},
+        },
+        time,
+    },
+    layout::Layout,
+    renderer::get_layout,
+    viewport::ViewportProjection,
+};
+
+pub mod raster;
+
+use raster::Canvas;
+
+/// A primitive which can be drawn by the [SoftwareRenderer]
+enum Primitive {
+    Lines(Vec<LineSpec>),
+    Circles(Vec<CircleSpec>),
+    Text(BakeCache),
+}
+
+/// A list of [Primitive]s which are drawn (in order) in a common [ViewportProjection].
+/// Corresponds to a component of the [Renderer][crate::renderer::Renderer].
+struct Layer {
+    viewport_projection: ViewportProjection,
+    primitives: Vec<Primitive>,
+}
+
+/// The software renderer, which renders the visualization output on the CPU.
+///
+/// Mirrors the interface of the [Renderer][crate::renderer::Renderer],
+/// but renders into an internal [Canvas] instead of a render-pass.
+pub struct SoftwareRenderer {
+    canvas: Canvas,
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+
+    machine: Layer,
+    atoms: Layer,
+    captions: Layer,
+    legend: Option<Layer>,
+    time: Option<Layer>,
+    /// Whether to force the [content-only-layout][Layout::new_content_only].
+    /// Independent of the selected style.
+    force_zen: bool,
+}
+
+impl SoftwareRenderer {
+    /// Creates a new [SoftwareRenderer] rendering at the passed `screen_resolution`
+    pub fn new(config: &Config, state: &State, screen_resolution: (u32, u32)) -> Self {
+        let mut font_system = create_font_system();
+
+        let Layout {
+            content,
+            legend,
+            time,
+        } = get_layout(config, screen_resolution, false);
+
+        Self {
+            machine: machine_layer(config, content, screen_resolution, &mut font_system),
+            atoms: atoms_layer(config, state, content, screen_resolution, &mut font_system),
+            captions: captions_layer(config, state, content, screen_resolution, &mut font_system),
+            legend: legend
+                .map(|legend| legend_layer(config, legend, screen_resolution, &mut font_system)),
+            time: time
+                .map(|time| time_layer(config, state, time, screen_resolution, &mut font_system)),
+            canvas: Canvas::new(screen_resolution),
+            font_system,
+            swash_cache: SwashCache::new(),
+            force_zen: false,
+        }
+    }
+
+    /// Whether to force the [content-only-layout][Layout::new_content_only].
+    /// Independent of the selected style.
+    pub fn set_force_zen(&mut self, force_zen: bool) {
+        self.force_zen = force_zen;
+    }
+
+    /// Updates this [SoftwareRenderer] to resemble the new [State].
+    /// The passed [Config] is assumed to be unchanged;
+    /// if the [Config] changed, use [SoftwareRenderer::update_full] instead.
+    pub fn update(&mut self, config: &Config, state: &State) {
+        let screen_resolution = self.canvas.size();
+        self.atoms = atoms_layer(
+            config,
+            state,
+            self.atoms.viewport_projection,
+            screen_resolution,
+            &mut self.font_system,
+        );
+        self.captions = captions_layer(
+            config,
+            state,
+            self.captions.viewport_projection,
+            screen_resolution,
+            &mut self.font_system,
+        );
+        if let Some(time) = &mut self.time {
+            *time = time_layer(
+                config,
+                state,
+                time.viewport_projection,
+                screen_resolution,
+                &mut self.font_system,
+            );
+        }
+    }
+
+    /// Updates this [SoftwareRenderer] to resemble the new [State] and [Config].
+    /// For updates, which _only_ changed the [State], use [SoftwareRenderer::update] instead.
+    pub fn update_full(&mut self, config: &Config, state: &State) {
+        let screen_resolution = self.canvas.size();
+        let Layout {
+            content,
+            legend,
+            time,
+        } = get_layout(config, screen_resolution, self.force_zen);
+
+        let font_system = &mut self.font_system;
+        self.machine = machine_layer(config, content, screen_resolution, font_system);
+        self.atoms = atoms_layer(config, state, content, screen_resolution, font_system);
+        self.captions = captions_layer(config, state, content, screen_resolution, font_system);
+        self.legend =
+            legend.map(|legend| legend_layer(config, legend, screen_resolution, font_system));
+        self.time =
+            time.map(|time| time_layer(config, state, time, screen_resolution, font_system));
+    }
+
+    /// Updates the viewport resolution of this [SoftwareRenderer].
+    /// The layout will only be updated on the next [SoftwareRenderer::update_full].
+    pub fn update_viewport(&mut self, screen_resolution: (u32, u32)) {
+        self.canvas = Canvas::new(screen_resolution);
+        [
+            Some(&mut self.machine),
+            Some(&mut self.atoms),
+            Some(&mut self.captions),
+            self.legend.as_mut(),
+            self.time.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|layer| layer.primitives.iter_mut())
+        .for_each(|primitive| {
+            if let Primitive::Text(bake_cache) = primitive {
+                bake_cache.screen_resolution = screen_resolution;
+            }
+        });
+    }
+
+    /// The resolution this [SoftwareRenderer] renders at
+    pub fn screen_resolution(&self) -> (u32, u32) {
+        self.canvas.size()
+    }
+
+    /// Draws the contents of this [SoftwareRenderer] onto a canvas cleared to `background`.
+    /// Returns the rendered frame as tightly packed RGBA-data.
+    pub fn draw(&mut self, background: Color) -> &[u8] {
+        self.canvas.clear(background);
+
+        for layer in [
+            Some(&self.machine),
+            Some(&self.atoms),
+            Some(&self.captions),
+            self.legend.as_ref(),
+            self.time.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            for primitive in &layer.primitives {
+                match primitive {
+                    Primitive::Lines(lines) => {
+                        self.canvas.draw_lines(layer.viewport_projection, lines)
+                    }
+                    Primitive::Circles(circles) => {
+                        self.canvas.draw_circles(layer.viewport_projection, circles)
+                    }
+                    Primitive::Text(bake_cache) => draw_text(
+                        &mut self.canvas,
+                        bake_cache,
+                        &mut self.font_system,
+                        &mut self.swash_cache,
+                    ),
+                }
+            }
+        }
+
+        self.canvas.data()
+    }
+}
+
+/// Creates the [Layer] for the [Machine][crate::component::machine::Machine]-component
+fn machine_layer(
+    config: &Config,
+    viewport_projection: ViewportProjection,
+    screen_resolution: (u32, u32),
+    font_system: &mut FontSystem,
+) -> Layer {
+    let mut text_buffer = Vec::new();
+    let MachineSpec {
+        lines,
+        traps,
+        labels,
+        zones,
+    } = machine::get_specs(config, viewport_projection, &mut text_buffer);
+    Layer {
+        viewport_projection,
+        primitives: vec![
+            Primitive::Lines(lines),
+            Primitive::Circles(traps),
+            Primitive::Lines(rectangles_to_lines(zones)),
+            text(labels, screen_resolution, font_system),
+        ],
+    }
+}
+
+/// Creates the [Layer] for the [Atoms][crate::component::atoms::Atoms]-component
+fn atoms_layer(
+    config: &Config,
+    state: &State,
+    viewport_projection: ViewportProjection,
+    screen_resolution: (u32, u32),
+    font_system: &mut FontSystem,
+) -> Layer {
+    let AtomSpec {
+        atom_circles,
+        shuttles,
+        labels,
+    } = atoms::get_specs(config, state, viewport_projection);
+    Layer {
+        viewport_projection,
+        primitives: vec![
+            Primitive::Lines(shuttles),
+            Primitive::Circles(atom_circles),
+            text(labels, screen_resolution, font_system),
+        ],
+    }
+}
+
+/// Creates the [Layer] for the [Captions][crate::component::captions::Captions]-component
+fn captions_layer(
+    config: &Config,
+    state: &State,
+    viewport_projection: ViewportProjection,
+    screen_resolution: (u32, u32),
+    font_system: &mut FontSystem,
+) -> Layer {
+    Layer {
+        viewport_projection,
+        primitives: vec![text(
+            captions::get_specs(config, state, viewport_projection),
+            screen_resolution,
+            font_system,
+        )],
+    }
+}
+
+/// Creates the [Layer] for the [Legend][crate::component::legend::Legend]-component
+fn legend_layer(
+    config: &Config,
+    viewport_projection: ViewportProjection,
+    screen_resolution: (u32, u32),
+    font_system: &mut FontSystem,
+) -> Layer {
+    let LegendSpec {
+        text: texts,
+        colors,
+    } = legend::get_specs(config, viewport_projection);
+    Layer {
+        viewport_projection,
+        primitives: vec![
+            Primitive::Circles(colors),
+            text(texts, screen_resolution, font_system),
+        ],
+    }
+}
+
+/// Creates the [Layer] for the [Time][crate::component::time::Time]-component
+fn time_layer(
+    config: &Config,
+    state: &State,
+    viewport_projection: ViewportProjection,
+    screen_resolution: (u32, u32),
+    font_system: &mut FontSystem,
+) -> Layer {
+    Layer {
+        viewport_projection,
+        primitives: vec![text(
+            time::get_specs(config, state, viewport_projection),
+            screen_resolution,
+            font_system,
+        )],
+    }
+}
+
+/// Shapes the texts of the passed [TextSpec] into a [Primitive::Text]
+fn text<'a>(
+    spec: TextSpec<'a, impl IntoIterator<Item = (&'a str, (f32, f32), Alignment)>>,
+    screen_resolution: (u32, u32),
+    font_system: &mut FontSystem,
+) -> Primitive {
+    Primitive::Text(BakeCache::create(spec, screen_resolution, font_system))
+}
+
+/// Draws the shaped texts of the passed [BakeCache] onto the [Canvas].
+/// Places the glyphs the same way as [glyphon] does.
+fn draw_text(
+    canvas: &mut Canvas,
+    bake_cache: &BakeCache,
+    font_system: &mut FontSystem,
+    swash_cache: &mut SwashCache,
+) {
+    let BakeCache {
+        text_buffers,
+        color,
+        viewport_projection,
+        screen_resolution,
+    } = bake_cache;
+
+    for (buffer, position, alignment) in text_buffers {
+        let area = to_text_area(
+            buffer,
+            *position,
+            *alignment,
+            *color,
+            *viewport_projection,
+            *screen_resolution,
+        );
+
+        for run in buffer.layout_runs() {
+            let line_y = (run.line_y * area.scale).round() as i32;
+            for glyph in run.glyphs {
+                let physical = glyph.physical((area.left, area.top), area.scale);
+                let glyph_color = glyph.color_opt.unwrap_or(area.default_color);
+                swash_cache.with_pixels(
+                    font_system,
+                    physical.cache_key,
+                    glyph_color,
+                    |x, y, pixel| {
+                        // The mask replaces the alpha of the glyph-color;
+                        // re-apply the alpha of the glyph-color
+                        let alpha = pixel.a() as u16 * glyph_color.a() as u16 / u8::MAX as u16;
+                        canvas.blend_pixel(
+                            physical.x + x,
+                            line_y + physical.y + y,
+                            [pixel.r(), pixel.g(), pixel.b(), alpha as u8],
+                        );
+                    },
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Rasterization of the primitives onto a [Canvas] on the CPU.
+//!
+//! The coverage-tests mirror the fragment-shaders of the [primitives][crate::component::primitive],
+//! so that the output matches the output of the GPU-renderer.
+
+use glam::{Mat4, Vec2};
+use naviz_state::Color;
+
+use crate::{
+    component::primitive::{circles::CircleSpec, lines::LineSpec},
+    viewport::ViewportProjection,
+};
+
+/// A canvas of tightly packed RGBA-pixels (row-major, starting at the top-left)
+pub struct Canvas {
+    /// The size of the canvas (`width`, `height`)
+    size: (u32, u32),
+    /// The pixel-data
+    data: Vec<u8>,
+}
+
+impl Canvas {
+    /// Creates a new (fully transparent) [Canvas] of the passed `size`
+    pub fn new(size: (u32, u32)) -> Self {
+        Self {
+            size,
+            data: vec![0; size.0 as usize * size.1 as usize * 4],
+        }
+    }
+
+    /// The size (`width`, `height`) of this [Canvas]
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// The pixel-data of this [Canvas] as tightly packed RGBA-values
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Fills the whole [Canvas] with the passed `color` (without blending)
+    pub fn clear(&mut self, color: Color) {
+        self.data
+            .chunks_exact_mut(4)
+            .for_each(|pixel| pixel.copy_from_slice(&color));
+    }
+
+    /// Blends the passed `color` over the pixel at (`x`, `y`).
+    /// Pixels outside of the [Canvas] are ignored.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x as u32 >= self.size.0 || y as u32 >= self.size.1 {
+            return;
+        }
+        let idx = (y as usize * self.size.0 as usize + x as usize) * 4;
+        blend(&mut self.data[idx..idx + 4], color);
+    }
+
+    /// Draws the passed `circles`, which are specified in the source-space of the `projection`
+    pub fn draw_circles(&mut self, projection: ViewportProjection, circles: &[CircleSpec]) {
+        let transform = PixelTransform::new(projection, self.size);
+        for CircleSpec {
+            center,
+            radius,
+            radius_inner,
+            color,
+        } in circles.iter().copied()
+        {
+            let center = Vec2::from(center);
+            let bounds = [
+                center + Vec2::new(-radius, -radius),
+                center + Vec2::new(radius, radius),
+            ];
+            self.fill(&transform, bounds, color, |pos| {
+                let dist = center.distance(pos);
+                dist <= radius && dist >= radius_inner
+            });
+        }
+    }
+
+    /// Draws the passed `lines`, which are specified in the source-space of the `projection`
+    pub fn draw_lines(&mut self, projection: ViewportProjection, lines: &[LineSpec]) {
+        let transform = PixelTransform::new(projection, self.size);
+        for LineSpec {
+            start,
+            end,
+            color,
+            width,
+            segment_length,
+            duty,
+        } in lines.iter().copied()
+        {
+            let start = Vec2::from(start);
+            let end = Vec2::from(end);
+            let direction = (end - start).normalize_or_zero();
+            if direction == Vec2::ZERO {
+                // Line has no direction => nothing to draw
+                continue;
+            }
+            let length = start.distance(end);
+            let perp = direction.perp() * width / 2.;
+            let bounds = [start - perp, start + perp, end - perp, end + perp];
+            self.fill(&transform, bounds, color, |pos| {
+                let along = direction.dot(pos - start);
+                let across = direction.perp_dot(pos - start);
+                if along < 0. || along > length || across.abs() > width / 2. {
+                    // Outside of the line
+                    return false;
+                }
+
+                // Not dashed (no segments or fully drawn segments)
+                if segment_length <= 0. || duty >= 1. {
+                    return true;
+                }
+
+                // Dashed: offset by half a drawn segment
+                let dist = along + (duty * segment_length) / 2.;
+                (dist / segment_length).rem_euclid(1.) <= duty
+            });
+        }
+    }
+
+    /// Fills all pixels inside the bounding box of `bounds` (specified in source-space)
+    /// for which `inside` returns `true` with `color`.
+    /// `inside` is called with the center of each pixel in source-space.
+    fn fill(
+        &mut self,
+        transform: &PixelTransform,
+        bounds: impl IntoIterator<Item = Vec2>,
+        color: Color,
+        inside: impl Fn(Vec2) -> bool,
+    ) {
+        let (min, max) = bounds
+            .into_iter()
+            .map(|p| transform.to_pixel(p))
+            .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), p| {
+                (min.min(p), max.max(p))
+            });
+        let x_range = (min.x.floor().max(0.) as u32)..(max.x.ceil().min(self.size.0 as f32) as u32);
+        let y_range = (min.y.floor().max(0.) as u32)..(max.y.ceil().min(self.size.1 as f32) as u32);
+
+        for y in y_range {
+            for x in x_range.clone() {
+                let pos = transform.to_source(Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+                if inside(pos) {
+                    self.blend_pixel(x as i32, y as i32, color);
+                }
+            }
+        }
+    }
+}
+
+/// Transforms coordinates between the source-space of a [ViewportProjection]
+/// and the pixel-space of a [Canvas].
+struct PixelTransform {
+    /// source-space -> pixel-space
+    to_pixel: Mat4,
+    /// pixel-space -> source-space
+    to_source: Mat4,
+}
+
+impl PixelTransform {
+    /// Creates a new [PixelTransform] for the passed `projection` onto a canvas of size `size`
+    fn new(projection: ViewportProjection, size: (u32, u32)) -> Self {
+        let to_pixel = projection.to_pixel_matrix(size);
+        Self {
+            to_pixel,
+            to_source: to_pixel.inverse(),
+        }
+    }
+
+    /// Transforms a point from source-space into pixel-space
+    fn to_pixel(&self, p: Vec2) -> Vec2 {
+        self.to_pixel.transform_point3(p.extend(0.)).truncate()
+    }
+
+    /// Transforms a point from pixel-space into source-space
+    fn to_source(&self, p: Vec2) -> Vec2 {
+        self.to_source.transform_point3(p.extend(0.)).truncate()
+    }
+}
+
+/// Blends the `src`-color over the `dst`-pixel.
+/// Uses the same blend-state as the GPU-pipelines
+/// (color: `src * src_alpha + dst * (1 - src_alpha)`, alpha: over).
+fn blend(dst: &mut [u8], [r, g, b, a]: Color) {
+    let alpha = a as f32 / u8::MAX as f32;
+    let mix = |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1. - alpha)).round() as u8;
+    dst[0] = mix(r, dst[0]);
+    dst[1] = mix(g, dst[1]);
+    dst[2] = mix(b, dst[2]);
+    dst[3] = (a as f32 + dst[3] as f32 * (1. - alpha)).round() as u8;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::viewport::{ViewportSource, ViewportTarget};
+
+    use super::*;
+
+    /// A projection which maps source-coordinates `(0, 0)..(size, size)` onto the full canvas
+    fn projection(size: f32) -> ViewportProjection {
+        ViewportProjection {
+            source: ViewportSource {
+                x: 0.,
+                y: 0.,
+                width: size,
+                height: size,
+            },
+            target: ViewportTarget::default(),
+        }
+    }
+
+    /// Gets the pixel at (`x`, `y`) of the passed [Canvas]
+    fn pixel(canvas: &Canvas, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * canvas.size().0 + x) * 4) as usize;
+        canvas.data()[idx..idx + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn circle_covers_center_not_corners() {
+        let mut canvas = Canvas::new((16, 16));
+        canvas.draw_circles(
+            projection(16.),
+            &[CircleSpec {
+                center: [8., 8.],
+                radius: 6.,
+                radius_inner: 0.,
+                color: [255, 0, 0, 255],
+            }],
+        );
+
+        assert_eq!(pixel(&canvas, 8, 8), [255, 0, 0, 255], "Center not drawn");
+        assert_eq!(pixel(&canvas, 0, 0), [0, 0, 0, 0], "Corner drawn");
+        assert_eq!(pixel(&canvas, 15, 15), [0, 0, 0, 0], "Corner drawn");
+    }
+
+    #[test]
+    fn circle_inner_radius_is_cut_out() {
+        let mut canvas = Canvas::new((16, 16));
+        canvas.draw_circles(
+            projection(16.),
+            &[CircleSpec {
+                center: [8., 8.],
+                radius: 6.,
+                radius_inner: 4.,
+                color: [0, 255, 0, 255],
+            }],
+        );
+
+        assert_eq!(pixel(&canvas, 8, 8), [0, 0, 0, 0], "Cutout drawn");
+        assert_eq!(pixel(&canvas, 13, 8), [0, 255, 0, 255], "Ring not drawn");
+    }
+
+    #[test]
+    fn dashed_line_has_gaps() {
+        let mut canvas = Canvas::new((16, 16));
+        canvas.draw_lines(
+            projection(16.),
+            &[LineSpec {
+                start: [0., 8.],
+                end: [16., 8.],
+                color: [0, 0, 255, 255],
+                width: 2.,
+                segment_length: 8.,
+                duty: 0.5,
+            }],
+        );
+
+        // Drawn segments are centered at the segment-starts (offset by half a drawn segment)
+        assert_eq!(pixel(&canvas, 0, 8), [0, 0, 255, 255], "Dash not drawn");
+        assert_eq!(pixel(&canvas, 4, 8), [0, 0, 0, 0], "Gap drawn");
+        assert_eq!(pixel(&canvas, 8, 8), [0, 0, 255, 255], "Dash not drawn");
+        assert_eq!(pixel(&canvas, 8, 2), [0, 0, 0, 0], "Outside of line drawn");
+    }
+
+    #[test]
+    fn blending_matches_pipeline() {
+        let mut canvas = Canvas::new((1, 1));
+        canvas.clear([0, 0, 0, 255]);
+        canvas.blend_pixel(0, 0, [255, 255, 255, 128]);
+
+        assert_eq!(pixel(&canvas, 0, 0), [128, 128, 128, 255]);
+    }
+}
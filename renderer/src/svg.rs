@@ -0,0 +1,342 @@
+//! Export of a single frame as an [SVG](https://www.w3.org/TR/SVG2/)-document.
+//!
+//! Uses the same specs as the components of the [Renderer][crate::renderer::Renderer]
+//! (and therefore the same colors, dashing, and layout),
+//! but emits vector-elements instead of rendering pixels.
+
+use std::fmt::{self, Display, Write};
+
+use glam::Mat4;
+use naviz_state::{config::Config, state::State, Color};
+
+use crate::{
+    component::{
+        atoms::{self, AtomSpec},
+        captions,
+        legend::{self, LegendSpec},
+        machine::{self, MachineSpec},
+        primitive::{
+            circles::CircleSpec,
+            lines::LineSpec,
+            rectangles::rectangles_to_lines,
+            text::{Alignment, HAlignment, TextSpec, VAlignment},
+        },
+        time,
+    },
+    layout::Layout,
+    viewport::ViewportProjection,
+};
+
+/// Renders the passed [State] with the passed [Config] into an SVG-document.
+///
+/// The components are placed according to the passed [Layout]
+/// (see [get_layout][crate::renderer::get_layout])
+/// on a canvas of size `screen_resolution`,
+/// which is filled with the `background`-color.
+pub fn to_svg(
+    config: &Config,
+    state: &State,
+    layout: Layout,
+    screen_resolution: (u32, u32),
+    background: Color,
+) -> String {
+    let mut svg = String::new();
+    write_svg(
+        &mut svg,
+        config,
+        state,
+        layout,
+        screen_resolution,
+        background,
+    )
+    .expect("Writing to a String cannot fail");
+    svg
+}
+
+/// Writes the SVG-document (see [to_svg]) into the passed `out`
+fn write_svg(
+    out: &mut impl Write,
+    config: &Config,
+    state: &State,
+    Layout {
+        content,
+        legend,
+        time,
+    }: Layout,
+    (width, height): (u32, u32),
+    background: Color,
+) -> fmt::Result {
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )?;
+    writeln!(
+        out,
+        r#"<rect width="{width}" height="{height}" {}/>"#,
+        Paint("fill", background)
+    )?;
+
+    // Machine
+    let mut text_buffer = Vec::new();
+    let MachineSpec {
+        lines,
+        traps,
+        labels,
+        zones,
+    } = machine::get_specs(config, content, &mut text_buffer);
+    group(out, "machine", content, (width, height), |out| {
+        write_lines(out, &lines)?;
+        write_circles(out, &traps)?;
+        write_lines(out, &rectangles_to_lines(zones))?;
+        write_text(out, labels)
+    })?;
+
+    // Atoms
+    let AtomSpec {
+        atom_circles,
+        shuttles,
+        labels,
+    } = atoms::get_specs(config, state, content);
+    group(out, "atoms", content, (width, height), |out| {
+        write_lines(out, &shuttles)?;
+        write_circles(out, &atom_circles)?;
+        write_text(out, labels)
+    })?;
+
+    // Captions
+    let text = captions::get_specs(config, state, content);
+    group(out, "captions", content, (width, height), |out| {
+        write_text(out, text)
+    })?;
+
+    // Legend
+    if let Some(legend) = legend {
+        let LegendSpec { text, colors } = legend::get_specs(config, legend);
+        group(out, "legend", legend, (width, height), |out| {
+            write_circles(out, &colors)?;
+            write_text(out, text)
+        })?;
+    }
+
+    // Time
+    if let Some(time) = time {
+        let text = time::get_specs(config, state, time);
+        group(out, "time", time, (width, height), |out| {
+            write_text(out, text)
+        })?;
+    }
+
+    writeln!(out, "</svg>")
+}
+
+/// Writes a group with the passed `id`,
+/// which transforms the contents written in `contents`
+/// from the source-space of the `viewport_projection` into pixel-space.
+fn group<W: Write>(
+    out: &mut W,
+    id: &str,
+    viewport_projection: ViewportProjection,
+    screen_resolution: (u32, u32),
+    contents: impl FnOnce(&mut W) -> fmt::Result,
+) -> fmt::Result {
+    let mat: Mat4 = viewport_projection.to_pixel_matrix(screen_resolution);
+    writeln!(
+        out,
+        r#"<g id="{id}" transform="matrix({} {} {} {} {} {})">"#,
+        mat.x_axis.x, mat.x_axis.y, mat.y_axis.x, mat.y_axis.y, mat.w_axis.x, mat.w_axis.y
+    )?;
+    contents(out)?;
+    writeln!(out, "</g>")
+}
+
+/// Writes the passed [LineSpec]s as `line`-elements.
+/// Dashing matches the line-shader:
+/// Segments are offset by half a drawn segment.
+fn write_lines(out: &mut impl Write, lines: &[LineSpec]) -> fmt::Result {
+    for LineSpec {
+        start: [x1, y1],
+        end: [x2, y2],
+        color,
+        width,
+        segment_length,
+        duty,
+    } in lines.iter().copied()
+    {
+        write!(
+            out,
+            r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" {} stroke-width="{width}""#,
+            Paint("stroke", color)
+        )?;
+        if segment_length > 0. && duty < 1. {
+            let drawn = duty * segment_length;
+            write!(
+                out,
+                r#" stroke-dasharray="{drawn} {}" stroke-dashoffset="{}""#,
+                segment_length - drawn,
+                drawn / 2.
+            )?;
+        }
+        writeln!(out, "/>")?;
+    }
+    Ok(())
+}
+
+/// Writes the passed [CircleSpec]s as `circle`-elements.
+/// Circles with an inner radius are drawn as a stroked ring.
+fn write_circles(out: &mut impl Write, circles: &[CircleSpec]) -> fmt::Result {
+    for CircleSpec {
+        center: [cx, cy],
+        radius,
+        radius_inner,
+        color,
+    } in circles.iter().copied()
+    {
+        if radius_inner > 0. {
+            writeln!(
+                out,
+                r#"<circle cx="{cx}" cy="{cy}" r="{}" fill="none" {} stroke-width="{}"/>"#,
+                (radius + radius_inner) / 2.,
+                Paint("stroke", color),
+                radius - radius_inner,
+            )?;
+        } else {
+            writeln!(
+                out,
+                r#"<circle cx="{cx}" cy="{cy}" r="{radius}" {}/>"#,
+                Paint("fill", color)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the texts of the passed [TextSpec] as `text`-elements
+fn write_text<'a>(
+    out: &mut impl Write,
+    TextSpec {
+        viewport_projection: _,
+        font_size,
+        font_family,
+        texts,
+        color,
+    }: TextSpec<'a, impl IntoIterator<Item = (&'a str, (f32, f32), Alignment)>>,
+) -> fmt::Result {
+    // Line-height as used for the text-buffers
+    let line_height = 1.2 * font_size;
+    for (text, (x, y), Alignment(h_alignment, v_alignment)) in texts {
+        let anchor = match h_alignment {
+            HAlignment::Left => "start",
+            HAlignment::Center => "middle",
+            HAlignment::Right => "end",
+        };
+        // Position is relative to the line-box; `y` is placed at its center
+        let y = match v_alignment {
+            VAlignment::Top => y + line_height / 2.,
+            VAlignment::Center => y,
+            VAlignment::Bottom => y - line_height / 2.,
+        };
+        writeln!(
+            out,
+            r#"<text x="{x}" y="{y}" font-family="{}" font-size="{font_size}" text-anchor="{anchor}" dominant-baseline="central" {}>{}</text>"#,
+            Escaped(font_family),
+            Paint("fill", color),
+            Escaped(text)
+        )?;
+    }
+    Ok(())
+}
+
+/// A paint-attribute (`fill` or `stroke`) with its opacity
+struct Paint(&'static str, Color);
+
+impl Display for Paint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(attribute, [r, g, b, a]) = *self;
+        write!(f, r#"{attribute}="rgb({r},{g},{b})""#)?;
+        if a != u8::MAX {
+            write!(f, r#" {attribute}-opacity="{}""#, a as f32 / u8::MAX as f32)?;
+        }
+        Ok(())
+    }
+}
+
+/// A string which will be XML-escaped when displayed
+struct Escaped<'a>(&'a str);
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&apos;")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::renderer::get_layout;
+
+    use super::*;
+
+    #[test]
+    fn example_contains_all_components() {
+        let config = Config::example();
+        let state = State::example();
+        let svg = to_svg(
+            &config,
+            &state,
+            get_layout(&config, (1920, 1080), false),
+            (1920, 1080),
+            [255, 255, 255, 255],
+        );
+
+        assert!(svg.starts_with("<svg"), "Not an SVG-document");
+        assert!(
+            svg.trim_end().ends_with("</svg>"),
+            "SVG-document not closed"
+        );
+        for id in ["machine", "atoms", "captions", "legend", "time"] {
+            assert!(
+                svg.contains(&format!(r#"<g id="{id}""#)),
+                "Component {id} missing"
+            );
+        }
+    }
+
+    #[test]
+    fn dashing_matches_shader() {
+        let mut svg = String::new();
+        write_lines(
+            &mut svg,
+            &[LineSpec {
+                start: [0., 0.],
+                end: [10., 0.],
+                color: [0, 0, 0, 255],
+                width: 1.,
+                segment_length: 4.,
+                duty: 0.5,
+            }],
+        )
+        .unwrap();
+
+        assert!(
+            svg.contains(r#"stroke-dasharray="2 2" stroke-dashoffset="1""#),
+            "Unexpected dashing: {svg}"
+        );
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            Escaped(r#"<a & "b">"#).to_string(),
+            "&lt;a &amp; &quot;b&quot;&gt;"
+        );
+    }
+}
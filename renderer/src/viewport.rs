@@ -1,4 +1,4 @@
-use glam::Mat4;
+use glam::{Mat4, Vec3};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
@@ -58,6 +58,17 @@ impl ViewportProjection {
             },
         }
     }
+
+    /// Creates a matrix which maps from the source-coordinates of this [ViewportProjection]
+    /// into the pixel-coordinates of a screen with the passed `screen_resolution`
+    /// (origin in the top-left, `y` pointing down).
+    pub fn to_pixel_matrix(self, (width, height): (u32, u32)) -> Mat4 {
+        let (width, height) = (width as f32, height as f32);
+        // clip-space ([-1, 1], y up) -> pixel-space ([0, size], y down)
+        let clip_to_pixel = Mat4::from_translation(Vec3::new(width / 2., height / 2., 0.))
+            * Mat4::from_scale(Vec3::new(width / 2., -height / 2., 1.));
+        clip_to_pixel * Mat4::from(self)
+    }
 }
 
 impl From<ViewportProjection> for Mat4 {
@@ -1,27 +1,68 @@
-use std::str::Utf8Error;
+use std::{
+    fmt::{self, Display},
+    str::Utf8Error,
+};
 
-use naviz_parser::{ParseError, ParseErrorInner};
+use naviz_parser::{
+    diagnostic::Diagnostic,
+    include::{IncludeError, Sources},
+    ParseError,
+};
 
 #[derive(Debug)]
 pub enum Error {
     IoError(std::io::Error),
     UTF8Error(Utf8Error),
     IdError,
-    LexError(usize, ParseErrorInner),
-    ParseError(usize, ParseErrorInner),
+    LexError(Box<Diagnostic>),
+    ParseError(Box<Diagnostic>),
+    IncludeError(Box<Diagnostic>),
     ConfigReadError(naviz_parser::config::error::Error),
     NotRemovableError,
 }
 
 impl Error {
-    /// Creates a [Error::LexError] from a [ParseError]
-    pub fn lex_error<I>(error: ParseError<I>) -> Self {
-        Self::LexError(error.offset(), error.into_inner())
+    /// Creates a [Error::LexError] or [Error::IncludeError] from an [IncludeError]
+    /// when loading the sources of a config
+    pub fn include_error(error: IncludeError) -> Self {
+        match error {
+            IncludeError::Lex(diagnostic) => Self::LexError(diagnostic),
+            error => Self::IncludeError(error.into_diagnostic()),
+        }
     }
 
-    /// Creates a [Error::ParseError] from a [ParseError]
-    pub fn parse_error<I>(error: ParseError<I>) -> Self {
-        Self::ParseError(error.offset(), error.into_inner())
+    /// Creates a [Error::ParseError] from a [ParseError] when parsing the tokens of `sources`
+    pub fn parse_error<F: naviz_parser::include::Format, T>(
+        sources: &Sources<F>,
+        error: ParseError<&[T]>,
+    ) -> Self {
+        Self::ParseError(Box::new(sources.token_error(&error)))
+    }
+
+    /// The [Diagnostic] of this [Error],
+    /// if it is a [Error::LexError], [Error::ParseError], or [Error::IncludeError]
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            Self::LexError(diagnostic)
+            | Self::ParseError(diagnostic)
+            | Self::IncludeError(diagnostic) => Some(diagnostic),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => e.fmt(f),
+            Self::UTF8Error(e) => e.fmt(f),
+            Self::IdError => f.write_str("invalid id"),
+            Self::LexError(diagnostic)
+            | Self::ParseError(diagnostic)
+            | Self::IncludeError(diagnostic) => diagnostic.fmt(f),
+            Self::ConfigReadError(e) => e.fmt(f),
+            Self::NotRemovableError => f.write_str("config can not be removed"),
+        }
     }
 }
 
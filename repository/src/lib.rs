@@ -4,18 +4,20 @@
 #[cfg(test)]
 use std::cell::RefCell;
 use std::{
-    borrow::{Borrow, Cow},
+    borrow::Cow,
     collections::HashMap,
     fs,
-    hash::Hash,
     path::{Path, PathBuf},
 };
 
 #[cfg(not(test))]
 use directories::ProjectDirs;
 use error::{Error, Result};
-use include_dir::{include_dir, Dir};
-use naviz_parser::config::{generic::Config, machine::MachineConfig, visual::VisualConfig};
+use include_dir::{include_dir, Dir, File};
+use naviz_parser::{
+    config::{generic::Config, machine::MachineConfig, visual::VisualConfig},
+    include::{self, ConfigFormat, Include, Resolver, SourceFile, Sources},
+};
 #[cfg(test)]
 use tempfile::TempDir;
 
@@ -67,21 +69,17 @@ impl Repository {
     }
 
     /// Loads the passed bundled config into the passed [Repository]
-    fn load_bundled(mut self, bundled: &Dir<'static>) -> Result<Self> {
-        self.0 = insert_results(
-            self.0,
-            bundled.files().map(|f| {
-                RepositoryEntry::new_with_id(
-                    f.path()
-                        .file_stem()
-                        .ok_or(Error::IdError)?
-                        .to_string_lossy()
-                        .into_owned(),
-                    RepositorySource::Bundled(f.contents()),
-                )
-            }),
-        )?;
-        Ok(self)
+    fn load_bundled(self, bundled: &'static Dir<'static>) -> Result<Self> {
+        self.insert_sources(bundled.files().map(|f| {
+            Ok((
+                f.path()
+                    .file_stem()
+                    .ok_or(Error::IdError)?
+                    .to_string_lossy()
+                    .into_owned(),
+                RepositorySource::Bundled(f),
+            ))
+        }))
     }
 
     /// Loads the bundled machines into the passed [Repository]
@@ -121,33 +119,63 @@ impl Repository {
 
     /// Loads the configs from the passed `subdir` of the user-directory
     /// into the passed [Repository]
-    fn load_user_dir(mut self, subdir: &str) -> Result<Self> {
-        self.0 = insert_results(
-            self.0,
-            Self::user_dir(subdir)?
-                .read_dir()
-                .map_err(Error::IoError)?
-                .filter_map(|x| {
-                    if let Ok(x) = x {
-                        if x.path().is_file() {
-                            Some(x.path())
-                        } else {
-                            None
-                        }
+    fn load_user_dir(self, subdir: &str) -> Result<Self> {
+        let sources: Vec<_> = Self::user_dir(subdir)?
+            .read_dir()
+            .map_err(Error::IoError)?
+            .filter_map(|x| {
+                if let Ok(x) = x {
+                    if x.path().is_file() {
+                        Some(x.path())
                     } else {
                         None
                     }
-                })
-                .map(|p| {
-                    RepositoryEntry::new_with_id(
-                        p.file_stem()
-                            .ok_or(Error::IdError)?
-                            .to_string_lossy()
-                            .into_owned(),
-                        RepositorySource::UserDir(p),
-                    )
-                }),
-        )?;
+                } else {
+                    None
+                }
+            })
+            .map(|p| {
+                Ok((
+                    p.file_stem()
+                        .ok_or(Error::IdError)?
+                        .to_string_lossy()
+                        .into_owned(),
+                    RepositorySource::UserDir(p),
+                ))
+            })
+            .collect();
+        self.insert_sources(sources)
+    }
+
+    /// Inserts the `sources` with their ids into the passed [Repository].
+    ///
+    /// Returns [Ok] with the updated [Repository] if all `sources` were [Ok]
+    /// and their names could be extracted,
+    /// or the first [Err].
+    fn insert_sources(
+        mut self,
+        sources: impl IntoIterator<Item = Result<(String, RepositorySource)>>,
+    ) -> Result<Self> {
+        let mut ids = Vec::new();
+        for source in sources {
+            let (id, source) = source?;
+            self.0.insert(
+                id.clone(),
+                RepositoryEntry {
+                    name: String::new(),
+                    source,
+                },
+            );
+            ids.push(id);
+        }
+        // Extract the names only once all sources are inserted,
+        // as configs may include other configs by id
+        for id in ids {
+            let name = self.0[&id].source.name(&self)?;
+            if let Some(entry) = self.0.get_mut(&id) {
+                entry.name = name;
+            }
+        }
         Ok(self)
     }
 
@@ -163,6 +191,8 @@ impl Repository {
 
     /// Imports a `file` into the passed `subdir` in the user-directory.
     /// Will validate that the config can be parsed into a valid `C`.
+    /// The config is stored with its includes resolved,
+    /// as includes relative to the `file` would not be found from the user-directory.
     fn import_to_user_dir<C>(&mut self, subdir: &str, file: &Path) -> Result<()>
     where
         Config: TryInto<C, Error = naviz_parser::config::error::Error>,
@@ -179,23 +209,20 @@ impl Repository {
             .ok_or(Error::IdError)?
             .to_string_lossy()
             .into_owned();
-        // Create temporary entry with the source path to check if the config is valid
-        let entry = RepositoryEntry::new(RepositorySource::UserDir(file.to_owned()))?;
+        // Load the config from the source path to check if the config is valid
+        let sources = self.sources(RepositorySource::UserDir(file.to_owned()).source_file()?)?;
         // Ensure the config is valid (i.e., can be parsed correctly)
-        entry
-            .contents_as_config()?
+        config_from_sources(&sources)?
             .try_into()
             .map_err(Error::ConfigReadError)?;
 
-        // Import: Copy to target path
+        // Import: Write the config with resolved includes to the target path
         let target_path = Self::user_dir(subdir)?.join(file.file_name().unwrap());
-        fs::copy(file, &target_path).map_err(Error::IoError)?;
+        fs::write(&target_path, sources.flatten()).map_err(Error::IoError)?;
 
-        self.0.insert(
-            id,
-            // New repository entry with correct target path
-            RepositoryEntry::new(RepositorySource::UserDir(target_path))?,
-        );
+        let entry = RepositoryEntry::new(RepositorySource::UserDir(target_path), self)?;
+        // New repository entry with correct target path
+        self.0.insert(id, entry);
 
         Ok(())
     }
@@ -261,12 +288,41 @@ impl Repository {
         Config: TryInto<C, Error = naviz_parser::config::error::Error>,
     {
         self.0.get(id).map(|e| {
-            e.contents_as_config()?
+            e.contents_as_config(self)?
                 .try_into()
                 .map_err(Error::ConfigReadError)
         })
     }
 
+    /// Tries to get the entry with the passed `id` as a [SourceFile]
+    /// (e.g., to load it with its includes using [Repository::resolver]).
+    ///
+    /// Returns:
+    /// - `None`: No entry with the passed `id` exists
+    /// - `Some(Err)`: An entry exists, but failed to load the data
+    /// - `Some(Ok)`: The source of the found entry
+    pub fn get_source(&self, id: &str) -> Option<Result<SourceFile>> {
+        self.0.get(id).map(|e| e.source.source_file())
+    }
+
+    /// A [Resolver] for includes in configs:
+    /// Paths are read relative to the including file,
+    /// ids are resolved to the entries of this repository.
+    pub fn resolver(&self) -> impl Resolver + '_ {
+        |from: &str, include: &Include| match include {
+            Include::Path(_) => include::file_system(from, include),
+            Include::Id(id) => self
+                .get_source(id)
+                .ok_or_else(|| format!("no config with id `{id}` exists"))?
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Loads the `file` and its includes using the [resolver][Repository::resolver] of this repository
+    pub fn sources(&self, file: SourceFile) -> Result<Sources<ConfigFormat>> {
+        Sources::load(file, &mut self.resolver()).map_err(Error::include_error)
+    }
+
     /// Try to get any config from this repository
     pub fn try_get_any<C>(&self) -> Option<(&str, C)>
     where
@@ -277,7 +333,7 @@ impl Repository {
             .filter_map(|(id, entry)| {
                 Some((
                     id.as_str(),
-                    entry.contents_as_config().ok()?.try_into().ok()?,
+                    entry.contents_as_config(self).ok()?.try_into().ok()?,
                 ))
             })
             .next()
@@ -296,17 +352,11 @@ struct RepositoryEntry {
 
 impl RepositoryEntry {
     /// Creates a new [RepositoryEntry] from the passed `source`.
-    /// Will also return the `id` for usage as a [HashMap]-entry.
-    /// Will extract the name from the `source`.
-    fn new_with_id(id: String, source: RepositorySource) -> Result<(String, Self)> {
-        Ok((id, Self::new(source)?))
-    }
-
-    /// Creates a new [RepositoryEntry] from the passed `source`.
-    /// Will extract the name from the `source`.
-    fn new(source: RepositorySource) -> Result<Self> {
+    /// Will extract the name from the `source`,
+    /// resolving includes using the passed `repository`.
+    fn new(source: RepositorySource, repository: &Repository) -> Result<Self> {
         Ok(Self {
-            name: source.name()?,
+            name: source.name(repository)?,
             source,
         })
     }
@@ -321,38 +371,57 @@ impl RepositoryEntry {
         self.source.contents()
     }
 
-    /// The name of this [RepositoryEntry] as a [Config]
-    pub fn contents_as_config(&self) -> Result<Config> {
-        self.source.contents_as_config()
+    /// The contents of this [RepositoryEntry] as a [Config].
+    /// Includes are resolved using the passed `repository`.
+    pub fn contents_as_config(&self, repository: &Repository) -> Result<Config> {
+        self.source.contents_as_config(repository)
     }
 }
 
 /// A data-source for the repository.
 enum RepositorySource {
     /// Bundled in the executable
-    Bundled(&'static [u8]),
+    Bundled(&'static File<'static>),
     /// Stored in the user-directory
     UserDir(PathBuf),
 }
 
 impl RepositorySource {
-    /// Extract the config-name from this [RepositorySource]
-    pub fn name(&self) -> Result<String> {
-        naviz_parser::config::generic::get_item(&mut self.contents_as_config()?, "name")
+    /// Extract the config-name from this [RepositorySource].
+    /// Includes are resolved using the passed `repository`.
+    pub fn name(&self, repository: &Repository) -> Result<String> {
+        naviz_parser::config::generic::get_item(&mut self.contents_as_config(repository)?, "name")
             .map_err(Error::ConfigReadError)
     }
 
     /// Read the contents of this [RepositorySource]
     pub fn contents(&self) -> Result<Cow<[u8]>> {
         Ok(match self {
-            Self::Bundled(c) => Cow::Borrowed(c),
+            Self::Bundled(f) => Cow::Borrowed(f.contents()),
             Self::UserDir(p) => fs::read(p).map(Cow::Owned).map_err(Error::IoError)?,
         })
     }
 
-    /// Get the contents of this [RepositorySource] as a [Config]
-    pub fn contents_as_config(&self) -> Result<Config> {
-        config_from_bytes(self.contents()?.borrow())
+    /// Get the contents of this [RepositorySource] as a [SourceFile].
+    /// User-dir-files are identified by their path,
+    /// so that includes relative to them can be resolved.
+    pub fn source_file(&self) -> Result<SourceFile> {
+        let source = String::from_utf8(self.contents()?.into_owned())
+            .map_err(|e| Error::UTF8Error(e.utf8_error()))?;
+        Ok(match self {
+            Self::Bundled(f) => SourceFile {
+                id: format!("bundled/{}", f.path().display()),
+                name: f.path().display().to_string(),
+                source,
+            },
+            Self::UserDir(p) => SourceFile::new(p.display().to_string(), source),
+        })
+    }
+
+    /// Get the contents of this [RepositorySource] as a [Config].
+    /// Includes are resolved using the passed `repository`.
+    pub fn contents_as_config(&self, repository: &Repository) -> Result<Config> {
+        config_from_sources(&repository.sources(self.source_file()?)?)
     }
 
     /// Check whether a [RepositoryEntry] from this [RepositorySource] can be removed.
@@ -364,32 +433,30 @@ impl RepositorySource {
     }
 }
 
-/// Try to parse a [Config] from the passed `bytes`
+/// Try to parse a [Config] from the passed `bytes`.
+/// Includes are not allowed; use [config_from_sources] to parse configs with includes.
 pub fn config_from_bytes(bytes: &[u8]) -> Result<Config> {
-    let config =
-        naviz_parser::config::lexer::lex(std::str::from_utf8(bytes).map_err(Error::UTF8Error)?)
-            .map_err(Error::lex_error)?;
-    let config = naviz_parser::config::parser::parse(&config).map_err(Error::parse_error)?;
-    Ok(config.into())
+    let source = std::str::from_utf8(bytes).map_err(Error::UTF8Error)?;
+    let sources = Sources::load(
+        SourceFile::new("<config>", source),
+        &mut include::no_includes,
+    )
+    .map_err(Error::include_error)?;
+    config_from_sources(&sources)
 }
 
-/// Insert an [Iterator] of [Result]s into the `target` [HashMap].
-///
-/// Returns [Ok] with the updated [HashMap] if all [Result]s were [Ok]
-/// or the first [Err].
-fn insert_results<K: Eq + Hash, V>(
-    mut target: HashMap<K, V>,
-    source: impl IntoIterator<Item = Result<(K, V)>>,
-) -> Result<HashMap<K, V>> {
-    for result in source.into_iter() {
-        let (key, value) = result?;
-        target.insert(key, value);
-    }
-    Ok(target)
+/// Try to parse a [Config] from the passed (already loaded) [Sources]
+pub fn config_from_sources(sources: &Sources<ConfigFormat>) -> Result<Config> {
+    let tokens = sources.tokens();
+    let config =
+        naviz_parser::config::parser::parse(&tokens).map_err(|e| Error::parse_error(sources, e))?;
+    Ok(config.into())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Borrow;
+
     use naviz_parser::config::{machine::MachineConfig, visual::VisualConfig};
 
     use super::*;
@@ -518,6 +585,50 @@ mod tests {
         test_import_configs(STYLES_SUBDIR, Repository::import_style_to_user_dir);
     }
 
+    /// Checks whether configs in the user-dir can include files relative to them
+    /// and other configs by id.
+    #[test]
+    fn includes() {
+        reset_temp_dir();
+
+        let directory =
+            Repository::user_dir(MACHINES_SUBDIR).expect("Failed to get machine subdirectory");
+        let example = BUNDLED_MACHINES
+            .get_file("example.namachine")
+            .and_then(File::contents_utf8)
+            .expect("Failed to get example machine");
+        // The example machine without its name
+        let (_, body) = example.split_once('\n').expect("Example machine is empty");
+        fs::create_dir(directory.join("parts")).expect("Failed to create directory");
+        fs::write(directory.join("parts/body.namachine"), body).expect("Failed to write");
+        fs::write(
+            directory.join("base.namachine"),
+            "name: \"Base\"\n#include \"parts/body.namachine\"",
+        )
+        .expect("Failed to write");
+        fs::write(directory.join("derived.namachine"), "#include base").expect("Failed to write");
+
+        let repo = Repository::empty()
+            .user_dir_machines()
+            .expect("Failed to load machines with includes");
+        let mut list: Vec<_> = repo.list().map(|(id, name, _)| (id, name)).collect();
+        list.sort();
+        assert_eq!(list, [("base", "Base"), ("derived", "Base")]);
+        repo.get::<MachineConfig>("derived")
+            .expect("Machine exists in `list`, but `get` returned `None`")
+            .expect("Machine with includes is invalid");
+
+        fs::write(
+            directory.join("cyclic.namachine"),
+            "name: \"Cyclic\"\n#include cyclic",
+        )
+        .expect("Failed to write");
+        assert!(matches!(
+            Repository::empty().user_dir_machines(),
+            Err(Error::IncludeError(_))
+        ));
+    }
+
     /// Should not be able to remove bundled configs.
     #[test]
     fn cannot_remove_bundled_configs() {
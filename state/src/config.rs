@@ -11,6 +11,8 @@ pub struct Config {
     pub legend: LegendConfig,
     /// The config for the time
     pub time: TimeConfig,
+    /// The config for the captions
+    pub caption: CaptionConfig,
     /// The extent of the content (in content-coordinates), denoted by top-left and bottom-right
     pub content_extent: Extent,
 }
@@ -128,6 +130,12 @@ pub struct TimeConfig {
     pub display: bool,
 }
 
+#[derive(Clone, Debug)]
+pub struct CaptionConfig {
+    /// The config for the font of the captions
+    pub font: FontConfig,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct LineConfig {
     /// The width of this line
@@ -355,6 +363,13 @@ impl Config {
                 },
                 display: true,
             },
+            caption: CaptionConfig {
+                font: FontConfig {
+                    size: 8.,
+                    color: [0, 0, 0, 255],
+                    family: "Fira Mono".to_owned(),
+                },
+            },
             content_extent: ((0., 0.), (100., 120.)),
         }
     }
@@ -7,6 +7,8 @@ pub struct State {
     pub atoms: Vec<AtomState>,
     // The time (full string to draw; i.e., with time prefix)
     pub time: String,
+    /// The captions which are currently shown
+    pub captions: Vec<CaptionState>,
 }
 
 #[derive(Clone, Debug)]
@@ -21,6 +23,18 @@ pub struct AtomState {
     pub shuttle: bool,
     /// The label to draw on this atom
     pub label: String,
+    /// The opacity of this atom (`0` for atoms which do not exist)
+    pub opacity: f32,
+    /// The outline drawn around this atom (width and color), if any
+    pub outline: Option<(f32, Color)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptionState {
+    /// The text of this caption
+    pub text: String,
+    /// The position of the center of this caption
+    pub position: Position,
 }
 
 impl State {
@@ -36,9 +50,15 @@ impl State {
                     color: [255, 128, 32, 255],
                     shuttle: s,
                     label: format!("{idx}"),
+                    opacity: 1.,
+                    outline: (idx == 3).then_some((0.5, [255, 0, 0, 255])),
                 })
                 .collect(),
             time: "Time: 42 us".to_owned(),
+            captions: vec![CaptionState {
+                text: "Example caption".to_owned(),
+                position: (50., 6.),
+            }],
         }
     }
 }
@@ -0,0 +1,363 @@
+//! Encoding of rendered frames into animated images (GIF or APNG),
+//! which does not require `ffmpeg`.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use color_quant::NeuQuant;
+
+/// The format of an animated image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnimationFormat {
+    /// An animated GIF.
+    /// Frames are quantized to a palette (see [AnimationOptions::palette]).
+    Gif,
+    /// An animated PNG.
+    /// Frames are stored in true color.
+    Apng,
+}
+
+impl AnimationFormat {
+    /// Gets the [AnimationFormat] from a file-extension
+    /// (`gif` for [AnimationFormat::Gif], `png` or `apng` for [AnimationFormat::Apng]),
+    /// or [None] if the extension is unknown.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Options for exporting an animated image
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct AnimationOptions {
+    /// The palette to quantize frames to.
+    /// Only used for [AnimationFormat::Gif].
+    pub palette: PaletteOptions,
+    /// Only every `frame_step`-th frame is encoded
+    /// (i.e., the framerate of the animation is divided by `frame_step`).
+    /// `1` encodes every frame.
+    pub frame_step: u32,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            palette: Default::default(),
+            frame_step: 1,
+        }
+    }
+}
+
+/// Options for quantizing frames to a palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PaletteOptions {
+    /// The maximum number of colors in the palette of each frame (`2..=256`).
+    /// If a frame is transparent, one of the colors is used for transparency.
+    pub colors: u16,
+    /// The speed of the quantization (`1..=30`).
+    /// Higher values are faster, but produce worse palettes.
+    pub speed: i32,
+}
+
+impl Default for PaletteOptions {
+    fn default() -> Self {
+        Self {
+            colors: 256,
+            speed: 10,
+        }
+    }
+}
+
+/// An encoder which writes frames into an animated image
+pub(crate) enum AnimationEncoder {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        size: (u16, u16),
+        palette: PaletteOptions,
+        /// The duration of a frame in seconds
+        frame_duration: f32,
+        /// The number of frames written so far
+        frames_written: u32,
+    },
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+impl AnimationEncoder {
+    /// Creates a new [AnimationEncoder] writing to the `target`-path.
+    /// The animation will contain `frame_count` frames of the passed `size`,
+    /// which are shown at `fps` frames per second.
+    pub fn new(
+        target: &Path,
+        format: AnimationFormat,
+        palette: PaletteOptions,
+        (width, height): (u32, u32),
+        frame_count: u32,
+        fps: f32,
+    ) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(target)?);
+        match format {
+            AnimationFormat::Gif => {
+                let size = (
+                    to_gif_dimension(width, "width")?,
+                    to_gif_dimension(height, "height")?,
+                );
+                let mut encoder =
+                    gif::Encoder::new(writer, size.0, size.1, &[]).map_err(io::Error::other)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(io::Error::other)?;
+                Ok(Self::Gif {
+                    encoder,
+                    size,
+                    palette,
+                    frame_duration: 1. / fps,
+                    frames_written: 0,
+                })
+            }
+            AnimationFormat::Apng => {
+                let mut encoder = png::Encoder::new(writer, width, height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(frame_count, 0)?;
+                let (numerator, denominator) = frame_delay(fps);
+                encoder.set_frame_delay(numerator, denominator)?;
+                // Frames always cover the full image
+                encoder.set_blend_op(png::BlendOp::Source)?;
+                encoder.set_dispose_op(png::DisposeOp::None)?;
+                Ok(Self::Apng(encoder.write_header()?))
+            }
+        }
+    }
+
+    /// Writes the next frame from the passed tightly packed RGBA-`data`
+    pub fn write_frame(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Gif {
+                encoder,
+                size,
+                palette,
+                frame_duration,
+                frames_written,
+            } => {
+                // Distribute rounding-errors of the delay (in centiseconds) over the frames
+                let centiseconds = |frame: u32| (frame as f32 * *frame_duration * 100.).round();
+                let delay = centiseconds(*frames_written + 1) - centiseconds(*frames_written);
+
+                let mut frame = quantize(*size, data, *palette);
+                frame.delay = delay as u16;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+                *frames_written += 1;
+                Ok(())
+            }
+            Self::Apng(writer) => Ok(writer.write_image_data(data)?),
+        }
+    }
+
+    /// Finishes the animated image
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Gif { encoder, .. } => encoder.into_inner().map(drop),
+            Self::Apng(writer) => Ok(writer.finish()?),
+        }
+    }
+}
+
+/// Converts a dimension to the size supported by GIFs
+fn to_gif_dimension(value: u32, name: &str) -> io::Result<u16> {
+    value.try_into().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("GIF {name} must not exceed {}", u16::MAX),
+        )
+    })
+}
+
+/// Gets the APNG frame-delay (`numerator`, `denominator`) in seconds for the passed `fps`
+fn frame_delay(fps: f32) -> (u16, u16) {
+    if fps.fract() == 0. && fps <= u16::MAX as f32 {
+        (1, fps as u16)
+    } else {
+        // Approximate in milliseconds
+        (
+            (1000. / fps).round().clamp(1., u16::MAX as f32) as u16,
+            1000,
+        )
+    }
+}
+
+/// Quantizes the tightly packed RGBA-`data` of the passed `size` into a [gif::Frame].
+///
+/// GIFs only support fully transparent or fully opaque pixels:
+/// Pixels with an alpha of `0` are transparent, all others are opaque.
+/// If the frame contains no more than the allowed number of colors, the palette is exact,
+/// otherwise the palette is created using [NeuQuant].
+fn quantize(
+    (width, height): (u16, u16),
+    data: &[u8],
+    PaletteOptions { colors, speed }: PaletteOptions,
+) -> gif::Frame<'static> {
+    let colors = colors.clamp(2, 256) as usize;
+    let speed = speed.clamp(1, 30);
+
+    let opaque: Vec<u8> = data
+        .chunks_exact(4)
+        .filter(|pixel| pixel[3] != 0)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+        .collect();
+    let has_transparency = opaque.len() < data.len();
+    // Reserve one color for transparency
+    let colors = if has_transparency { colors - 1 } else { colors };
+
+    let palette = match exact_palette(&opaque, colors) {
+        Some(lookup) => Palette::Exact(lookup),
+        None => Palette::Quantized(NeuQuant::new(speed, colors, &opaque)),
+    };
+    let mut colors = palette.colors();
+
+    // Transparent color is appended to the palette
+    let transparent_index = (colors.len() / 3) as u8;
+    let buffer = data
+        .chunks_exact(4)
+        .map(|pixel| {
+            if pixel[3] == 0 {
+                transparent_index
+            } else {
+                palette.index_of(pixel)
+            }
+        })
+        .collect();
+    let transparent = has_transparency.then(|| {
+        colors.extend([0, 0, 0]);
+        transparent_index
+    });
+
+    gif::Frame {
+        width,
+        height,
+        buffer: Cow::Owned(buffer),
+        palette: Some(colors),
+        transparent,
+        // Transparent pixels should not show the previous frame
+        dispose: gif::DisposalMethod::Background,
+        ..Default::default()
+    }
+}
+
+/// A palette which maps opaque RGBA-pixels to indices
+enum Palette {
+    /// An exact palette (lookup from color to index)
+    Exact(HashMap<[u8; 3], u8>),
+    /// A quantized palette
+    Quantized(NeuQuant),
+}
+
+impl Palette {
+    /// Gets the index of the passed (opaque) RGBA-`pixel` in this [Palette]
+    fn index_of(&self, pixel: &[u8]) -> u8 {
+        match self {
+            Self::Exact(lookup) => lookup[&[pixel[0], pixel[1], pixel[2]]],
+            Self::Quantized(quant) => {
+                quant.index_of(&[pixel[0], pixel[1], pixel[2], u8::MAX]) as u8
+            }
+        }
+    }
+
+    /// Gets the colors of this [Palette] as packed RGB-values, ordered by their index
+    fn colors(&self) -> Vec<u8> {
+        match self {
+            Self::Exact(lookup) => {
+                let mut colors = vec![0; lookup.len() * 3];
+                for (color, index) in lookup {
+                    colors[*index as usize * 3..][..3].copy_from_slice(color);
+                }
+                colors
+            }
+            Self::Quantized(quant) => quant.color_map_rgb(),
+        }
+    }
+}
+
+/// Creates an exact palette (as a lookup from color to index) for the passed RGBA-`pixels`,
+/// or returns [None] if the pixels contain more than `max_colors` colors.
+fn exact_palette(pixels: &[u8], max_colors: usize) -> Option<HashMap<[u8; 3], u8>> {
+    let mut lookup = HashMap::new();
+    for pixel in pixels.chunks_exact(4) {
+        let next_index = lookup.len();
+        lookup
+            .entry([pixel[0], pixel[1], pixel[2]])
+            .or_insert(next_index as u8);
+        if lookup.len() > max_colors {
+            return None;
+        }
+    }
+    Some(lookup)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quantize_exact_palette() {
+        let data = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [255, 0, 0, 255],
+            [0, 0, 0, 0],
+        ]
+        .concat();
+        let frame = quantize((2, 2), &data, Default::default());
+
+        let palette = frame.palette.unwrap();
+        let transparent = frame.transparent.expect("Transparency not detected");
+        assert_eq!(palette.len(), 3 * 3, "Palette not exact");
+        assert_eq!(
+            frame.buffer[0], frame.buffer[2],
+            "Same color not same index"
+        );
+        assert_ne!(
+            frame.buffer[0], frame.buffer[1],
+            "Different colors same index"
+        );
+        assert_eq!(
+            frame.buffer[3], transparent,
+            "Transparent pixel not transparent"
+        );
+        assert_eq!(&palette[frame.buffer[1] as usize * 3..][..3], &[0, 255, 0]);
+    }
+
+    #[test]
+    fn quantize_reduces_colors() {
+        let data: Vec<u8> = (0..=255).flat_map(|v| [v, v, v, 255]).collect();
+        let frame = quantize(
+            (16, 16),
+            &data,
+            PaletteOptions {
+                colors: 64,
+                speed: 10,
+            },
+        );
+
+        assert!(frame.transparent.is_none(), "Opaque frame has transparency");
+        assert!(frame.palette.unwrap().len() <= 64 * 3, "Too many colors");
+    }
+
+    #[test]
+    fn apng_frame_delay() {
+        assert_eq!(frame_delay(30.), (1, 30));
+        assert_eq!(frame_delay(0.5), (2000, 1000));
+    }
+}
@@ -0,0 +1,316 @@
+//! The backends a [VideoExport][crate::VideoExport] can render its frames with.
+
+use std::{ops::Deref, sync::mpsc::channel};
+
+use naviz_animator::animator::Animator;
+use naviz_renderer::{renderer::Renderer, software::SoftwareRenderer};
+use naviz_state::{config::Config, state::State};
+use wgpu::{
+    Buffer, BufferView, Color, CommandEncoderDescriptor, Device, DeviceDescriptor, Extent3d,
+    Features, Instance, InstanceDescriptor, Limits, LoadOp, MapMode, MemoryHints, Operations,
+    Queue, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TexelCopyBufferInfo,
+    TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages,
+};
+
+use crate::{
+    error::{Result, VideoExportError},
+    image,
+};
+
+/// The backend to render the frames of a video with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenderBackend {
+    /// Render on the GPU if an adapter is available,
+    /// otherwise fall back to [RenderBackend::Software]
+    #[default]
+    Auto,
+    /// Always render on the GPU using [wgpu].
+    /// Requires an adapter.
+    Gpu,
+    /// Always render on the CPU using the [SoftwareRenderer]
+    Software,
+}
+
+/// A created backend, which can render frames
+pub(crate) enum Backend {
+    /// Render using the [GpuBackend]
+    Gpu(Box<GpuBackend>),
+    /// Render using the [SoftwareRenderer]
+    Software(Box<SoftwareRenderer>),
+}
+
+/// A rendered frame as tightly packed RGBA-data.
+/// Can be dereferenced to the raw data.
+pub(crate) enum Frame<'a> {
+    /// A frame mapped from the output-buffer of the [GpuBackend].
+    /// The output-buffer will be unmapped when this [Frame] is dropped.
+    Gpu(Option<BufferView<'a>>, &'a Buffer),
+    /// A frame rendered by the [SoftwareRenderer]
+    Software(&'a [u8]),
+    /// A frame which was copied out of the backend (e.g., to convert it)
+    Owned(Vec<u8>),
+}
+
+impl Deref for Frame<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Gpu(view, _) => view.as_deref().unwrap_or_default(),
+            Self::Software(data) => data,
+            Self::Owned(data) => data,
+        }
+    }
+}
+
+impl Drop for Frame<'_> {
+    fn drop(&mut self) {
+        if let Self::Gpu(view, buffer) = self {
+            // View needs to be dropped before unmapping
+            drop(view.take());
+            buffer.unmap();
+        }
+    }
+}
+
+impl Backend {
+    /// Creates the requested [RenderBackend] for the passed [Animator]
+    /// and with the passed `screen_resolution`.
+    pub async fn new(
+        backend: RenderBackend,
+        animator: &Animator,
+        screen_resolution: (u32, u32),
+    ) -> Result<Self> {
+        let device = match backend {
+            RenderBackend::Software => None,
+            RenderBackend::Auto | RenderBackend::Gpu => create_device().await,
+        };
+
+        match (device, backend) {
+            (Some((device, queue)), _) => Ok(Self::Gpu(Box::new(GpuBackend::new(
+                device,
+                queue,
+                &animator.config(),
+                &animator.state((0.).into()),
+                screen_resolution,
+            )))),
+            (None, RenderBackend::Gpu) => Err(VideoExportError::NoAdapter),
+            (None, _) => Ok(Self::Software(Box::new(SoftwareRenderer::new(
+                &animator.config(),
+                &animator.state((0.).into()),
+                screen_resolution,
+            )))),
+        }
+    }
+
+    /// The [RenderBackend] this [Backend] renders with.
+    /// Will never be [RenderBackend::Auto].
+    pub fn kind(&self) -> RenderBackend {
+        match self {
+            Self::Gpu(_) => RenderBackend::Gpu,
+            Self::Software(_) => RenderBackend::Software,
+        }
+    }
+
+    /// Updates the backend to render the passed [State]
+    pub fn update(&mut self, config: &Config, state: &State) {
+        match self {
+            Self::Gpu(gpu) => gpu.update(config, state),
+            Self::Software(software) => software.update(config, state),
+        }
+    }
+
+    /// Renders the current frame on a canvas cleared to `background` and gets the resulting [Frame].
+    /// If no `background` is passed, the frame is rendered with a transparent background
+    /// and has straight alpha.
+    pub fn get_frame(&mut self, background: Option<[u8; 4]>) -> Result<Frame<'_>> {
+        let clear = background.unwrap_or([0, 0, 0, 0]);
+        let frame = match self {
+            Self::Gpu(gpu) => Frame::Gpu(Some(gpu.get_frame(clear)?), &gpu.output_buffer),
+            Self::Software(software) => Frame::Software(software.draw(clear)),
+        };
+        if background.is_some() {
+            return Ok(frame);
+        }
+        // Blending onto a transparent black canvas results in premultiplied colors
+        let data = image::unpremultiply(&frame);
+        drop(frame);
+        Ok(Frame::Owned(data))
+    }
+}
+
+/// Creates a headless rendering [Device] and [Queue].
+/// Returns [None] if no adapter or device is available.
+async fn create_device() -> Option<(Device, Queue)> {
+    let instance = Instance::new(&InstanceDescriptor::default());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        })
+        .await?;
+    adapter
+        .request_device(
+            &DeviceDescriptor {
+                label: Some("naviz video renderer"),
+                required_features: Features::default(),
+                required_limits: Limits::default(),
+                memory_hints: MemoryHints::default(),
+            },
+            None,
+        )
+        .await
+        .ok()
+}
+
+/// Creates an output-buffer on the passed [Device] for the specified `screen_resolution` and `pixel_size`.
+fn create_output_buffer(device: &Device, screen_resolution: (u32, u32), pixel_size: u32) -> Buffer {
+    let output_buffer_size =
+        (pixel_size * screen_resolution.0 * screen_resolution.1) as wgpu::BufferAddress;
+    let output_buffer_desc = wgpu::BufferDescriptor {
+        size: output_buffer_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        label: Some("naviz output buffer"),
+        mapped_at_creation: false,
+    };
+    device.create_buffer(&output_buffer_desc)
+}
+
+/// Renders frames on the GPU using the [Renderer]
+pub(crate) struct GpuBackend {
+    renderer: Renderer,
+    texture: Texture,
+    device: Device,
+    queue: Queue,
+    output_buffer: Buffer,
+    screen_resolution: (u32, u32),
+}
+
+impl GpuBackend {
+    /// Creates a new [GpuBackend] on the passed [Device]
+    fn new(
+        device: Device,
+        queue: Queue,
+        config: &Config,
+        state: &State,
+        screen_resolution: (u32, u32),
+    ) -> Self {
+        let texture_format = TextureFormat::Rgba8Unorm;
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("naviz render target"),
+            size: Extent3d {
+                width: screen_resolution.0,
+                height: screen_resolution.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: texture_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let renderer = Renderer::new(
+            &device,
+            &queue,
+            texture_format,
+            config,
+            state,
+            screen_resolution,
+        );
+
+        let output_buffer = create_output_buffer(
+            &device,
+            screen_resolution,
+            texture_format.components() as u32,
+        );
+
+        Self {
+            renderer,
+            texture,
+            device,
+            queue,
+            output_buffer,
+            screen_resolution,
+        }
+    }
+
+    /// Updates the [Renderer] to have the passed [State]
+    fn update(&mut self, config: &Config, state: &State) {
+        self.renderer.update(
+            &mut (&self.device, &self.queue),
+            &self.device,
+            &self.queue,
+            config,
+            state,
+        );
+    }
+
+    /// Renders the current frame and gets the resulting data as a [BufferView].
+    /// [Self::output_buffer] will need to be [unmapped][Buffer::unmap] after the [BufferView] was used.
+    fn get_frame(&self, background: [u8; 4]) -> Result<BufferView<'_>> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        {
+            let [r, g, b, a] = background;
+            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.texture.create_view(&Default::default()),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color {
+                            r: r as f64 / u8::MAX as f64,
+                            g: g as f64 / u8::MAX as f64,
+                            b: b as f64 / u8::MAX as f64,
+                            a: a as f64 / u8::MAX as f64,
+                        }),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.renderer.draw(&mut render_pass);
+        }
+
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                aspect: TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            TexelCopyBufferInfo {
+                buffer: &self.output_buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(
+                        self.screen_resolution.0 * self.texture.format().components() as u32,
+                    ),
+                    rows_per_image: Some(self.screen_resolution.1),
+                },
+            },
+            self.texture.size(),
+        );
+
+        self.queue.submit([encoder.finish()]);
+
+        let buffer_slice = self.output_buffer.slice(..);
+        let (tx, rx) = channel();
+        buffer_slice.map_async(MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(wgpu::MaintainBase::Wait);
+        rx.recv()
+            .map_err(|_| VideoExportError::BufferMap(None))?
+            .map_err(|e| VideoExportError::BufferMap(Some(e)))?;
+
+        Ok(buffer_slice.get_mapped_range())
+    }
+}
@@ -0,0 +1,54 @@
+//! Cancellation of running exports.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle to cancel an export of a [VideoExport][crate::VideoExport]
+/// (see [VideoExport::set_cancel_handle][crate::VideoExport::set_cancel_handle]).
+///
+/// Clones of this handle share the same state,
+/// so an export can be cancelled from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<CancelState>);
+
+/// The shared state of a [CancelHandle]
+#[derive(Debug, Default)]
+struct CancelState {
+    /// Whether the export was cancelled
+    cancelled: AtomicBool,
+    /// Whether the partial output should be deleted
+    delete_output: AtomicBool,
+}
+
+impl CancelHandle {
+    /// Creates a new [CancelHandle] which is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the export.
+    /// The partially written output is kept.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Cancels the export and deletes the partially written output
+    pub fn cancel_and_delete_output(&self) {
+        // Set before cancelling to be visible once the cancellation is observed
+        self.0.delete_output.store(true, Ordering::Relaxed);
+        self.0.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Whether the export was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Whether the partial output should be deleted
+    /// (i.e., the export was cancelled using [CancelHandle::cancel_and_delete_output])
+    pub fn delete_output(&self) -> bool {
+        self.is_cancelled() && self.0.delete_output.load(Ordering::Relaxed)
+    }
+}
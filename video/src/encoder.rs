@@ -0,0 +1,176 @@
+//! Settings for encoding videos using `ffmpeg`.
+
+/// Settings for the `ffmpeg`-encoder used by [VideoExport::export_video][crate::VideoExport::export_video]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EncoderSettings {
+    /// The codec to encode with (e.g., `libx264`),
+    /// or [None] to let `ffmpeg` choose the codec from the file-extension
+    pub codec: Option<String>,
+    /// The constant rate factor (`-crf`) to encode with
+    /// (lower is better; the range depends on the codec),
+    /// or [None] to use the default of the codec
+    pub quality: Option<u32>,
+    /// The pixel format to convert the frames to (e.g., `yuv420p`),
+    /// or [None] to let `ffmpeg` choose a pixel format supported by the codec
+    pub pixel_format: Option<String>,
+    /// Additional arguments passed to `ffmpeg` for the output (e.g., `["-preset", "slow"]`)
+    pub extra_args: Vec<String>,
+}
+
+impl Default for EncoderSettings {
+    fn default() -> Self {
+        EncoderPreset::Default.settings()
+    }
+}
+
+impl EncoderSettings {
+    /// Gets the output-arguments to pass to `ffmpeg` for these [EncoderSettings]
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(codec) = &self.codec {
+            args.extend(["-c:v".to_string(), codec.clone()]);
+        }
+        if let Some(quality) = self.quality {
+            args.extend(["-crf".to_string(), quality.to_string()]);
+        }
+        if let Some(pixel_format) = &self.pixel_format {
+            args.extend(["-vf".to_string(), format!("format={pixel_format}")]);
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+/// Presets for [EncoderSettings]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EncoderPreset {
+    /// The codec is chosen from the file-extension
+    /// and frames are converted to `yuv420p` for compatibility with most video players
+    #[default]
+    Default,
+    /// Lossless FFV1 (use an `mkv`-container)
+    Lossless,
+    /// High-quality H.265 (use an `mp4`-container)
+    H265,
+    /// High-quality VP9 (use a `webm`-container)
+    Vp9,
+    /// High-quality AV1 (use an `mp4`- or `webm`-container)
+    Av1,
+    /// ProRes 4444 with alpha (use a `mov`-container)
+    ProRes4444,
+    /// VP9 with alpha (use a `webm`-container)
+    WebmAlpha,
+}
+
+impl EncoderPreset {
+    /// All available [EncoderPreset]s
+    pub const ALL: [Self; 7] = [
+        Self::Default,
+        Self::Lossless,
+        Self::H265,
+        Self::Vp9,
+        Self::Av1,
+        Self::ProRes4444,
+        Self::WebmAlpha,
+    ];
+
+    /// Gets a human-readable name of this [EncoderPreset]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Lossless => "Lossless (FFV1)",
+            Self::H265 => "H.265",
+            Self::Vp9 => "VP9",
+            Self::Av1 => "AV1",
+            Self::ProRes4444 => "ProRes 4444 (alpha)",
+            Self::WebmAlpha => "WebM (alpha)",
+        }
+    }
+
+    /// Gets the file-extension of the container recommended for this [EncoderPreset],
+    /// or [None] if the container can be chosen freely
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Default => None,
+            Self::Lossless => Some("mkv"),
+            Self::H265 | Self::Av1 => Some("mp4"),
+            Self::Vp9 | Self::WebmAlpha => Some("webm"),
+            Self::ProRes4444 => Some("mov"),
+        }
+    }
+
+    /// Gets the [EncoderSettings] of this [EncoderPreset]
+    pub fn settings(self) -> EncoderSettings {
+        let settings =
+            |codec: Option<&str>, quality, pixel_format: Option<&str>, extra: &[&str]| {
+                EncoderSettings {
+                    codec: codec.map(str::to_string),
+                    quality,
+                    pixel_format: pixel_format.map(str::to_string),
+                    extra_args: extra.iter().map(|a| a.to_string()).collect(),
+                }
+            };
+        match self {
+            // Set chroma subsampling for some video players
+            // See https://trac.ffmpeg.org/wiki/Encode/H.264#Encodingfordumbplayers
+            // Should be ignored on formats that don't support it
+            Self::Default => settings(None, None, Some("yuv420p"), &[]),
+            // Keep the input pixel format (or the closest one supported by FFV1)
+            Self::Lossless => settings(Some("ffv1"), None, None, &["-level", "3"]),
+            // Tag as `hvc1` to be playable on Apple devices
+            Self::H265 => settings(
+                Some("libx265"),
+                Some(22),
+                Some("yuv420p"),
+                &["-tag:v", "hvc1"],
+            ),
+            // VP9 only uses constant quality when the bitrate is `0`
+            Self::Vp9 => settings(
+                Some("libvpx-vp9"),
+                Some(28),
+                Some("yuv420p"),
+                &["-b:v", "0"],
+            ),
+            Self::Av1 => settings(Some("libsvtav1"), Some(30), Some("yuv420p"), &[]),
+            Self::ProRes4444 => settings(
+                Some("prores_ks"),
+                None,
+                Some("yuva444p10le"),
+                &["-profile:v", "4444"],
+            ),
+            Self::WebmAlpha => settings(
+                Some("libvpx-vp9"),
+                Some(28),
+                Some("yuva420p"),
+                &["-b:v", "0"],
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_args() {
+        assert_eq!(EncoderSettings::default().args(), ["-vf", "format=yuv420p"]);
+    }
+
+    #[test]
+    fn all_args() {
+        let settings = EncoderSettings {
+            codec: Some("libx264".to_string()),
+            quality: Some(18),
+            pixel_format: None,
+            extra_args: vec!["-preset".to_string(), "slow".to_string()],
+        };
+        assert_eq!(
+            settings.args(),
+            ["-c:v", "libx264", "-crf", "18", "-preset", "slow"]
+        );
+    }
+}
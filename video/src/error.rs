@@ -0,0 +1,82 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    process::ExitStatus,
+    sync::Arc,
+};
+
+use wgpu::BufferAsyncError;
+
+/// A [Result][std::result::Result] pre-filled with [VideoExportError]
+pub type Result<T, E = VideoExportError> = std::result::Result<T, E>;
+
+/// An error that can occur during a [VideoExport][crate::VideoExport].
+///
+/// Can be cloned to be sent as [VideoProgress::Error][crate::VideoProgress::Error]
+/// while also being returned.
+#[derive(Debug, Clone)]
+pub enum VideoExportError {
+    /// No GPU-adapter is available,
+    /// but rendering on the GPU was requested
+    /// (see [RenderBackend::Gpu][crate::backend::RenderBackend::Gpu]).
+    NoAdapter,
+    /// Failed to read a rendered frame back from the GPU.
+    /// Contains [None] if the mapping never completed.
+    BufferMap(Option<BufferAsyncError>),
+    /// `ffmpeg` could not be started (e.g., because it is not installed)
+    FfmpegSpawn(Arc<io::Error>),
+    /// Frames could not be sent to `ffmpeg` (e.g., because `ffmpeg` exited early)
+    FfmpegWrite(Arc<io::Error>),
+    /// `ffmpeg` exited unsuccessfully
+    FfmpegExit(ExitStatus),
+    /// Writing the output failed
+    Io(Arc<io::Error>),
+    /// The export was cancelled using a [CancelHandle][crate::cancel::CancelHandle]
+    Cancelled,
+}
+
+impl VideoExportError {
+    /// Creates a [VideoExportError::FfmpegSpawn] from an [io::Error]
+    pub fn ffmpeg_spawn(error: io::Error) -> Self {
+        Self::FfmpegSpawn(Arc::new(error))
+    }
+
+    /// Creates a [VideoExportError::FfmpegWrite] from an [io::Error]
+    pub fn ffmpeg_write(error: io::Error) -> Self {
+        Self::FfmpegWrite(Arc::new(error))
+    }
+}
+
+impl From<io::Error> for VideoExportError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(Arc::new(value))
+    }
+}
+
+impl Display for VideoExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAdapter => f.write_str("no GPU adapter available"),
+            Self::BufferMap(Some(e)) => write!(f, "failed to read frame from GPU: {e}"),
+            Self::BufferMap(None) => f.write_str("failed to read frame from GPU"),
+            Self::FfmpegSpawn(e) => write!(f, "failed to run ffmpeg: {e}"),
+            Self::FfmpegWrite(e) => write!(f, "failed to send frame to ffmpeg: {e}"),
+            Self::FfmpegExit(status) => match status.code() {
+                Some(code) => write!(f, "ffmpeg exited with code {code}"),
+                None => f.write_str("ffmpeg exited abnormally"),
+            },
+            Self::Io(e) => write!(f, "failed to write output: {e}"),
+            Self::Cancelled => f.write_str("export was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for VideoExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::BufferMap(Some(e)) => Some(e),
+            Self::FfmpegSpawn(e) | Self::FfmpegWrite(e) | Self::Io(e) => Some(e.as_ref()),
+            Self::NoAdapter | Self::BufferMap(None) | Self::FfmpegExit(_) | Self::Cancelled => None,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Encoding of rendered frames into image-files.
+
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use png::{BitDepth, ColorType, Encoder};
+
+/// Encodes the passed tightly packed RGBA-`data` of the passed `size` as a PNG
+/// and writes it to the `target`-path.
+pub(crate) fn write_png(target: &Path, (width, height): (u32, u32), data: &[u8]) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(target)?);
+    let mut encoder = Encoder::new(writer, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Converts the passed tightly packed RGBA-`data` from premultiplied to straight alpha.
+/// Fully transparent pixels stay black.
+pub(crate) fn unpremultiply(data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u16;
+        if alpha == 0 || alpha == u8::MAX as u16 {
+            continue;
+        }
+        for channel in &mut pixel[..3] {
+            let straight = (*channel as u16 * u8::MAX as u16 + alpha / 2) / alpha;
+            *channel = straight.min(u8::MAX as u16) as u8;
+        }
+    }
+    data
+}
+
+/// Gets the file-name of the frame with the passed `index`
+/// for a sequence of `count` frames.
+/// The index is zero-padded such that all frames sort by their index.
+pub(crate) fn sequence_file_name(index: usize, count: usize) -> String {
+    let width = count.saturating_sub(1).max(1).ilog10() as usize + 1;
+    format!("{index:0width$}.png")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sequence_file_names_are_padded() {
+        assert_eq!(sequence_file_name(0, 1), "0.png");
+        assert_eq!(sequence_file_name(3, 10), "3.png");
+        assert_eq!(sequence_file_name(3, 11), "03.png");
+        assert_eq!(sequence_file_name(42, 1000), "042.png");
+    }
+
+    #[test]
+    fn unpremultiply_alpha() {
+        assert_eq!(
+            unpremultiply(&[64, 32, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255]),
+            [128, 64, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255]
+        );
+    }
+}
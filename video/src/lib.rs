@@ -1,141 +1,186 @@
 use std::{
+    fs,
     io::{BufRead, BufReader, Write},
     path::Path,
     process::{Command, ExitStatus, Stdio},
-    sync::mpsc::{channel, Sender},
+    sync::mpsc::Sender,
     thread,
 };
 
 use naviz_animator::animator::Animator;
-use naviz_renderer::renderer::Renderer;
-use wgpu::{
-    Buffer, BufferView, Color, CommandEncoderDescriptor, Device, DeviceDescriptor, Extent3d,
-    Features, Instance, InstanceDescriptor, Limits, LoadOp, MapMode, MemoryHints, Operations,
-    Queue, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TexelCopyBufferInfo,
-    TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsages,
-};
+
+pub mod animation;
+pub mod backend;
+pub mod cancel;
+pub mod encoder;
+pub mod error;
+mod image;
+pub mod time;
+
+use animation::{AnimationEncoder, AnimationFormat, AnimationOptions};
+use backend::{Backend, RenderBackend};
+use cancel::CancelHandle;
+use encoder::EncoderSettings;
+use error::{Result, VideoExportError};
+use time::TimeOptions;
 
 /// Struct to export a video from an [Animator]
 pub struct VideoExport {
     animator: Animator,
-    renderer: Renderer,
-    texture: Texture,
-    device: Device,
-    queue: Queue,
-    output_buffer: Buffer,
+    backend: Backend,
     fps: u32,
     screen_resolution: (u32, u32),
+    time: TimeOptions,
+    encoder: EncoderSettings,
+    transparent: bool,
+    cancel: CancelHandle,
 }
 
-/// Video progress update event
+/// Video progress update event.
+/// Times are in seconds of the exported video.
 pub enum VideoProgress {
     /// Render-update (`current time`, `duration`)
     Render(f32, f32),
     /// Encode-update (`current time`, `duration`)
     Encode(f32, f32),
-    /// Finished export (with `ffmpeg` exit status; may not always be successful)
+    /// Finished export successfully
+    /// (with the `ffmpeg` exit status, or a default status if `ffmpeg` was not used)
     Done(ExitStatus),
-}
-
-/// Creates a headless rendering [Device] and [Queue]
-async fn create_device() -> (Device, Queue) {
-    let instance = Instance::new(&InstanceDescriptor::default());
-    let adapter = instance
-        .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            compatible_surface: None,
-            force_fallback_adapter: false,
-        })
-        .await
-        .expect("No adapter");
-    adapter
-        .request_device(
-            &DeviceDescriptor {
-                label: Some("naviz video renderer"),
-                required_features: Features::default(),
-                required_limits: Limits::default(),
-                memory_hints: MemoryHints::default(),
-            },
-            None,
-        )
-        .await
-        .expect("Failed to create device")
-}
-
-/// Creates an output-buffer on the passed [Device] for the specified `screen_resolution` and `pixel_size`.
-fn create_output_buffer(device: &Device, screen_resolution: (u32, u32), pixel_size: u32) -> Buffer {
-    let output_buffer_size =
-        (pixel_size * screen_resolution.0 * screen_resolution.1) as wgpu::BufferAddress;
-    let output_buffer_desc = wgpu::BufferDescriptor {
-        size: output_buffer_size,
-        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-        label: Some("naviz output buffer"),
-        mapped_at_creation: false,
-    };
-    device.create_buffer(&output_buffer_desc)
+    /// Export failed with the contained error
+    Error(VideoExportError),
 }
 
 impl VideoExport {
     /// Creates a new [VideoExport] from the passed [Animator]
-    /// and with the passed `screen_resolution` and `fps`
-    pub async fn new(animator: Animator, screen_resolution: (u32, u32), fps: u32) -> Self {
-        let (device, queue) = create_device().await;
-        let texture_format = TextureFormat::Rgba8Unorm;
-        let texture = device.create_texture(&TextureDescriptor {
-            label: Some("naviz render target"),
-            size: Extent3d {
-                width: screen_resolution.0,
-                height: screen_resolution.1,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: texture_format,
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
-            view_formats: &[],
-        });
-
-        let renderer = Renderer::new(
-            &device,
-            &queue,
-            texture_format,
-            &animator.config(),
-            &animator.state((0.).into()),
-            screen_resolution,
-        );
+    /// and with the passed `screen_resolution` and `fps`.
+    /// Will render on the GPU if available and fall back to rendering on the CPU otherwise
+    /// (see [RenderBackend::Auto]).
+    pub async fn new(animator: Animator, screen_resolution: (u32, u32), fps: u32) -> Result<Self> {
+        Self::new_with_backend(animator, screen_resolution, fps, RenderBackend::Auto).await
+    }
 
-        let output_buffer = create_output_buffer(
-            &device,
-            screen_resolution,
-            texture_format.components() as u32,
-        );
+    /// Creates a new [VideoExport] from the passed [Animator]
+    /// and with the passed `screen_resolution` and `fps`,
+    /// which renders using the passed [RenderBackend].
+    ///
+    /// Returns [VideoExportError::NoAdapter]
+    /// if [RenderBackend::Gpu] is requested but no GPU is available.
+    pub async fn new_with_backend(
+        animator: Animator,
+        screen_resolution: (u32, u32),
+        fps: u32,
+        backend: RenderBackend,
+    ) -> Result<Self> {
+        let backend = Backend::new(backend, &animator, screen_resolution).await?;
 
-        Self {
+        Ok(Self {
             animator,
-            renderer,
-            texture,
-            device,
-            queue,
-            output_buffer,
+            backend,
             fps,
             screen_resolution,
+            time: Default::default(),
+            encoder: Default::default(),
+            transparent: false,
+            cancel: CancelHandle::new(),
+        })
+    }
+
+    /// Sets the [TimeOptions] for the exports of this [VideoExport]
+    /// (i.e., which part of the animation is exported, at which speed,
+    /// and how long the first and last frame are held).
+    /// Single frames (see [VideoExport::export_frame]) are not affected.
+    pub fn set_time_options(&mut self, time: TimeOptions) {
+        self.time = time;
+    }
+
+    /// Sets the [EncoderSettings] used by [VideoExport::export_video]
+    pub fn set_encoder_settings(&mut self, encoder: EncoderSettings) {
+        self.encoder = encoder;
+    }
+
+    /// Sets whether frames are rendered with a transparent background
+    /// instead of the background of the [Animator].
+    /// Transparent frames have straight alpha.
+    /// The alpha is only kept by formats supporting it
+    /// (e.g., image-sequences, animations, and videos encoded with an alpha-capable
+    /// [EncoderPreset][encoder::EncoderPreset]).
+    pub fn set_transparent_background(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    /// Gets the background to render frames on,
+    /// or [None] if frames are rendered with a transparent background
+    fn background(&self) -> Option<[u8; 4]> {
+        (!self.transparent).then(|| self.animator.background())
+    }
+
+    /// Sets the [CancelHandle] which can cancel the exports of this [VideoExport].
+    /// A cancelled export stops rendering, terminates `ffmpeg` if it is running,
+    /// and returns [VideoExportError::Cancelled].
+    pub fn set_cancel_handle(&mut self, cancel: CancelHandle) {
+        self.cancel = cancel;
+    }
+
+    /// Gets the [CancelHandle] which can cancel the exports of this [VideoExport]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Returns [VideoExportError::Cancelled] if the export was cancelled
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            Err(VideoExportError::Cancelled)
+        } else {
+            Ok(())
         }
     }
 
-    /// Gets the frame times for the duration of the [Animator] and the set `fps`.
-    fn get_frame_times(&self) -> impl Iterator<Item = f32> {
-        let frame_count: u64 = (self.animator.duration() * self.fps)
-            .ceil()
-            .try_into()
-            .unwrap();
-        let fps = self.fps;
-        (0..=frame_count).map(move |i| i as f32 * (1. / fps as f32))
+    /// Whether the partial output of the export with the passed `result` should be deleted
+    /// (see [CancelHandle::cancel_and_delete_output])
+    fn delete_partial_output(&self, result: &Result<ExitStatus>) -> bool {
+        matches!(result, Err(VideoExportError::Cancelled)) && self.cancel.delete_output()
     }
 
-    /// Exports a video the the specified `target`-path using system-installed `ffmpeg`
-    pub fn export_video(&mut self, target: &Path, progress: Sender<VideoProgress>) {
+    /// The [RenderBackend] this [VideoExport] renders with
+    /// (i.e., which backend was chosen when [RenderBackend::Auto] was requested).
+    pub fn backend(&self) -> RenderBackend {
+        self.backend.kind()
+    }
+
+    /// Gets the animation-times of all frames
+    /// for the duration of the [Animator], the set `fps`, and the set [TimeOptions].
+    fn get_frame_times(&self) -> Vec<f32> {
+        self.time
+            .frame_times(self.animator.duration().try_into().unwrap(), self.fps)
+    }
+
+    /// Gets the time in the exported video after the frame with the passed `index` was shown
+    fn video_time(&self, index: usize) -> f32 {
+        (index + 1) as f32 / self.fps as f32
+    }
+
+    /// Exports a video the the specified `target`-path using system-installed `ffmpeg`.
+    /// The video is encoded using the set [EncoderSettings].
+    ///
+    /// Sends [VideoProgress::Render] after each rendered frame
+    /// and [VideoProgress::Encode] whenever `ffmpeg` reports its progress.
+    /// Once `ffmpeg` finished successfully, [VideoProgress::Done] is sent.
+    /// If the export fails, [VideoProgress::Error] is sent and the error is returned.
+    pub fn export_video(&mut self, target: &Path, progress: Sender<VideoProgress>) -> Result<()> {
+        let result = self.export_video_inner(target, &progress);
+        if self.delete_partial_output(&result) {
+            let _ = fs::remove_file(target);
+        }
+        report(result, &progress)
+    }
+
+    /// Exports a video (see [VideoExport::export_video]).
+    /// Returns the successful exit status of `ffmpeg`.
+    fn export_video_inner(
+        &mut self,
+        target: &Path,
+        progress: &Sender<VideoProgress>,
+    ) -> Result<ExitStatus> {
         let mut ffmpeg = Command::new("ffmpeg")
             .args([
                 "-progress",
@@ -150,18 +195,15 @@ impl VideoExport {
                 format!("{}/1", self.fps).as_str(),
                 "-i",
                 "-",
-                // Set chroma subsampling for some video players
-                // See https://trac.ffmpeg.org/wiki/Encode/H.264#Encodingfordumbplayers
-                // Should be ignored on formats that don't support it
-                "-vf",
-                "format=yuv420p",
-                "-y",
             ])
+            .args(self.encoder.args())
+            .arg("-y")
             .arg(target)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .spawn()
-            .expect("Failed to run ffmpeg");
+            .map_err(VideoExportError::ffmpeg_spawn)?;
+        // Both are piped and therefore always available
         let mut ffmpeg_input = ffmpeg.stdin.take().unwrap();
         let ffmpeg_progress = ffmpeg.stdout.take().unwrap();
         let ffmpeg_progress = BufReader::new(ffmpeg_progress)
@@ -171,7 +213,7 @@ impl VideoExport {
             .filter_map(Result::ok)
             .map(|ms| ms as f32 / 1_000_000.);
 
-        let duration = self.animator.duration().try_into().unwrap();
+        let duration = self.video_time(self.get_frame_times().len() - 1);
 
         let encode_progress = progress.clone();
         thread::spawn(move || {
@@ -180,98 +222,350 @@ impl VideoExport {
             });
         });
 
-        self.get_frame_times().for_each(|time| {
+        let written = self.write_frames(&mut ffmpeg_input, progress);
+        // Closing the input signals the end of the video to ffmpeg
+        drop(ffmpeg_input);
+
+        if let Err(error) = written {
+            if matches!(error, VideoExportError::FfmpegWrite(_)) {
+                // ffmpeg stopped reading the frames, which is most likely because it failed
+                if let Ok(status) = ffmpeg.wait() {
+                    if !status.success() {
+                        return Err(VideoExportError::FfmpegExit(status));
+                    }
+                }
+            } else {
+                let _ = ffmpeg.kill();
+                let _ = ffmpeg.wait();
+            }
+            return Err(error);
+        }
+
+        let status = ffmpeg.wait()?;
+        if !status.success() {
+            return Err(VideoExportError::FfmpegExit(status));
+        }
+        Ok(status)
+    }
+
+    /// Renders all frames and writes them to `target` as raw RGBA-data.
+    /// Sends [VideoProgress::Render] after each written frame.
+    fn write_frames(
+        &mut self,
+        target: &mut impl Write,
+        progress: &Sender<VideoProgress>,
+    ) -> Result<()> {
+        let background = self.background();
+        let frame_times = self.get_frame_times();
+        let duration = self.video_time(frame_times.len() - 1);
+        for (index, time) in frame_times.into_iter().enumerate() {
+            self.check_cancelled()?;
             self.set_time(time);
-            let frame = self.get_frame();
-            ffmpeg_input
+            let frame = self.backend.get_frame(background)?;
+            target
                 .write_all(&frame)
-                .expect("Failed to send frame to ffmpeg");
+                .map_err(VideoExportError::ffmpeg_write)?;
             drop(frame);
-            self.output_buffer.unmap();
-            let _ = progress.send(VideoProgress::Render(time, duration));
-        });
-        ffmpeg_input
-            .flush()
-            .expect("Failed to flush frames to ffmpeg");
+            let _ = progress.send(VideoProgress::Render(self.video_time(index), duration));
+        }
+        target.flush().map_err(VideoExportError::ffmpeg_write)
+    }
 
-        drop(ffmpeg_input);
+    /// Exports each frame as a numbered PNG-image into the specified `target`-directory
+    /// (which will be created if it does not exist).
+    /// Does not require `ffmpeg`.
+    ///
+    /// Frames are named by their index, zero-padded to the same length (e.g., `007.png`).
+    /// Once all frames are written, [VideoProgress::Done] is sent with a successful status.
+    /// If the export fails, [VideoProgress::Error] is sent and the error is returned.
+    pub fn export_image_sequence(
+        &mut self,
+        target: &Path,
+        progress: Sender<VideoProgress>,
+    ) -> Result<()> {
+        let result = self.export_image_sequence_inner(target, &progress);
+        if self.delete_partial_output(&result) {
+            // Only delete the frames, as the directory may have already contained other files
+            let frame_count = self.get_frame_times().len();
+            for index in 0..frame_count {
+                let _ = fs::remove_file(target.join(image::sequence_file_name(index, frame_count)));
+            }
+            // Only succeeds if the directory is empty
+            let _ = fs::remove_dir(target);
+        }
+        report(result, &progress)
+    }
+
+    /// Exports an image sequence (see [VideoExport::export_image_sequence])
+    fn export_image_sequence_inner(
+        &mut self,
+        target: &Path,
+        progress: &Sender<VideoProgress>,
+    ) -> Result<ExitStatus> {
+        fs::create_dir_all(target)?;
 
-        if let Ok(code) = ffmpeg.wait() {
-            let _ = progress.send(VideoProgress::Done(code));
+        let background = self.background();
+        let frame_times = self.get_frame_times();
+        let frame_count = frame_times.len();
+        let duration = self.video_time(frame_count - 1);
+        for (index, time) in frame_times.into_iter().enumerate() {
+            self.check_cancelled()?;
+            self.set_time(time);
+            let frame = self.backend.get_frame(background)?;
+            image::write_png(
+                &target.join(image::sequence_file_name(index, frame_count)),
+                self.screen_resolution,
+                &frame,
+            )?;
+            drop(frame);
+            let _ = progress.send(VideoProgress::Render(self.video_time(index), duration));
         }
+
+        Ok(ExitStatus::default())
     }
 
-    /// Updates the [Renderer] to have the state of the [Animator] at the passed `time`
+    /// Exports an animated image (GIF or APNG, see [AnimationFormat])
+    /// to the specified `target`-path.
+    /// Does not require `ffmpeg`.
+    ///
+    /// Sends [VideoProgress::Render] after each rendered frame
+    /// and [VideoProgress::Encode] after each encoded frame.
+    /// Once the animation is written, [VideoProgress::Done] is sent with a successful status.
+    /// If the export fails, [VideoProgress::Error] is sent and the error is returned.
+    pub fn export_animation(
+        &mut self,
+        target: &Path,
+        format: AnimationFormat,
+        options: AnimationOptions,
+        progress: Sender<VideoProgress>,
+    ) -> Result<()> {
+        let result = self.export_animation_inner(target, format, options, &progress);
+        if self.delete_partial_output(&result) {
+            let _ = fs::remove_file(target);
+        }
+        report(result, &progress)
+    }
+
+    /// Exports an animated image (see [VideoExport::export_animation])
+    fn export_animation_inner(
+        &mut self,
+        target: &Path,
+        format: AnimationFormat,
+        AnimationOptions {
+            palette,
+            frame_step,
+        }: AnimationOptions,
+        progress: &Sender<VideoProgress>,
+    ) -> Result<ExitStatus> {
+        let frame_step = frame_step.max(1);
+        let frame_times = self.get_frame_times();
+        let duration = self.video_time(frame_times.len() - 1);
+        // Keep the index of each frame in the full video
+        let frame_times: Vec<_> = frame_times
+            .into_iter()
+            .enumerate()
+            .step_by(frame_step as usize)
+            .collect();
+
+        let mut encoder = AnimationEncoder::new(
+            target,
+            format,
+            palette,
+            self.screen_resolution,
+            frame_times.len() as u32,
+            self.fps as f32 / frame_step as f32,
+        )?;
+
+        let background = self.background();
+        for (index, time) in frame_times {
+            self.check_cancelled()?;
+            self.set_time(time);
+            let video_time = self.video_time(index);
+            let frame = self.backend.get_frame(background)?;
+            let _ = progress.send(VideoProgress::Render(video_time, duration));
+            encoder.write_frame(&frame)?;
+            drop(frame);
+            let _ = progress.send(VideoProgress::Encode(video_time, duration));
+        }
+        encoder.finish()?;
+
+        Ok(ExitStatus::default())
+    }
+
+    /// Exports the single frame at the passed animation-`time` as a PNG-image
+    /// to the specified `target`-path.
+    /// Does not require `ffmpeg`.
+    pub fn export_frame(&mut self, time: f32, target: &Path) -> Result<()> {
+        self.set_time(time);
+        let background = self.background();
+        let frame = self.backend.get_frame(background)?;
+        Ok(image::write_png(target, self.screen_resolution, &frame)?)
+    }
+
+    /// Updates the [Backend] to have the state of the [Animator] at the passed `time`
     fn set_time(&mut self, time: f32) {
-        self.renderer.update(
-            &mut (&self.device, &self.queue),
-            &self.device,
-            &self.queue,
-            &self.animator.config(),
-            &self.animator.state(time.into()),
-        );
+        self.backend
+            .update(&self.animator.config(), &self.animator.state(time.into()));
     }
+}
 
-    /// Renders the current frame and gets the resulting data as a [BufferView].
-    /// [Self::output_buffer] will need to be [unmapped][Buffer::unmap] after the [BufferView] was used.
-    fn get_frame(&self) -> BufferView<'_> {
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
-        {
-            let [r, g, b, a] = self.animator.background();
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &self.texture.create_view(&Default::default()),
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color {
-                            r: r as f64 / u8::MAX as f64,
-                            g: g as f64 / u8::MAX as f64,
-                            b: b as f64 / u8::MAX as f64,
-                            a: a as f64 / u8::MAX as f64,
-                        }),
-                        store: StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            self.renderer.draw(&mut render_pass);
+/// Reports the `result` of an export over the `progress`-channel:
+/// Sends [VideoProgress::Done] on success and [VideoProgress::Error] on failure.
+fn report(result: Result<ExitStatus>, progress: &Sender<VideoProgress>) -> Result<()> {
+    match result {
+        Ok(status) => {
+            let _ = progress.send(VideoProgress::Done(status));
+            Ok(())
+        }
+        Err(error) => {
+            let _ = progress.send(VideoProgress::Error(error.clone()));
+            Err(error)
         }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc::{channel, Receiver};
+
+    use naviz_parser::input::{concrete::Instructions, lexer, parser};
+    use naviz_repository::Repository;
+
+    use super::*;
+
+    /// Creates a [VideoExport] of a short example which renders on the CPU
+    fn example() -> VideoExport {
+        let machine = Repository::empty()
+            .bundled_machines()
+            .expect("Failed to load bundled machines")
+            .get("example")
+            .expect("Machine does not exist")
+            .expect("Failed to load machine");
+        let style = Repository::empty()
+            .bundled_styles()
+            .expect("Failed to load bundled styles")
+            .get("tum")
+            .expect("Style does not exist")
+            .expect("Failed to load style");
+        let input = "
+            atom (0, 0) atom0
+            @0 load atom0
+            @+ move (10, 10) atom0
+            @+ store atom0
+        ";
+        let lexed = lexer::lex(input).expect("Failed to lex");
+        let parsed = parser::parse(&lexed).expect("Failed to parse");
+        let instructions = Instructions::new(parsed).expect("Failed to convert");
+        let animator = Animator::new(machine, style, instructions).expect("Failed to animate");
+        futures::executor::block_on(VideoExport::new_with_backend(
+            animator,
+            (64, 36),
+            5,
+            RenderBackend::Software,
+        ))
+        .expect("Failed to create export")
+    }
 
-        encoder.copy_texture_to_buffer(
-            TexelCopyTextureInfo {
-                aspect: TextureAspect::All,
-                texture: &self.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            TexelCopyBufferInfo {
-                buffer: &self.output_buffer,
-                layout: TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(
-                        self.screen_resolution.0 * self.texture.format().components() as u32,
-                    ),
-                    rows_per_image: Some(self.screen_resolution.1),
-                },
-            },
-            self.texture.size(),
+    /// The last [VideoProgress] which was sent over the `receiver`
+    fn last_progress(receiver: &Receiver<VideoProgress>) -> Option<VideoProgress> {
+        receiver.try_iter().last()
+    }
+
+    #[test]
+    fn video_error_is_reported() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        // ffmpeg cannot write into a missing directory (or is not installed at all)
+        let target = dir.path().join("missing").join("video.mp4");
+        let (sender, receiver) = channel();
+
+        let result = example().export_video(&target, sender);
+
+        assert!(
+            matches!(
+                result,
+                Err(VideoExportError::FfmpegSpawn(_)
+                    | VideoExportError::FfmpegWrite(_)
+                    | VideoExportError::FfmpegExit(_))
+            ),
+            "Unexpected result: {result:?}"
         );
+        assert!(matches!(
+            last_progress(&receiver),
+            Some(VideoProgress::Error(_))
+        ));
+        assert!(!target.exists());
+    }
 
-        self.queue.submit([encoder.finish()]);
+    #[test]
+    fn animation_error_is_reported() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let target = dir.path().join("missing").join("animation.gif");
+        let (sender, receiver) = channel();
 
-        let buffer_slice = self.output_buffer.slice(..);
-        let (tx, rx) = channel();
-        buffer_slice.map_async(MapMode::Read, move |result| {
-            tx.send(result).unwrap();
-        });
-        self.device.poll(wgpu::MaintainBase::Wait);
-        rx.recv().unwrap().unwrap();
+        let result =
+            example().export_animation(&target, AnimationFormat::Gif, Default::default(), sender);
+
+        assert!(matches!(result, Err(VideoExportError::Io(_))));
+        assert!(matches!(
+            last_progress(&receiver),
+            Some(VideoProgress::Error(VideoExportError::Io(_)))
+        ));
+    }
+
+    #[test]
+    fn frame_error_is_returned() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let target = dir.path().join("missing").join("frame.png");
+
+        assert!(matches!(
+            example().export_frame(0., &target),
+            Err(VideoExportError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn cancelled_export_deletes_output() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let target = dir.path().join("frames");
+        let mut export = example();
+        export.cancel_handle().cancel_and_delete_output();
+        let (sender, receiver) = channel();
+
+        let result = export.export_image_sequence(&target, sender);
+
+        assert!(matches!(result, Err(VideoExportError::Cancelled)));
+        let progress: Vec<_> = receiver.try_iter().collect();
+        assert!(
+            !progress
+                .iter()
+                .any(|p| matches!(p, VideoProgress::Render(..))),
+            "Rendered after cancelling"
+        );
+        assert!(matches!(
+            progress.last(),
+            Some(VideoProgress::Error(VideoExportError::Cancelled))
+        ));
+        assert!(!target.exists(), "Output was not deleted");
+    }
+
+    #[test]
+    fn cancelled_export_keeps_output() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let target = dir.path().join("animation.gif");
+        let mut export = example();
+        let cancel = CancelHandle::new();
+        export.set_cancel_handle(cancel.clone());
+        cancel.cancel();
+        let (sender, receiver) = channel();
+
+        let result =
+            export.export_animation(&target, AnimationFormat::Gif, Default::default(), sender);
 
-        buffer_slice.get_mapped_range()
+        assert!(matches!(result, Err(VideoExportError::Cancelled)));
+        assert!(matches!(
+            last_progress(&receiver),
+            Some(VideoProgress::Error(VideoExportError::Cancelled))
+        ));
+        assert!(target.exists(), "Output was deleted");
     }
 }
@@ -0,0 +1,94 @@
+//! Options for the part of the animation to export and its speed.
+
+use std::iter;
+
+/// Options for which part of the animation is exported,
+/// how animation-time maps to video-time,
+/// and how long the first and last frame are held.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TimeOptions {
+    /// The animation-time to start the export at
+    pub start: f32,
+    /// The animation-time to end the export at,
+    /// or [None] to export until the end of the animation
+    pub end: Option<f32>,
+    /// Seconds of video per unit of animation-time
+    /// (e.g., `0.5` to show one unit of animation-time in half a second)
+    pub scale: f32,
+    /// Seconds to hold the first frame for before the animation starts
+    pub hold_start: f32,
+    /// Seconds to hold the last frame for after the animation ended
+    pub hold_end: f32,
+}
+
+impl Default for TimeOptions {
+    fn default() -> Self {
+        Self {
+            start: 0.,
+            end: None,
+            scale: 1.,
+            hold_start: 0.,
+            hold_end: 0.,
+        }
+    }
+}
+
+impl TimeOptions {
+    /// Gets the animation-times of all frames of a video with the passed `fps`
+    /// for an animation with the passed `duration`.
+    ///
+    /// The exported range is clamped to the animation.
+    /// A non-positive `scale` is treated as `1`.
+    pub(crate) fn frame_times(&self, duration: f32, fps: u32) -> Vec<f32> {
+        let start = self.start.clamp(0., duration);
+        let end = self.end.unwrap_or(duration).clamp(start, duration);
+        let scale = if self.scale > 0. { self.scale } else { 1. };
+        let fps = fps as f32;
+
+        let hold_frames = |seconds: f32| (seconds.max(0.) * fps).round() as usize;
+        let frame_count = ((end - start) * scale * fps).ceil() as usize;
+        let step = 1. / (fps * scale);
+
+        iter::repeat_n(start, hold_frames(self.hold_start))
+            .chain((0..=frame_count).map(|i| (start + i as f32 * step).min(end)))
+            .chain(iter::repeat_n(end, hold_frames(self.hold_end)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_exports_full_animation() {
+        assert_eq!(
+            TimeOptions::default().frame_times(1., 4),
+            [0., 0.25, 0.5, 0.75, 1.]
+        );
+    }
+
+    #[test]
+    fn range_scale_and_hold() {
+        let options = TimeOptions {
+            start: 1.,
+            end: Some(2.),
+            scale: 0.5,
+            hold_start: 0.5,
+            hold_end: 0.25,
+        };
+        assert_eq!(options.frame_times(10., 4), [1., 1., 1., 1.5, 2., 2.]);
+    }
+
+    #[test]
+    fn range_is_clamped() {
+        let options = TimeOptions {
+            start: -1.,
+            end: Some(5.),
+            ..Default::default()
+        };
+        assert_eq!(options.frame_times(1., 2), [0., 0.5, 1.]);
+    }
+}